@@ -3,7 +3,12 @@
 extern crate test;
 
 extern crate avro_rs;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+
 use avro_rs::schema::Schema;
+use avro_rs::{from_value, to_value};
 use avro_rs::types::{Record, ToAvro, Value};
 use avro_rs::Reader;
 use avro_rs::Writer;
@@ -89,6 +94,37 @@ static RAW_BIG_SCHEMA: &'static str = r#"
 }
 "#;
 
+// Reads `RAW_BIG_SCHEMA` data with fields removed, added, reordered and promoted.
+static RAW_BIG_READER_SCHEMA: &'static str = r#"
+{
+  "namespace": "my.example",
+  "type": "record",
+  "name": "userInfo",
+  "fields": [
+    {
+      "default": -1,
+      "type": "long",
+      "name": "age"
+    },
+    {
+      "default": "NONE",
+      "type": "string",
+      "name": "username"
+    },
+    {
+      "default": "NONE",
+      "type": "string",
+      "name": "email"
+    },
+    {
+      "default": "NONE",
+      "type": "bytes",
+      "name": "housenum"
+    }
+  ]
+}
+"#;
+
 static RAW_ADDRESS_SCHEMA: &'static str = r#"
 {
   "fields": [
@@ -156,6 +192,42 @@ fn make_big_record() -> (Schema, Value) {
     (big_schema, big_record)
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+struct MailingAddress {
+    street: String,
+    city: String,
+    state_prov: String,
+    country: String,
+    zip: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct UserInfo {
+    username: String,
+    age: i32,
+    phone: String,
+    housenum: String,
+    address: MailingAddress,
+}
+
+fn make_big_struct() -> (Schema, UserInfo) {
+    let big_schema = Schema::parse_str(RAW_BIG_SCHEMA).unwrap();
+    let big_struct = UserInfo {
+        username: "username".to_owned(),
+        age: 10,
+        phone: "000000000".to_owned(),
+        housenum: "0000".to_owned(),
+        address: MailingAddress {
+            street: "street".to_owned(),
+            city: "city".to_owned(),
+            state_prov: "state_prov".to_owned(),
+            country: "country".to_owned(),
+            zip: "zip".to_owned(),
+        },
+    };
+    (big_schema, big_struct)
+}
+
 fn make_records(record: &Value, count: usize) -> Vec<Value> {
     let mut records = Vec::new();
     for _ in 0..count {
@@ -201,6 +273,90 @@ fn bench_read(b: &mut test::Bencher, make_record: &Fn() -> (Schema, Value), n_re
     b.iter(|| read(&schema, &bytes));
 }
 
+fn bench_resolve(
+    b: &mut test::Bencher,
+    make_record: &Fn() -> (Schema, Value),
+    reader_schema: &str,
+    n_records: usize,
+) {
+    let (schema, record) = make_record();
+    let reader_schema = Schema::parse_str(reader_schema).unwrap();
+    let records = make_records(&record, n_records);
+    let bytes = write(&schema, &records);
+    b.iter(|| read(&reader_schema, &bytes));
+}
+
+fn bench_write_ser<T: serde::Serialize>(
+    b: &mut test::Bencher,
+    make_struct: &Fn() -> (Schema, T),
+    n_records: usize,
+) {
+    let (schema, value) = make_struct();
+    b.iter(|| {
+        let mut writer = Writer::new(&schema, Vec::new());
+        for _ in 0..n_records {
+            writer.append_ser(&value).unwrap();
+        }
+        writer.flush().unwrap();
+        writer.into_inner()
+    });
+}
+
+// Writes structs the way `Writer::append_ser` used to: converted to a `Value`, then validated
+// and encoded.
+fn bench_write_to_value<T: serde::Serialize>(
+    b: &mut test::Bencher,
+    make_struct: &Fn() -> (Schema, T),
+    n_records: usize,
+) {
+    let (schema, value) = make_struct();
+    b.iter(|| {
+        let mut writer = Writer::new(&schema, Vec::new());
+        for _ in 0..n_records {
+            writer.append(to_value(&value).unwrap()).unwrap();
+        }
+        writer.flush().unwrap();
+        writer.into_inner()
+    });
+}
+
+fn write_ser<T: serde::Serialize>(schema: &Schema, value: &T, n_records: usize) -> Vec<u8> {
+    let mut writer = Writer::new(&schema, Vec::new());
+    for _ in 0..n_records {
+        writer.append_ser(value).unwrap();
+    }
+    writer.into_inner()
+}
+
+fn bench_read_typed<T: serde::Serialize + serde::de::DeserializeOwned>(
+    b: &mut test::Bencher,
+    make_struct: &Fn() -> (Schema, T),
+    n_records: usize,
+) {
+    let (schema, value) = make_struct();
+    let bytes = write_ser(&schema, &value, n_records);
+    b.iter(|| {
+        for value in Reader::new(&bytes[..]).unwrap().into_typed::<T>() {
+            let _ = value.unwrap();
+        }
+    });
+}
+
+// Reads structs the way they used to be read: decoded into a `Value`, then deserialized.
+fn bench_read_from_value<T: serde::Serialize + serde::de::DeserializeOwned>(
+    b: &mut test::Bencher,
+    make_struct: &Fn() -> (Schema, T),
+    n_records: usize,
+) {
+    let (schema, value) = make_struct();
+    let bytes = write_ser(&schema, &value, n_records);
+    b.iter(|| {
+        for value in Reader::new(&bytes[..]).unwrap() {
+            let _ = from_value::<T>(&value.unwrap()).unwrap();
+        }
+    });
+}
+
 fn bench_from_file(b: &mut test::Bencher, file_path: &str) {
     use std::fs;
     let bytes = fs::read(file_path).unwrap();
@@ -253,6 +409,16 @@ fn bench_big_schema_write_10000_record(b: &mut test::Bencher) {
     bench_write(b, &make_big_record, 10000);
 }
 
+#[bench]
+fn bench_big_schema_write_ser_10000_record(b: &mut test::Bencher) {
+    bench_write_ser(b, &make_big_struct, 10000);
+}
+
+#[bench]
+fn bench_big_schema_write_to_value_10000_record(b: &mut test::Bencher) {
+    bench_write_to_value(b, &make_big_struct, 10000);
+}
+
 #[bench]
 fn bench_big_schema_read_1_record(b: &mut test::Bencher) {
     bench_read(b, &make_big_record, 1);
@@ -273,6 +439,21 @@ fn bench_big_schema_read_100000_record(b: &mut test::Bencher) {
     bench_read(b, &make_big_record, 100000);
 }
 
+#[bench]
+fn bench_big_schema_read_typed_10000_record(b: &mut test::Bencher) {
+    bench_read_typed(b, &make_big_struct, 10000);
+}
+
+#[bench]
+fn bench_big_schema_read_from_value_10000_record(b: &mut test::Bencher) {
+    bench_read_from_value(b, &make_big_struct, 10000);
+}
+
+#[bench]
+fn bench_big_schema_resolve_10000_record(b: &mut test::Bencher) {
+    bench_resolve(b, &make_big_record, RAW_BIG_READER_SCHEMA, 10000);
+}
+
 // This benchmark reads from the `benches/quickstop-null.avro` file, which was pulled from
 // the `goavro` project benchmarks:
 // https://github.com/linkedin/goavro/blob/master/fixtures/quickstop-null.avro
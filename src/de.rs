@@ -5,9 +5,13 @@ use std::error::{self, Error as StdError};
 use std::fmt;
 use std::slice::Iter;
 
-use serde::de::{self, Deserialize, DeserializeSeed, Error as SerdeError, Visitor};
+use serde::de::{
+    self, Deserialize, DeserializeSeed, Error as SerdeError, IntoDeserializer, Visitor,
+};
 
+use schema::{Names, Schema};
 use types::Value;
+use util::format_uuid;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Error {
@@ -34,59 +38,124 @@ impl error::Error for Error {
     }
 }
 
-pub struct Deserializer<'de> {
+pub struct Deserializer<'s, 'de> {
     input: &'de Value,
+    /// The schema of the input, if known, which tells the variants of enums held by unions by
+    /// their names.
+    schema: Option<&'s Schema>,
+    names: &'s Names<'s>,
 }
 
-struct SeqDeserializer<'de> {
+struct SeqDeserializer<'s, 'de> {
     input: Iter<'de, Value>,
+    items: Option<&'s Schema>,
+    names: &'s Names<'s>,
 }
 
-struct MapDeserializer<'de> {
+struct MapDeserializer<'s, 'de> {
     input_keys: Keys<'de, String, Value>,
     input_values: Values<'de, String, Value>,
+    values: Option<&'s Schema>,
+    names: &'s Names<'s>,
 }
 
-struct StructDeserializer<'de> {
+struct StructDeserializer<'s, 'de> {
     input: Iter<'de, (String, Value)>,
-    value: Option<&'de Value>,
+    /// The field whose name was deserialized last, whose value is deserialized next.
+    value: Option<&'de (String, Value)>,
+    schema: Option<&'s Schema>,
+    names: &'s Names<'s>,
 }
 
-impl<'de> Deserializer<'de> {
-    pub fn new(input: &'de Value) -> Self {
-        Deserializer { input }
+/// Deserializes the fields of a record in order, as the fields of a tuple variant.
+struct TupleDeserializer<'s, 'de> {
+    input: Iter<'de, (String, Value)>,
+    schema: Option<&'s Schema>,
+    names: &'s Names<'s>,
+}
+
+/// Deserializes a unit variant from the symbol of an enum, or any variant from the variant of a
+/// union called after it. Like `Reader::into_typed`, variants of the union which are not named
+/// after a variant of the enum are taken as the variant at the same position.
+struct EnumDeserializer<'s, 'de> {
+    /// The value of the variant, within its union if any.
+    input: &'de Value,
+    schema: Option<&'s Schema>,
+    names: &'s Names<'s>,
+    /// The position of the variant within its union, if any.
+    index: Option<u32>,
+    variants: &'static [&'static str],
+}
+
+impl<'s, 'de> Deserializer<'s, 'de> {
+    pub fn new(input: &'de Value, schema: Option<&'s Schema>, names: &'s Names<'s>) -> Self {
+        Deserializer {
+            input,
+            schema: schema.and_then(|schema| schema.resolve_ref(names)),
+            names,
+        }
+    }
+
+    /// The schema of the variant at position `index` of the union, if known.
+    fn variant(&self, index: u32) -> Option<&'s Schema> {
+        match self.schema {
+            Some(Schema::Union(union)) => union.variants().get(index as usize),
+            _ => None,
+        }
     }
 }
 
-impl<'de> SeqDeserializer<'de> {
-    pub fn new(input: &'de [Value]) -> Self {
+impl<'s, 'de> SeqDeserializer<'s, 'de> {
+    pub fn new(input: &'de [Value], schema: Option<&'s Schema>, names: &'s Names<'s>) -> Self {
+        let items = match schema {
+            Some(Schema::Array(items)) => Some(&**items),
+            _ => None,
+        };
         SeqDeserializer {
             input: input.iter(),
+            items,
+            names,
         }
     }
 }
 
-impl<'de> MapDeserializer<'de> {
-    pub fn new(input: &'de HashMap<String, Value>) -> Self {
+impl<'s, 'de> MapDeserializer<'s, 'de> {
+    pub fn new(
+        input: &'de HashMap<String, Value>,
+        schema: Option<&'s Schema>,
+        names: &'s Names<'s>,
+    ) -> Self {
+        let values = match schema {
+            Some(Schema::Map(values)) => Some(&**values),
+            _ => None,
+        };
         MapDeserializer {
             input_keys: input.keys(), // input.keys().map(|k| Value::String(k.clone())).collect::<Vec<_>>().iter(),
             input_values: input.values(),
             // keys: input.keys().map(|s| Value::String(s.to_owned())).collect::<Vec<Value>>(),
             // values: input.values().map(|s| s.to_owned()).collect::<Vec<Value>>(),
+            values,
+            names,
         }
     }
 }
 
-impl<'de> StructDeserializer<'de> {
-    pub fn new(input: &'de [(String, Value)]) -> Self {
+impl<'s, 'de> StructDeserializer<'s, 'de> {
+    pub fn new(
+        input: &'de [(String, Value)],
+        schema: Option<&'s Schema>,
+        names: &'s Names<'s>,
+    ) -> Self {
         StructDeserializer {
             input: input.iter(),
             value: None,
+            schema,
+            names,
         }
     }
 }
 
-impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'a, 's, 'de> de::Deserializer<'de> for &'a mut Deserializer<'s, 'de> {
     type Error = Error;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -96,10 +165,16 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         match *self.input {
             Value::Null => visitor.visit_unit(),
             Value::Boolean(b) => visitor.visit_bool(b),
-            Value::Int(i) => visitor.visit_i32(i),
-            Value::Long(i) => visitor.visit_i64(i),
+            Value::Int(i) | Value::Date(i) | Value::TimeMillis(i) => visitor.visit_i32(i),
+            Value::Long(i)
+            | Value::TimeMicros(i)
+            | Value::TimestampMillis(i)
+            | Value::TimestampMicros(i)
+            | Value::LocalTimestampMillis(i)
+            | Value::LocalTimestampMicros(i) => visitor.visit_i64(i),
             Value::Float(x) => visitor.visit_f32(x),
             Value::Double(x) => visitor.visit_f64(x),
+            Value::Uuid(ref uuid) => visitor.visit_string(format_uuid(uuid)),
             _ => Err(Error::custom("incorrect value")),
         }
     }
@@ -124,6 +199,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
             Value::Bytes(ref bytes) | Value::Fixed(_, ref bytes) => ::std::str::from_utf8(bytes)
                 .map_err(|e| Error::custom(e.description()))
                 .and_then(|s| visitor.visit_str(s)),
+            Value::Uuid(ref uuid) => visitor.visit_string(format_uuid(uuid)),
             _ => Err(Error::custom("not a string|bytes|fixed")),
         }
     }
@@ -139,6 +215,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
                     .map_err(|e| Error::custom(e.description()))
                     .and_then(|s| visitor.visit_string(s))
             },
+            Value::Uuid(ref uuid) => visitor.visit_string(format_uuid(uuid)),
             _ => Err(Error::custom("not a string|bytes|fixed")),
         }
     }
@@ -150,6 +227,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         match *self.input {
             Value::String(ref s) => visitor.visit_bytes(s.as_bytes()),
             Value::Bytes(ref bytes) | Value::Fixed(_, ref bytes) => visitor.visit_bytes(bytes),
+            Value::Uuid(ref uuid) => visitor.visit_bytes(uuid),
             _ => Err(Error::custom("not a string|bytes|fixed")),
         }
     }
@@ -163,6 +241,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
             Value::Bytes(ref bytes) | Value::Fixed(_, ref bytes) => {
                 visitor.visit_byte_buf(bytes.to_owned())
             },
+            Value::Uuid(ref uuid) => visitor.visit_byte_buf(uuid.to_vec()),
             _ => Err(Error::custom("not a string|bytes|fixed")),
         }
     }
@@ -172,8 +251,12 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: Visitor<'de>,
     {
         match *self.input {
-            Value::Union(ref inner) if inner.as_ref() == &Value::Null => visitor.visit_none(),
-            Value::Union(ref inner) => visitor.visit_some(&mut Deserializer::new(inner)),
+            Value::Union(_, ref inner) if inner.as_ref() == &Value::Null => visitor.visit_none(),
+            Value::Union(index, ref inner) => visitor.visit_some(&mut Deserializer::new(
+                inner,
+                self.variant(index),
+                self.names,
+            )),
             _ => Err(Error::custom("not a union")),
         }
     }
@@ -215,7 +298,9 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: Visitor<'de>,
     {
         match *self.input {
-            Value::Array(ref items) => visitor.visit_seq(SeqDeserializer::new(items)),
+            Value::Array(ref items) => {
+                visitor.visit_seq(SeqDeserializer::new(items, self.schema, self.names))
+            },
             _ => Err(Error::custom("not an array")),
         }
     }
@@ -244,7 +329,9 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: Visitor<'de>,
     {
         match *self.input {
-            Value::Map(ref items) => visitor.visit_map(MapDeserializer::new(items)),
+            Value::Map(ref items) => {
+                visitor.visit_map(MapDeserializer::new(items, self.schema, self.names))
+            },
             _ => Err(Error::custom("not a map")),
         }
     }
@@ -259,7 +346,9 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: Visitor<'de>,
     {
         match *self.input {
-            Value::Record(ref fields) => visitor.visit_map(StructDeserializer::new(fields)),
+            Value::Record(ref fields) => {
+                visitor.visit_map(StructDeserializer::new(fields, self.schema, self.names))
+            },
             _ => Err(Error::custom("not a record")),
         }
     }
@@ -267,16 +356,24 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     fn deserialize_enum<V>(
         self,
         _: &'static str,
-        _variants: &'static [&'static str],
-        _visitor: V,
+        variants: &'static [&'static str],
+        visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        match *self.input {
-            // &Value::Enum(i) => ,  TODO
-            _ => Err(Error::custom("not an enum")),
-        }
+        // The value can also be the variant of a union already found, such as the one of an option.
+        let (input, schema, index) = match *self.input {
+            Value::Union(index, ref inner) => (&**inner, self.variant(index), Some(index)),
+            _ => (self.input, self.schema, None),
+        };
+        visitor.visit_enum(EnumDeserializer {
+            input,
+            schema,
+            names: self.names,
+            index,
+            variants,
+        })
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -294,7 +391,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 }
 
-impl<'de> de::SeqAccess<'de> for SeqDeserializer<'de> {
+impl<'s, 'de> de::SeqAccess<'de> for SeqDeserializer<'s, 'de> {
     type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
@@ -302,13 +399,15 @@ impl<'de> de::SeqAccess<'de> for SeqDeserializer<'de> {
         T: DeserializeSeed<'de>,
     {
         match self.input.next() {
-            Some(item) => seed.deserialize(&mut Deserializer::new(&item)).map(Some),
+            Some(item) => seed
+                .deserialize(&mut Deserializer::new(&item, self.items, self.names))
+                .map(Some),
             None => Ok(None),
         }
     }
 }
 
-impl<'de> de::MapAccess<'de> for MapDeserializer<'de> {
+impl<'s, 'de> de::MapAccess<'de> for MapDeserializer<'s, 'de> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
@@ -329,13 +428,15 @@ impl<'de> de::MapAccess<'de> for MapDeserializer<'de> {
         V: DeserializeSeed<'de>,
     {
         match self.input_values.next() {
-            Some(ref value) => seed.deserialize(&mut Deserializer::new(value)),
+            Some(ref value) => {
+                seed.deserialize(&mut Deserializer::new(value, self.values, self.names))
+            },
             None => Err(Error::custom("should not happen - too many values")),
         }
     }
 }
 
-impl<'de> de::MapAccess<'de> for StructDeserializer<'de> {
+impl<'s, 'de> de::MapAccess<'de> for StructDeserializer<'s, 'de> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
@@ -344,10 +445,9 @@ impl<'de> de::MapAccess<'de> for StructDeserializer<'de> {
     {
         match self.input.next() {
             Some(item) => {
-                let (ref field, ref value) = *item;
-                self.value = Some(value);
+                self.value = Some(item);
                 seed.deserialize(StringDeserializer {
-                    input: field.clone(),
+                    input: item.0.clone(),
                 }).map(Some)
             },
             None => Ok(None),
@@ -359,12 +459,120 @@ impl<'de> de::MapAccess<'de> for StructDeserializer<'de> {
         V: DeserializeSeed<'de>,
     {
         match self.value.take() {
-            Some(value) => seed.deserialize(&mut Deserializer::new(value)),
+            Some((field, value)) => {
+                let schema = self.schema.and_then(|schema| schema.field_schema(field));
+                seed.deserialize(&mut Deserializer::new(value, schema, self.names))
+            },
             None => Err(Error::custom("should not happen - too many values")),
         }
     }
 }
 
+impl<'s, 'de> de::SeqAccess<'de> for TupleDeserializer<'s, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.input.next() {
+            Some((field, value)) => {
+                let schema = self.schema.and_then(|schema| schema.field_schema(field));
+                seed.deserialize(&mut Deserializer::new(value, schema, self.names))
+                    .map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+impl<'s, 'de> de::EnumAccess<'de> for EnumDeserializer<'s, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let named = match self.schema.and_then(|schema| schema.name()) {
+            Some(name) if self.variants.contains(&name.name.as_str()) => Some(name.name.as_str()),
+            _ => None,
+        };
+        let variant = match (self.input, named, self.index) {
+            (Value::Enum(_, symbol), _, _) => seed.deserialize(StringDeserializer {
+                input: symbol.clone(),
+            })?,
+            (_, Some(name), _) => seed.deserialize(StringDeserializer {
+                input: name.to_owned(),
+            })?,
+            (_, None, Some(index)) if self.schema.is_some() => {
+                seed.deserialize(index.into_deserializer())?
+            },
+            (_, None, Some(_)) => {
+                return Err(Error::custom(
+                    "the variant of a union is only known with its schema",
+                ))
+            },
+            _ => return Err(Error::custom("not an enum")),
+        };
+        Ok((variant, self))
+    }
+}
+
+impl<'s, 'de> de::VariantAccess<'de> for EnumDeserializer<'s, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match *self.input {
+            Value::Enum(..) | Value::Null => Ok(()),
+            Value::Record(ref fields) if fields.is_empty() => Ok(()),
+            _ => Err(Error::custom("not a unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.index {
+            Some(_) => {
+                seed.deserialize(&mut Deserializer::new(self.input, self.schema, self.names))
+            },
+            None => Err(Error::custom("not a newtype variant")),
+        }
+    }
+
+    fn tuple_variant<V>(self, _: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match *self.input {
+            Value::Record(ref fields) => visitor.visit_seq(TupleDeserializer {
+                input: fields.iter(),
+                schema: self.schema,
+                names: self.names,
+            }),
+            _ => Err(Error::custom("not a tuple variant")),
+        }
+    }
+
+    fn struct_variant<V>(
+        self,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match *self.input {
+            Value::Record(ref fields) => {
+                visitor.visit_map(StructDeserializer::new(fields, self.schema, self.names))
+            },
+            _ => Err(Error::custom("not a struct variant")),
+        }
+    }
+}
+
 struct StringDeserializer {
     input: String,
 }
@@ -390,7 +598,25 @@ impl<'de> de::Deserializer<'de> for StringDeserializer {
 ///
 /// This conversion can fail if the structure of the `Value` does not match the
 /// structure expected by `D`.
+///
+/// Enums can be deserialized from the symbol of an Avro enum. Deserializing them from a union needs
+/// the schema of the value, see `from_value_with_schema`.
 pub fn from_value<'de, D: Deserialize<'de>>(value: &'de Value) -> Result<D, Error> {
-    let mut de = Deserializer::new(value);
+    let names = Names::new();
+    let mut de = Deserializer::new(value, None, &names);
+    D::deserialize(&mut de)
+}
+
+/// Interpret a `Value` of the given `Schema` as an instance of type `D`.
+///
+/// Unlike `from_value`, enums can also be deserialized from unions: the variant of the union
+/// named after a variant of the enum, typically a record, gives that variant, and other variants
+/// of the union give the variant of the enum at the same position.
+pub fn from_value_with_schema<'de, D: Deserialize<'de>>(
+    schema: &Schema,
+    value: &'de Value,
+) -> Result<D, Error> {
+    let names = schema.names();
+    let mut de = Deserializer::new(value, Some(schema), &names);
     D::deserialize(&mut de)
 }
@@ -0,0 +1,805 @@
+//! Logic for serde-compatible deserialization straight from Avro format, following a schema.
+use std::slice::Iter;
+use std::str::from_utf8;
+
+use failure;
+use serde::de::{
+    self, Deserialize, DeserializeSeed, Deserializer, Error as SerdeError, IgnoredAny,
+    IntoDeserializer, Visitor,
+};
+
+use de::Error;
+use decode::{decode_block_header, decode_len};
+use schema::{describe, Names, RecordField, Schema, UnionSchema};
+use util::{zag_i32, zag_i64};
+
+/// The schema of the keys of maps.
+static STRING_SCHEMA: Schema = Schema::String;
+
+/// Deserializes anything implementing `Deserialize` straight from Avro format, following the
+/// schema the data was written with.
+///
+/// Strings and bytes are borrowed from the input. Records are visited as maps from the names of
+/// their fields, so that fields unknown to the target type are skipped.
+pub(crate) struct SchemaDeserializer<'a, 's: 'a, 'de: 'a> {
+    schema: &'s Schema,
+    names: &'a Names<'s>,
+    input: &'a mut &'de [u8],
+}
+
+/// Deserializes the items of an array or the entries of a map, block by block.
+struct BlockDeserializer<'a, 's: 'a, 'de: 'a> {
+    schema: &'s Schema,
+    names: &'a Names<'s>,
+    input: &'a mut &'de [u8],
+    is_map: bool,
+    // The number of items left in the current block.
+    remaining: usize,
+    // Whether the block ending the array or map was read.
+    done: bool,
+    // Whether the key of an entry was read without its value.
+    pending_value: bool,
+}
+
+/// Deserializes a variant of an enum from the variant of a union: the enum variant named after the
+/// union variant, or else the one at the same position.
+struct VariantDeserializer<'a, 's: 'a, 'de: 'a> {
+    deserializer: SchemaDeserializer<'a, 's, 'de>,
+    index: u32,
+    variants: &'static [&'static str],
+}
+
+/// Deserializes the fields of a record as a map from their names, or in order as a sequence.
+struct RecordDeserializer<'a, 's: 'a, 'de: 'a> {
+    fields: Iter<'s, RecordField>,
+    names: &'a Names<'s>,
+    input: &'a mut &'de [u8],
+    // The schema of the field whose name was read without its value.
+    value: Option<&'s Schema>,
+}
+
+impl<'a, 's, 'de> SchemaDeserializer<'a, 's, 'de> {
+    pub(crate) fn new(
+        schema: &'s Schema,
+        names: &'a Names<'s>,
+        input: &'a mut &'de [u8],
+    ) -> SchemaDeserializer<'a, 's, 'de> {
+        SchemaDeserializer {
+            schema,
+            names,
+            input,
+        }
+    }
+
+    fn with_schema(self, schema: &'s Schema) -> SchemaDeserializer<'a, 's, 'de> {
+        SchemaDeserializer::new(schema, self.names, self.input)
+    }
+
+    /// Read the index of the variant of a union, returning it with the schema of that variant.
+    fn read_variant(&mut self, union: &'s UnionSchema) -> Result<(u32, &'s Schema), Error> {
+        let index = zag_i64(self.input).map_err(Error::custom)?;
+        match union.variants().get(index as usize) {
+            Some(variant) if index >= 0 => Ok((index as u32, variant)),
+            _ => Err(Error::custom("union index out of bounds")),
+        }
+    }
+
+    fn read_bytes(&mut self) -> Result<&'de [u8], Error> {
+        let len = decode_len(self.input).map_err(Error::custom)?;
+        take(self.input, len)
+    }
+
+    fn read_str(&mut self) -> Result<&'de str, Error> {
+        from_utf8(self.read_bytes()?).map_err(|_| Error::custom("not a valid utf-8 string"))
+    }
+
+    /// Skip the value, jumping over the blocks of arrays and maps written with their size.
+    fn skip(self) -> Result<(), Error> {
+        match *resolve(self.schema, self.names)? {
+            Schema::Array(ref inner) => {
+                BlockDeserializer::new(inner, self.names, self.input, false).end()
+            },
+            Schema::Map(ref inner) => {
+                BlockDeserializer::new(inner, self.names, self.input, true).end()
+            },
+            _ => self.deserialize_any(IgnoredAny).map(|_| ()),
+        }
+    }
+
+    /// Visit the fields of a record in order, as the fields of a tuple variant.
+    fn deserialize_record_seq<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match *resolve(self.schema, self.names)? {
+            Schema::Record { ref fields, .. } => {
+                let mut record = RecordDeserializer::new(fields, self.names, self.input);
+                let value = visitor.visit_seq(&mut record)?;
+                record.end()?;
+                Ok(value)
+            },
+            _ => self.deserialize_any(visitor),
+        }
+    }
+}
+
+impl<'a, 's, 'de> Deserializer<'de> for SchemaDeserializer<'a, 's, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let schema = resolve(self.schema, self.names)?;
+        match *schema {
+            Schema::Null => visitor.visit_unit(),
+            Schema::Boolean => match take(self.input, 1)?[0] {
+                0u8 => visitor.visit_bool(false),
+                1u8 => visitor.visit_bool(true),
+                _ => Err(Error::custom("not a bool")),
+            },
+            Schema::Int | Schema::Date | Schema::TimeMillis => {
+                visitor.visit_i32(zag_i32(self.input).map_err(Error::custom)?)
+            },
+            Schema::Long
+            | Schema::TimeMicros
+            | Schema::TimestampMillis
+            | Schema::TimestampMicros
+            | Schema::LocalTimestampMillis
+            | Schema::LocalTimestampMicros => {
+                visitor.visit_i64(zag_i64(self.input).map_err(Error::custom)?)
+            },
+            Schema::Float => {
+                let mut buf = [0u8; 4];
+                buf.copy_from_slice(take(self.input, 4)?);
+                visitor.visit_f32(f32::from_le_bytes(buf))
+            },
+            Schema::Double => {
+                let mut buf = [0u8; 8];
+                buf.copy_from_slice(take(self.input, 8)?);
+                visitor.visit_f64(f64::from_le_bytes(buf))
+            },
+            Schema::Bytes => visitor.visit_borrowed_bytes(self.read_bytes()?),
+            Schema::String | Schema::Uuid => visitor.visit_borrowed_str(self.read_str()?),
+            Schema::Fixed { size, .. } => visitor.visit_borrowed_bytes(take(self.input, size)?),
+            Schema::Duration { .. } => visitor.visit_borrowed_bytes(take(self.input, 12)?),
+            Schema::Decimal { ref inner, .. } => self.with_schema(inner).deserialize_any(visitor),
+            Schema::Enum { ref symbols, .. } => {
+                let index = zag_i32(self.input).map_err(Error::custom)?;
+                match symbols.get(index as usize) {
+                    Some(symbol) if index >= 0 => visitor.visit_str(symbol),
+                    _ => Err(Error::custom("enum symbol index out of bounds")),
+                }
+            },
+            Schema::Union(ref union) => {
+                let (_, variant) = self.read_variant(union)?;
+                self.with_schema(variant).deserialize_any(visitor)
+            },
+            Schema::Array(ref inner) => {
+                let mut items = BlockDeserializer::new(inner, self.names, self.input, false);
+                let value = visitor.visit_seq(&mut items)?;
+                items.end()?;
+                Ok(value)
+            },
+            Schema::Map(ref inner) => {
+                let mut entries = BlockDeserializer::new(inner, self.names, self.input, true);
+                let value = visitor.visit_map(&mut entries)?;
+                entries.end()?;
+                Ok(value)
+            },
+            Schema::Record { ref fields, .. } => {
+                let mut record = RecordDeserializer::new(fields, self.names, self.input);
+                let value = visitor.visit_map(&mut record)?;
+                record.end()?;
+                Ok(value)
+            },
+            Schema::Ref { .. } | Schema::Annotated { .. } => Err(Error::custom(format!(
+                "unresolved schema reference {}",
+                describe(schema)
+            ))),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+
+    fn deserialize_option<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match *resolve(self.schema, self.names)? {
+            Schema::Null => visitor.visit_none(),
+            Schema::Union(ref union) => {
+                let (_, variant) = self.read_variant(union)?;
+                if let Schema::Null = *resolve(variant, self.names)? {
+                    visitor.visit_none()
+                } else {
+                    visitor.visit_some(self.with_schema(variant))
+                }
+            },
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        mut self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match *resolve(self.schema, self.names)? {
+            Schema::Enum { ref symbols, .. } => {
+                let index = zag_i32(self.input).map_err(Error::custom)?;
+                match symbols.get(index as usize) {
+                    Some(symbol) if index >= 0 => {
+                        visitor.visit_enum(symbol.as_str().into_deserializer())
+                    },
+                    _ => Err(Error::custom("enum symbol index out of bounds")),
+                }
+            },
+            Schema::Union(ref union) => {
+                let (index, variant) = self.read_variant(union)?;
+                let variant = resolve(variant, self.names)?;
+                if let Schema::Enum { .. } = *variant {
+                    return self.with_schema(variant).deserialize_enum(name, variants, visitor)
+                }
+                visitor.visit_enum(VariantDeserializer {
+                    deserializer: self.with_schema(variant),
+                    index,
+                    variants,
+                })
+            },
+            _ => Err(Error::custom("not an enum")),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.skip()?;
+        visitor.visit_unit()
+    }
+}
+
+impl<'a, 's, 'de> BlockDeserializer<'a, 's, 'de> {
+    fn new(
+        schema: &'s Schema,
+        names: &'a Names<'s>,
+        input: &'a mut &'de [u8],
+        is_map: bool,
+    ) -> BlockDeserializer<'a, 's, 'de> {
+        BlockDeserializer {
+            schema,
+            names,
+            input,
+            is_map,
+            remaining: 0,
+            done: false,
+            pending_value: false,
+        }
+    }
+
+    fn deserializer(&mut self, schema: &'s Schema) -> SchemaDeserializer<'_, 's, 'de> {
+        SchemaDeserializer::new(schema, self.names, self.input)
+    }
+
+    /// Move to the next item, reading the header of the next block when needed. Returns `false`
+    /// once all the items were read.
+    fn next_item(&mut self) -> Result<bool, Error> {
+        if self.remaining == 0 {
+            if self.done {
+                return Ok(false)
+            }
+            let (len, _) = decode_block_header(self.input).map_err(Error::custom)?;
+            if len == 0 {
+                self.done = true;
+                return Ok(false)
+            }
+            self.remaining = len;
+        }
+        self.remaining -= 1;
+        Ok(true)
+    }
+
+    /// Skip the items which were not read, so that the input is left past the array or map.
+    fn end(mut self) -> Result<(), Error> {
+        if self.pending_value {
+            let schema = self.schema;
+            self.deserializer(schema).skip()?;
+        }
+        loop {
+            while self.remaining > 0 {
+                self.remaining -= 1;
+                if self.is_map {
+                    self.deserializer(&STRING_SCHEMA).skip()?;
+                }
+                let schema = self.schema;
+                self.deserializer(schema).skip()?;
+            }
+            if self.done {
+                return Ok(())
+            }
+            match decode_block_header(self.input).map_err(Error::custom)? {
+                (0, _) => return Ok(()),
+                (_, Some(size)) => {
+                    take(self.input, size as usize)?;
+                },
+                (len, None) => self.remaining = len,
+            }
+        }
+    }
+}
+
+impl<'a, 's, 'de> de::SeqAccess<'de> for BlockDeserializer<'a, 's, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if !self.next_item()? {
+            return Ok(None)
+        }
+        let schema = self.schema;
+        seed.deserialize(self.deserializer(schema)).map(Some)
+    }
+}
+
+impl<'a, 's, 'de> de::MapAccess<'de> for BlockDeserializer<'a, 's, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if !self.next_item()? {
+            return Ok(None)
+        }
+        let key = seed.deserialize(self.deserializer(&STRING_SCHEMA))?;
+        self.pending_value = true;
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.pending_value = false;
+        let schema = self.schema;
+        seed.deserialize(self.deserializer(schema))
+    }
+}
+
+impl<'a, 's, 'de> de::EnumAccess<'de> for VariantDeserializer<'a, 's, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let named = match self.deserializer.schema.name() {
+            Some(name) if self.variants.contains(&name.name.as_str()) => Some(name.name.as_str()),
+            _ => None,
+        };
+        let variant = match named {
+            Some(name) => seed.deserialize(name.into_deserializer())?,
+            None => seed.deserialize(self.index.into_deserializer())?,
+        };
+        Ok((variant, self))
+    }
+}
+
+impl<'a, 's, 'de> de::VariantAccess<'de> for VariantDeserializer<'a, 's, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        self.deserializer.skip()
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.deserializer)
+    }
+
+    fn tuple_variant<V>(self, _: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserializer.deserialize_record_seq(visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserializer.deserialize_any(visitor)
+    }
+}
+
+impl<'a, 's, 'de> RecordDeserializer<'a, 's, 'de> {
+    fn new(
+        fields: &'s [RecordField],
+        names: &'a Names<'s>,
+        input: &'a mut &'de [u8],
+    ) -> RecordDeserializer<'a, 's, 'de> {
+        RecordDeserializer {
+            fields: fields.iter(),
+            names,
+            input,
+            value: None,
+        }
+    }
+
+    /// Skip the fields which were not read, so that the input is left past the record.
+    fn end(mut self) -> Result<(), Error> {
+        if let Some(schema) = self.value.take() {
+            SchemaDeserializer::new(schema, self.names, self.input).skip()?;
+        }
+        for field in self.fields {
+            SchemaDeserializer::new(&field.schema, self.names, self.input).skip()?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, 's, 'de> de::MapAccess<'de> for RecordDeserializer<'a, 's, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.fields.next() {
+            Some(field) => {
+                self.value = Some(&field.schema);
+                seed.deserialize(field.name.as_str().into_deserializer()).map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some(schema) => {
+                seed.deserialize(SchemaDeserializer::new(schema, self.names, self.input))
+            },
+            None => Err(Error::custom("should not happen - too many values")),
+        }
+    }
+}
+
+impl<'a, 's, 'de> de::SeqAccess<'de> for RecordDeserializer<'a, 's, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.fields.next() {
+            Some(field) => seed
+                .deserialize(SchemaDeserializer::new(&field.schema, self.names, self.input))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Take the next `len` bytes of the input, without copying them.
+fn take<'de>(input: &mut &'de [u8], len: usize) -> Result<&'de [u8], Error> {
+    let bytes = *input;
+    if bytes.len() < len {
+        return Err(Error::custom("unexpected end of input"))
+    }
+    let (taken, rest) = bytes.split_at(len);
+    *input = rest;
+    Ok(taken)
+}
+
+fn resolve<'s>(schema: &'s Schema, names: &Names<'s>) -> Result<&'s Schema, Error> {
+    schema.resolve_ref(names).ok_or_else(|| {
+        Error::custom(format!("unresolved schema reference {}", describe(schema)))
+    })
+}
+
+/// Deserialize a `T` from the start of `input`, written in Avro format following `schema`, and
+/// move `input` past it.
+pub(crate) fn deserialize<'de, T>(
+    schema: &Schema,
+    input: &mut &'de [u8],
+) -> Result<T, failure::Error>
+where
+    T: Deserialize<'de>,
+{
+    let names = schema.names();
+    Ok(T::deserialize(SchemaDeserializer::new(schema, &names, input))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use encode::encode;
+    use ser_schema::SchemaSerializer;
+    use serde::Serialize;
+    use types::Value;
+
+    fn serialize<S: Serialize>(schema: &Schema, value: S, sized_blocks: bool) -> Vec<u8> {
+        let names = schema.names();
+        let mut buffer = Vec::new();
+        value
+            .serialize(SchemaSerializer::new(schema, &names, sized_blocks, &mut buffer))
+            .unwrap();
+        buffer
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Inner {
+        a: i64,
+        b: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Outer {
+        flag: bool,
+        count: i32,
+        ratio: f32,
+        score: f64,
+        name: String,
+        suit: String,
+        inner: Inner,
+        items: Vec<Inner>,
+        tags: HashMap<String, i64>,
+        nothing: (),
+    }
+
+    const OUTER_SCHEMA: &str = r#"
+        {
+            "type": "record",
+            "name": "Outer",
+            "fields": [
+                {"name": "flag", "type": "boolean"},
+                {"name": "count", "type": "int"},
+                {"name": "ratio", "type": "float"},
+                {"name": "score", "type": "double"},
+                {"name": "name", "type": "string"},
+                {"name": "suit", "type": {
+                    "type": "enum", "name": "Suit", "symbols": ["Hearts", "Spades"]
+                }},
+                {"name": "inner", "type": {
+                    "type": "record",
+                    "name": "Inner",
+                    "fields": [
+                        {"name": "a", "type": "long"},
+                        {"name": "b", "type": ["null", "string"]}
+                    ]
+                }},
+                {"name": "items", "type": {"type": "array", "items": "Inner"}},
+                {"name": "tags", "type": {"type": "map", "values": "long"}},
+                {"name": "nothing", "type": "null"}
+            ]
+        }
+    "#;
+
+    fn outer() -> Outer {
+        let mut tags = HashMap::new();
+        tags.insert("x".to_string(), 1);
+        tags.insert("y".to_string(), -2);
+        Outer {
+            flag: true,
+            count: -7,
+            ratio: 0.5,
+            score: 1.25,
+            name: "outer".to_string(),
+            suit: "Spades".to_string(),
+            inner: Inner { a: 3, b: None },
+            items: vec![
+                Inner {
+                    a: 4,
+                    b: Some("four".to_string()),
+                },
+                Inner { a: 5, b: None },
+            ],
+            tags,
+            nothing: (),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_round_trip() {
+        let schema = Schema::parse_str(OUTER_SCHEMA).unwrap();
+        for &sized_blocks in &[false, true] {
+            let bytes = serialize(&schema, outer(), sized_blocks);
+            let mut input = &bytes[..];
+            assert_eq!(deserialize::<Outer>(&schema, &mut input).unwrap(), outer());
+            assert!(input.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_deserialize_borrowed() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Borrowed<'a> {
+            name: &'a str,
+            bytes: &'a [u8],
+            tags: Vec<&'a str>,
+        }
+
+        let schema = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "Borrowed",
+                "fields": [
+                    {"name": "name", "type": "string"},
+                    {"name": "bytes", "type": "bytes"},
+                    {"name": "tags", "type": {"type": "array", "items": "string"}}
+                ]
+            }
+        "#,
+        )
+        .unwrap();
+        let value = Value::Record(vec![
+            ("name".to_string(), Value::String("borrowed".to_string())),
+            ("bytes".to_string(), Value::Bytes(vec![1, 2, 3])),
+            (
+                "tags".to_string(),
+                Value::Array(vec![Value::String("a".to_string())]),
+            ),
+        ]);
+        let mut bytes = Vec::new();
+        encode(&value, &schema, &mut bytes).unwrap();
+
+        let borrowed: Borrowed = deserialize(&schema, &mut &bytes[..]).unwrap();
+        assert_eq!(
+            borrowed,
+            Borrowed {
+                name: "borrowed",
+                bytes: &[1, 2, 3],
+                tags: vec!["a"],
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_skips_unread_values() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Partial {
+            count: i32,
+            items: (Inner,),
+            nothing: (),
+        }
+
+        let schema = Schema::parse_str(OUTER_SCHEMA).unwrap();
+        for &sized_blocks in &[false, true] {
+            // Two values one after the other, to check the input is left past the first one.
+            let mut bytes = serialize(&schema, outer(), sized_blocks);
+            bytes.extend(serialize(&schema, outer(), sized_blocks));
+            let mut input = &bytes[..];
+            for _ in 0..2 {
+                assert_eq!(
+                    deserialize::<Partial>(&schema, &mut input).unwrap(),
+                    Partial {
+                        count: -7,
+                        items: (Inner {
+                            a: 4,
+                            b: Some("four".to_string()),
+                        },),
+                        nothing: (),
+                    }
+                );
+            }
+            assert!(input.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_deserialize_enums() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Suit {
+            Hearts,
+            Spades,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Shape {
+            Circle { radius: f64 },
+            Rectangle(f64, f64),
+            Named(String),
+            Empty,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Drawing {
+            suit: Option<Suit>,
+            shapes: Vec<Shape>,
+        }
+
+        // Records are found by name, other variants by position.
+        let schema = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "Drawing",
+                "fields": [
+                    {"name": "suit", "type": ["null", {
+                        "type": "enum", "name": "Suit", "symbols": ["Hearts", "Spades"]
+                    }]},
+                    {"name": "shapes", "type": {"type": "array", "items": [
+                        {"type": "record", "name": "Rectangle", "fields": [
+                            {"name": "width", "type": "double"},
+                            {"name": "height", "type": "double"}
+                        ]},
+                        {"type": "record", "name": "Circle", "fields": [
+                            {"name": "radius", "type": "double"}
+                        ]},
+                        "string",
+                        {"type": "record", "name": "Empty", "fields": []}
+                    ]}}
+                ]
+            }
+        "#,
+        )
+        .unwrap();
+        let drawing = Drawing {
+            suit: Some(Suit::Spades),
+            shapes: vec![
+                Shape::Circle { radius: 1.0 },
+                Shape::Rectangle(2.0, 3.0),
+                Shape::Named("square".to_string()),
+                Shape::Empty,
+            ],
+        };
+        let bytes = serialize(&schema, &drawing, false);
+        let mut input = &bytes[..];
+        assert_eq!(deserialize::<Drawing>(&schema, &mut input).unwrap(), drawing);
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn test_deserialize_errors() {
+        let schema = Schema::parse_str(OUTER_SCHEMA).unwrap();
+        let bytes = serialize(&schema, outer(), false);
+        let mut truncated = &bytes[..bytes.len() - 4];
+        assert!(deserialize::<Outer>(&schema, &mut truncated).is_err());
+
+        let schema = Schema::parse_str(r#"["null", "long"]"#).unwrap();
+        assert!(deserialize::<Option<i64>>(&schema, &mut &[4u8, 2][..]).is_err());
+        assert_eq!(
+            deserialize::<Option<i64>>(&schema, &mut &[2u8, 2][..]).unwrap(),
+            Some(1)
+        );
+        assert_eq!(
+            deserialize::<Option<i64>>(&schema, &mut &[0u8][..]).unwrap(),
+            None
+        );
+        assert!(deserialize::<String>(&Schema::Long, &mut &[2u8][..]).is_err());
+    }
+}
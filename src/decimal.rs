@@ -0,0 +1,151 @@
+//! Logic handling the unscaled values of the `decimal` logical type.
+
+/// The unscaled value of an Avro `decimal`.
+///
+/// The value is kept as a big-endian two's-complement integer, which is how Avro encodes it on
+/// the wire. Its scale is not part of the value: it is defined by the
+/// [Schema](../schema/enum.Schema.html) the value is validated against.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Decimal {
+    // Minimal representation: no redundant leading sign extension bytes, and at least one byte.
+    bytes: Vec<u8>,
+}
+
+impl Decimal {
+    /// Returns the big-endian two's-complement bytes of the unscaled value, using as few bytes
+    /// as possible.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns true if the unscaled value is strictly negative.
+    pub fn is_negative(&self) -> bool {
+        self.bytes[0] & 0x80 != 0
+    }
+
+    /// Returns the number of decimal digits of the unscaled value, ignoring its sign.
+    pub fn num_digits(&self) -> usize {
+        let mut magnitude = self.bytes.clone();
+        if self.is_negative() {
+            // Two's-complement negation: invert all the bits and add one.
+            let mut carry = true;
+            for byte in magnitude.iter_mut().rev() {
+                let (value, overflow) = (!*byte).overflowing_add(carry as u8);
+                *byte = value;
+                carry = overflow;
+            }
+        }
+
+        let mut digits = 0;
+        loop {
+            // Long division of the magnitude by 10, in place.
+            let mut remainder = 0u16;
+            for byte in magnitude.iter_mut() {
+                let current = (remainder << 8) | u16::from(*byte);
+                *byte = (current / 10) as u8;
+                remainder = current % 10;
+            }
+            digits += 1;
+            if magnitude.iter().all(|byte| *byte == 0) {
+                return digits
+            }
+        }
+    }
+
+    /// Returns the two's-complement bytes of the unscaled value sign-extended to exactly `len`
+    /// bytes, or `None` if the value does not fit in `len` bytes.
+    pub fn to_sign_extended_bytes(&self, len: usize) -> Option<Vec<u8>> {
+        if self.bytes.len() > len {
+            return None
+        }
+
+        let fill = if self.is_negative() { 0xFF } else { 0x00 };
+        let mut bytes = vec![fill; len - self.bytes.len()];
+        bytes.extend_from_slice(&self.bytes);
+        Some(bytes)
+    }
+}
+
+impl From<Vec<u8>> for Decimal {
+    /// Create a `Decimal` from the big-endian two's-complement bytes of its unscaled value.
+    fn from(mut bytes: Vec<u8>) -> Self {
+        let redundant = bytes
+            .windows(2)
+            .take_while(|pair| {
+                (pair[0] == 0x00 && pair[1] & 0x80 == 0) || (pair[0] == 0xFF && pair[1] & 0x80 != 0)
+            }).count();
+        bytes.drain(..redundant);
+
+        if bytes.is_empty() {
+            bytes.push(0);
+        }
+
+        Decimal { bytes }
+    }
+}
+
+impl<'a> From<&'a [u8]> for Decimal {
+    fn from(bytes: &'a [u8]) -> Self {
+        Decimal::from(bytes.to_vec())
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(unscaled: i64) -> Self {
+        let bytes = (0..8)
+            .rev()
+            .map(|i| (unscaled >> (i * 8)) as u8)
+            .collect::<Vec<_>>();
+        Decimal::from(bytes)
+    }
+}
+
+impl From<i32> for Decimal {
+    fn from(unscaled: i32) -> Self {
+        Decimal::from(i64::from(unscaled))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minimal_representation() {
+        assert_eq!(Decimal::from(0i64).as_bytes(), &[0x00]);
+        assert_eq!(Decimal::from(127i64).as_bytes(), &[0x7F]);
+        assert_eq!(Decimal::from(128i64).as_bytes(), &[0x00, 0x80]);
+        assert_eq!(Decimal::from(-1i64).as_bytes(), &[0xFF]);
+        assert_eq!(Decimal::from(-128i64).as_bytes(), &[0x80]);
+        assert_eq!(Decimal::from(-129i64).as_bytes(), &[0xFF, 0x7F]);
+        assert_eq!(
+            Decimal::from(vec![0xFF, 0xFF, 0x80]),
+            Decimal::from(-128i64)
+        );
+        assert_eq!(Decimal::from(Vec::new()), Decimal::from(0i64));
+    }
+
+    #[test]
+    fn test_num_digits() {
+        assert_eq!(Decimal::from(0i64).num_digits(), 1);
+        assert_eq!(Decimal::from(9i64).num_digits(), 1);
+        assert_eq!(Decimal::from(-10i64).num_digits(), 2);
+        assert_eq!(Decimal::from(12345i64).num_digits(), 5);
+        assert_eq!(Decimal::from(-128i64).num_digits(), 3);
+        assert_eq!(Decimal::from(i64::MIN).num_digits(), 19);
+        assert_eq!(Decimal::from(i64::MAX).num_digits(), 19);
+    }
+
+    #[test]
+    fn test_sign_extension() {
+        assert_eq!(
+            Decimal::from(-2i64).to_sign_extended_bytes(3),
+            Some(vec![0xFF, 0xFF, 0xFE])
+        );
+        assert_eq!(
+            Decimal::from(2i64).to_sign_extended_bytes(2),
+            Some(vec![0x00, 0x02])
+        );
+        assert_eq!(Decimal::from(65536i64).to_sign_extended_bytes(2), None);
+    }
+}
@@ -4,9 +4,10 @@ use std::mem::transmute;
 
 use failure::Error;
 
-use schema::Schema;
-use types::Value;
-use util::{safe_len, zag_i32, zag_i64, DecodeError};
+use decimal::Decimal;
+use schema::{Names, Schema};
+use types::{duration_from_bytes, Value};
+use util::{parse_uuid, safe_len, zag_i32, zag_i64, DecodeError};
 
 #[inline]
 fn decode_long<R: Read>(reader: &mut R) -> Result<Value, Error> {
@@ -19,12 +20,47 @@ fn decode_int<R: Read>(reader: &mut R) -> Result<Value, Error> {
 }
 
 #[inline]
-fn decode_len<R: Read>(reader: &mut R) -> Result<usize, Error> {
+pub(crate) fn decode_len<R: Read>(reader: &mut R) -> Result<usize, Error> {
     zag_i64(reader).and_then(|len| safe_len(len as usize))
 }
 
+/// Decode the header of a block of an array or a map: its number of items, followed by its size
+/// in bytes when the number of items is written as a negative number.
+#[inline]
+pub(crate) fn decode_block_header<R: Read>(reader: &mut R) -> Result<(usize, Option<u64>), Error> {
+    let len = zag_i64(reader)?;
+    if len >= 0 {
+        return Ok((safe_len(len as usize)?, None))
+    }
+
+    let len = len
+        .checked_neg()
+        .ok_or_else(|| DecodeError::new("block count out of bounds"))?;
+    let size = zag_i64(reader)?;
+    if size < 0 {
+        return Err(DecodeError::new("negative block size").into())
+    }
+    Ok((safe_len(len as usize)?, Some(size as u64)))
+}
+
 /// Decode a `Value` from avro format given its `Schema`.
+///
+/// The named types of the `Schema` are collected on every call: to decode several values, prefer
+/// a `ResolvedSchema`, computed once.
 pub fn decode<R: Read>(schema: &Schema, reader: &mut R) -> Result<Value, Error> {
+    decode_internal(schema, &schema.names(), reader)
+}
+
+/// Decode a `Value` from avro format given its `Schema`, following references to named types
+/// through `names`.
+///
+/// `names` are the named types of the root `Schema`, as given by `Schema::names`, which callers
+/// decoding several values with the same `Schema` should collect once.
+pub(crate) fn decode_internal<R: Read>(
+    schema: &Schema,
+    names: &Names,
+    reader: &mut R,
+) -> Result<Value, Error> {
     match *schema {
         Schema::Null => Ok(Value::Null),
         Schema::Boolean => {
@@ -39,6 +75,24 @@ pub fn decode<R: Read>(schema: &Schema, reader: &mut R) -> Result<Value, Error>
         },
         Schema::Int => decode_int(reader),
         Schema::Long => decode_long(reader),
+        Schema::Date => zag_i32(reader).map(Value::Date),
+        Schema::TimeMillis => zag_i32(reader).map(Value::TimeMillis),
+        Schema::TimeMicros => zag_i64(reader).map(Value::TimeMicros),
+        Schema::TimestampMillis => zag_i64(reader).map(Value::TimestampMillis),
+        Schema::TimestampMicros => zag_i64(reader).map(Value::TimestampMicros),
+        Schema::LocalTimestampMillis => zag_i64(reader).map(Value::LocalTimestampMillis),
+        Schema::LocalTimestampMicros => zag_i64(reader).map(Value::LocalTimestampMicros),
+        Schema::Duration { .. } => {
+            let mut buf = [0u8; 12];
+            reader.read_exact(&mut buf)?;
+            Ok(duration_from_bytes(&buf))
+        },
+        Schema::Uuid => match decode_internal(&Schema::String, names, reader)? {
+            Value::String(ref s) => parse_uuid(s)
+                .map(Value::Uuid)
+                .ok_or_else(|| DecodeError::new(format!("not a valid uuid: {}", s)).into()),
+            _ => Err(DecodeError::new("uuid is not backed by a string").into()),
+        },
         Schema::Float => {
             let mut buf = [0u8; 4];
             reader.read_exact(&mut buf[..])?;
@@ -75,11 +129,17 @@ pub fn decode<R: Read>(schema: &Schema, reader: &mut R) -> Result<Value, Error>
             reader.read_exact(&mut buf)?;
             Ok(Value::Fixed(size, buf))
         },
+        Schema::Decimal { ref inner, .. } => match decode_internal(inner, names, reader)? {
+            Value::Bytes(bytes) | Value::Fixed(_, bytes) => {
+                Ok(Value::Decimal(Decimal::from(bytes)))
+            },
+            _ => Err(DecodeError::new("decimal is not backed by bytes or fixed").into()),
+        },
         Schema::Array(ref inner) => {
             let mut items = Vec::new();
 
             loop {
-                let len = decode_len(reader)?;
+                let (len, _) = decode_block_header(reader)?;
                 // arrays are 0-terminated, 0i64 is also encoded as 0 in Avro
                 // reading a length of 0 means the end of the array
                 if len == 0 {
@@ -88,7 +148,7 @@ pub fn decode<R: Read>(schema: &Schema, reader: &mut R) -> Result<Value, Error>
 
                 items.reserve(len as usize);
                 for _ in 0..len {
-                    items.push(decode(inner, reader)?);
+                    items.push(decode_internal(inner, names, reader)?);
                 }
             }
 
@@ -98,7 +158,7 @@ pub fn decode<R: Read>(schema: &Schema, reader: &mut R) -> Result<Value, Error>
             let mut items = HashMap::new();
 
             loop {
-                let len = decode_len(reader)?;
+                let (len, _) = decode_block_header(reader)?;
                 // maps are 0-terminated, 0i64 is also encoded as 0 in Avro
                 // reading a length of 0 means the end of the map
                 if len == 0 {
@@ -107,8 +167,8 @@ pub fn decode<R: Read>(schema: &Schema, reader: &mut R) -> Result<Value, Error>
 
                 items.reserve(len as usize);
                 for _ in 0..len {
-                    if let Value::String(key) = decode(&Schema::String, reader)? {
-                        let value = decode(inner, reader)?;
+                    if let Value::String(key) = decode_internal(&Schema::String, names, reader)? {
+                        let value = decode_internal(inner, names, reader)?;
                         items.insert(key, value);
                     } else {
                         return Err(DecodeError::new("map key is not a string").into())
@@ -122,7 +182,8 @@ pub fn decode<R: Read>(schema: &Schema, reader: &mut R) -> Result<Value, Error>
             let index = zag_i64(reader)?;
             let variants = inner.variants();
             match variants.get(index as usize) {
-                Some(variant) => decode(variant, reader).map(|x| Value::Union(Box::new(x))),
+                Some(variant) => decode_internal(variant, names, reader)
+                    .map(|x| Value::Union(index as u32, Box::new(x))),
                 None => Err(DecodeError::new("Union index out of bounds").into()),
             }
         },
@@ -131,7 +192,10 @@ pub fn decode<R: Read>(schema: &Schema, reader: &mut R) -> Result<Value, Error>
             let mut items = Vec::new();
             for field in fields {
                 // This clone is also expensive. See if we can do away with it...
-                items.push((field.name.clone(), decode(&field.schema, reader)?));
+                items.push((
+                    field.name.clone(),
+                    decode_internal(&field.schema, names, reader)?,
+                ));
             }
             Ok(Value::Record(items))
             // fields
@@ -152,5 +216,51 @@ pub fn decode<R: Read>(schema: &Schema, reader: &mut R) -> Result<Value, Error>
                 Err(DecodeError::new("enum symbol not found").into())
             }
         },
+        Schema::Annotated { ref inner, .. } => decode_internal(inner, names, reader),
+        Schema::Ref { ref name } => match names.get(&name.fullname(None)) {
+            Some(schema) => decode_internal(schema, names, reader),
+            None => Err(DecodeError::new(format!(
+                "unresolved schema reference: {}",
+                name.fullname(None)
+            )).into()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::zig_i64;
+
+    #[test]
+    fn test_decode_blocks_with_size() {
+        // [1, 2] as a block of one item, then as a block of one item with its size
+        let mut array = Vec::new();
+        zig_i64(1, &mut array);
+        zig_i64(1, &mut array);
+        zig_i64(-1, &mut array);
+        zig_i64(1, &mut array);
+        zig_i64(2, &mut array);
+        array.push(0u8);
+        assert_eq!(
+            decode(&Schema::Array(Box::new(Schema::Long)), &mut &array[..]).unwrap(),
+            Value::Array(vec![Value::Long(1), Value::Long(2)])
+        );
+
+        let mut map = Vec::new();
+        zig_i64(-1, &mut map);
+        zig_i64(3, &mut map);
+        map.extend(vec![2u8, b'k', 1u8, 0u8]);
+        let mut expected = HashMap::new();
+        expected.insert("k".to_owned(), Value::Boolean(true));
+        assert_eq!(
+            decode(&Schema::Map(Box::new(Schema::Boolean)), &mut &map[..]).unwrap(),
+            Value::Map(expected)
+        );
+
+        let mut negative_size = Vec::new();
+        zig_i64(-1, &mut negative_size);
+        zig_i64(-1, &mut negative_size);
+        assert!(decode(&Schema::Array(Box::new(Schema::Long)), &mut &negative_size[..]).is_err());
     }
 }
@@ -1,118 +1,251 @@
 use std::mem::transmute;
 
-use schema::Schema;
-use types::Value;
-use util::{zig_i32, zig_i64};
+use failure::Error;
+
+use schema::{describe, Names, Schema};
+use types::{describe_value, duration_to_bytes, Value};
+use util::{format_uuid, zig_i32, zig_i64, EncodeError};
 
 /// Encode a `Value` into avro format.
 ///
-/// **NOTE** This will not perform schema validation. The value is assumed to
-/// be valid with regards to the schema. Schema are needed only to guide the
-/// encoding for complex type values.
-pub fn encode(value: &Value, schema: &Schema, buffer: &mut Vec<u8>) {
+/// **NOTE** This will not perform schema validation. Schema are needed only to guide the
+/// encoding for complex type values, but values which cannot be encoded with the schema, such as
+/// a `string` for a `long` or a record missing fields, are reported as an `EncodeError`. The
+/// buffer might then contain part of the value.
+pub fn encode(value: &Value, schema: &Schema, buffer: &mut Vec<u8>) -> Result<(), Error> {
     encode_ref(&value, schema, buffer)
 }
 
-fn encode_bytes<B: AsRef<[u8]> + ?Sized>(s: &B, buffer: &mut Vec<u8>) {
+pub(crate) fn encode_bytes<B: AsRef<[u8]> + ?Sized>(s: &B, buffer: &mut Vec<u8>) {
     let bytes = s.as_ref();
-    encode(&Value::Long(bytes.len() as i64), &Schema::Long, buffer);
+    encode_long(bytes.len() as i64, buffer);
     buffer.extend_from_slice(bytes);
 }
 
-fn encode_long(i: i64, buffer: &mut Vec<u8>) {
+pub(crate) fn encode_long(i: i64, buffer: &mut Vec<u8>) {
     zig_i64(i, buffer)
 }
 
-fn encode_int(i: i32, buffer: &mut Vec<u8>) {
+pub(crate) fn encode_int(i: i32, buffer: &mut Vec<u8>) {
     zig_i32(i, buffer)
 }
 
 /// Encode a `Value` into avro format.
 ///
-/// **NOTE** This will not perform schema validation. The value is assumed to
-/// be valid with regards to the schema. Schema are needed only to guide the
-/// encoding for complex type values.
-pub fn encode_ref(value: &Value, schema: &Schema, buffer: &mut Vec<u8>) {
-    match value {
-        Value::Null => (),
-        Value::Boolean(b) => buffer.push(if *b { 1u8 } else { 0u8 }),
-        Value::Int(i) => encode_int(*i, buffer),
-        Value::Long(i) => encode_long(*i, buffer),
-        Value::Float(x) => buffer.extend_from_slice(&unsafe { transmute::<f32, [u8; 4]>(*x) }),
-        Value::Double(x) => buffer.extend_from_slice(&unsafe { transmute::<f64, [u8; 8]>(*x) }),
-        Value::Bytes(bytes) => encode_bytes(bytes, buffer),
-        Value::String(s) => match *schema {
-            Schema::String => {
-                encode_bytes(s, buffer);
-            },
-            Schema::Enum { ref symbols, .. } => {
-                if let Some(index) = symbols.iter().position(|item| item == s) {
-                    encode_int(index as i32, buffer);
-                }
-            },
-            _ => (),
+/// **NOTE** This will not perform schema validation. Schema are needed only to guide the
+/// encoding for complex type values, but values which cannot be encoded with the schema, such as
+/// a `string` for a `long` or a record missing fields, are reported as an `EncodeError`. The
+/// buffer might then contain part of the value.
+///
+/// The named types of the `Schema` are collected on every call: to encode several values, prefer
+/// a `Writer`, which collects them once.
+pub fn encode_ref(value: &Value, schema: &Schema, buffer: &mut Vec<u8>) -> Result<(), Error> {
+    Ok(encode_internal(value, schema, &schema.names(), false, buffer)?)
+}
+
+/// Encode the items of an array or a map as a single block followed by the empty block ending
+/// it. With `sized`, the number of items is written as a negative number followed by the size
+/// of the block in bytes, so that readers can skip it without decoding its items.
+fn encode_block<F>(
+    len: usize,
+    sized: bool,
+    buffer: &mut Vec<u8>,
+    encode_items: F,
+) -> Result<(), EncodeError>
+where
+    F: FnOnce(&mut Vec<u8>) -> Result<(), EncodeError>,
+{
+    if len > 0 {
+        if sized {
+            let start = buffer.len();
+            encode_items(buffer)?;
+            insert_block_header(len, start, buffer);
+        } else {
+            encode_long(len as i64, buffer);
+            encode_items(buffer)?;
+        }
+    }
+    buffer.push(0u8);
+    Ok(())
+}
+
+/// Insert at `start` the header of a block of `len` items written in `buffer` from `start`: the
+/// number of items as a negative number, followed by the size of the block in bytes.
+pub(crate) fn insert_block_header(len: usize, start: usize, buffer: &mut Vec<u8>) {
+    let mut header = Vec::new();
+    encode_long(-(len as i64), &mut header);
+    encode_long((buffer.len() - start) as i64, &mut header);
+    buffer.splice(start..start, header);
+}
+
+/// Encode a `Value` into avro format, following references to named types through `names`.
+///
+/// `names` are the named types of the root `Schema`, as given by `Schema::names`, which callers
+/// encoding several values with the same `Schema`, like `Writer`, collect once.
+///
+/// With `sized_blocks`, arrays and maps are written with the size of their block in bytes.
+pub(crate) fn encode_internal(
+    value: &Value,
+    schema: &Schema,
+    names: &Names,
+    sized_blocks: bool,
+    buffer: &mut Vec<u8>,
+) -> Result<(), EncodeError> {
+    let schema = schema.resolve_ref(names).ok_or_else(|| {
+        EncodeError::new(format!("unresolved schema reference {}", describe(schema)))
+    })?;
+
+    match (value, schema) {
+        (Value::Null, Schema::Null) => (),
+        (Value::Boolean(b), Schema::Boolean) => buffer.push(if *b { 1u8 } else { 0u8 }),
+        (Value::Int(i), Schema::Int)
+        | (Value::Date(i), Schema::Date)
+        | (Value::TimeMillis(i), Schema::TimeMillis) => encode_int(*i, buffer),
+        (Value::Long(i), Schema::Long)
+        | (Value::TimeMicros(i), Schema::TimeMicros)
+        | (Value::TimestampMillis(i), Schema::TimestampMillis)
+        | (Value::TimestampMicros(i), Schema::TimestampMicros)
+        | (Value::LocalTimestampMillis(i), Schema::LocalTimestampMillis)
+        | (Value::LocalTimestampMicros(i), Schema::LocalTimestampMicros) => encode_long(*i, buffer),
+        (Value::Float(x), Schema::Float) => {
+            buffer.extend_from_slice(&unsafe { transmute::<f32, [u8; 4]>(*x) })
+        },
+        (Value::Double(x), Schema::Double) => {
+            buffer.extend_from_slice(&unsafe { transmute::<f64, [u8; 8]>(*x) })
         },
-        Value::Fixed(_, bytes) => buffer.extend(bytes),
-        Value::Enum(i, _) => encode_int(*i, buffer),
-        Value::Union(item) => {
-            if let Schema::Union(ref inner) = *schema {
-                // Find the schema that is matched here. Due to validation, this should always
-                // return a value.
-                let (idx, inner_schema) = inner
-                    .find_schema(item)
-                    .expect("Invalid Union validation occurred");
-                encode_long(idx as i64, buffer);
-                encode_ref(&*item, inner_schema, buffer);
+        (Value::Bytes(bytes), Schema::Bytes) => encode_bytes(bytes, buffer),
+        (Value::String(s), Schema::String) | (Value::String(s), Schema::Uuid) => {
+            encode_bytes(s, buffer)
+        },
+        (Value::String(s), Schema::Enum { symbols, .. }) => {
+            match symbols.iter().position(|item| item == s) {
+                Some(index) => encode_int(index as i32, buffer),
+                None => return Err(EncodeError::new(format!("unknown enum symbol {}", s))),
             }
         },
-        Value::Array(items) => {
-            if let Schema::Array(ref inner) = *schema {
-                if items.len() > 0 {
-                    encode_long(items.len() as i64, buffer);
-                    for item in items.iter() {
-                        encode_ref(item, inner, buffer);
-                    }
-                }
-                buffer.push(0u8);
+        (Value::Fixed(_, bytes), Schema::Fixed { size, .. }) => {
+            if bytes.len() != *size {
+                return Err(EncodeError::new(format!(
+                    "fixed of {} bytes instead of {}",
+                    bytes.len(),
+                    size
+                )))
             }
+            buffer.extend(bytes)
         },
-        Value::Map(items) => {
-            if let Schema::Map(ref inner) = *schema {
-                if items.len() > 0 {
-                    encode_long(items.len() as i64, buffer);
-                    for (key, value) in items {
-                        encode_bytes(key, buffer);
-                        encode_ref(value, inner, buffer);
-                    }
-                }
-                buffer.push(0u8);
+        (Value::Uuid(uuid), Schema::Uuid) => encode_bytes(&format_uuid(uuid), buffer),
+        (
+            Value::Duration {
+                months,
+                days,
+                millis,
+            },
+            Schema::Duration { .. },
+        ) => buffer.extend_from_slice(&duration_to_bytes(*months, *days, *millis)),
+        (Value::Decimal(decimal), Schema::Decimal { inner, .. }) => {
+            match inner.resolve_ref(names) {
+                Some(Schema::Fixed { size, .. }) => {
+                    let bytes = decimal.to_sign_extended_bytes(*size).ok_or_else(|| {
+                        EncodeError::new(format!("decimal does not fit in {} bytes", size))
+                    })?;
+                    buffer.extend(bytes)
+                },
+                Some(Schema::Bytes) => encode_bytes(decimal.as_bytes(), buffer),
+                _ => {
+                    return Err(EncodeError::new(format!(
+                        "decimal cannot be encoded as {}",
+                        describe(inner)
+                    )))
+                },
+            }
+        },
+        (Value::Enum(i, _), Schema::Enum { symbols, .. }) => {
+            if *i < 0 || *i as usize >= symbols.len() {
+                return Err(EncodeError::new(format!("no enum symbol at position {}", i)))
             }
+            encode_int(*i, buffer)
+        },
+        (Value::Union(idx, item), Schema::Union(inner)) => {
+            let inner_schema = inner.variants().get(*idx as usize).ok_or_else(|| {
+                EncodeError::new(format!("no union variant at position {}", idx))
+            })?;
+            encode_long(i64::from(*idx), buffer);
+            encode_internal(item, inner_schema, names, sized_blocks, buffer)?
+        },
+        (Value::Array(items), Schema::Array(inner)) => {
+            encode_block(items.len(), sized_blocks, buffer, |buffer| {
+                items.iter().enumerate().try_for_each(|(i, item)| {
+                    encode_internal(item, inner, names, sized_blocks, buffer)
+                        .map_err(|e| e.within(&format!("[{}]", i)))
+                })
+            })?
+        },
+        (Value::Map(items), Schema::Map(inner)) => {
+            encode_block(items.len(), sized_blocks, buffer, |buffer| {
+                items.iter().try_for_each(|(key, value)| {
+                    encode_bytes(key, buffer);
+                    encode_internal(value, inner, names, sized_blocks, buffer)
+                        .map_err(|e| e.within(&format!("{{{}}}", key)))
+                })
+            })?
         },
-        Value::Record(fields) => {
-            if let Schema::Record {
-                fields: ref schema_fields,
+        (
+            Value::Record(fields),
+            Schema::Record {
+                fields: schema_fields,
                 ..
-            } = *schema
-            {
-                for (i, &(_, ref value)) in fields.iter().enumerate() {
-                    encode_ref(value, &schema_fields[i].schema, buffer);
+            },
+        ) => {
+            if fields.len() != schema_fields.len() {
+                return Err(EncodeError::new(format!(
+                    "record of {} fields instead of {}",
+                    fields.len(),
+                    schema_fields.len()
+                )))
+            }
+            for ((name, value), field) in fields.iter().zip(schema_fields.iter()) {
+                if *name != field.name {
+                    return Err(EncodeError::new(format!(
+                        "field {} instead of {}",
+                        name, field.name
+                    )))
                 }
+                encode_internal(value, &field.schema, names, sized_blocks, buffer)
+                    .map_err(|e| e.within(&format!(".{}", field.name)))?;
             }
         },
+        _ => {
+            return Err(EncodeError::new(format!(
+                "{} value cannot be encoded as {}",
+                describe_value(value),
+                describe(schema)
+            )))
+        },
     }
+    Ok(())
 }
 
-pub fn encode_to_vec(value: &Value, schema: &Schema) -> Vec<u8> {
+pub fn encode_to_vec(value: &Value, schema: &Schema) -> Result<Vec<u8>, Error> {
     let mut buffer = Vec::new();
-    encode(&value, schema, &mut buffer);
-    buffer
+    encode(&value, schema, &mut buffer)?;
+    Ok(buffer)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use decimal::Decimal;
     use std::collections::HashMap;
 
+    fn encode_error(value: Value, schema: &str) -> EncodeError {
+        let schema = Schema::parse_str(schema).unwrap();
+        let mut buf = Vec::new();
+        encode(&value, &schema, &mut buf)
+            .unwrap_err()
+            .downcast::<EncodeError>()
+            .unwrap()
+    }
+
     #[test]
     fn test_encode_empty_array() {
         let mut buf = Vec::new();
@@ -121,7 +254,7 @@ mod tests {
             &Value::Array(empty),
             &Schema::Array(Box::new(Schema::Int)),
             &mut buf,
-        );
+        ).unwrap();
         assert_eq!(vec![0u8], buf);
     }
 
@@ -133,7 +266,97 @@ mod tests {
             &Value::Map(empty),
             &Schema::Map(Box::new(Schema::Int)),
             &mut buf,
-        );
+        ).unwrap();
         assert_eq!(vec![0u8], buf);
     }
+
+    #[test]
+    fn test_encode_mismatches() {
+        let error = encode_error(Value::String("a".to_owned()), r#""long""#);
+        assert_eq!(error.path(), ".");
+        assert_eq!(error.message(), "string value cannot be encoded as long");
+        assert_eq!(
+            error.to_string(),
+            "Encoding error at .: string value cannot be encoded as long"
+        );
+
+        let error = encode_error(
+            Value::String("c".to_owned()),
+            r#"{"type": "enum", "name": "e", "symbols": ["a", "b"]}"#,
+        );
+        assert_eq!(error.message(), "unknown enum symbol c");
+
+        let error = encode_error(
+            Value::Enum(2, "c".to_owned()),
+            r#"{"type": "enum", "name": "e", "symbols": ["a", "b"]}"#,
+        );
+        assert_eq!(error.message(), "no enum symbol at position 2");
+
+        let error = encode_error(
+            Value::Fixed(3, vec![0, 0, 0]),
+            r#"{"type": "fixed", "name": "f", "size": 2}"#,
+        );
+        assert_eq!(error.message(), "fixed of 3 bytes instead of 2");
+
+        let error = encode_error(
+            Value::Decimal(Decimal::from(128)),
+            r#"{"type": "fixed", "name": "f", "size": 1, "logicalType": "decimal", "precision": 2}"#,
+        );
+        assert_eq!(error.message(), "decimal does not fit in 1 bytes");
+
+        let error = encode_error(Value::Union(2, Box::new(Value::Null)), r#"["null", "int"]"#);
+        assert_eq!(error.message(), "no union variant at position 2");
+    }
+
+    #[test]
+    fn test_encode_records_with_wrong_fields() {
+        let schema = r#"
+            {
+                "type": "record",
+                "name": "r",
+                "fields": [
+                    {"name": "a", "type": "long"},
+                    {"name": "b", "type": {"type": "array", "items": {"type": "map", "values": [
+                        "null",
+                        {"type": "record", "name": "s", "fields": [{"name": "c", "type": "int"}]}
+                    ]}}}
+                ]
+            }
+        "#;
+        let short = Value::Record(vec![("a".to_owned(), Value::Long(1))]);
+        let error = encode_error(short, schema);
+        assert_eq!(error.path(), ".");
+        assert_eq!(error.message(), "record of 1 fields instead of 2");
+
+        let long = Value::Record(vec![
+            ("a".to_owned(), Value::Long(1)),
+            ("b".to_owned(), Value::Array(vec![])),
+            ("c".to_owned(), Value::Null),
+        ]);
+        let error = encode_error(long, schema);
+        assert_eq!(error.message(), "record of 3 fields instead of 2");
+
+        let swapped = Value::Record(vec![
+            ("b".to_owned(), Value::Array(vec![])),
+            ("a".to_owned(), Value::Long(1)),
+        ]);
+        let error = encode_error(swapped, schema);
+        assert_eq!(error.message(), "field b instead of a");
+
+        let mut map = HashMap::new();
+        map.insert(
+            "key".to_owned(),
+            Value::Union(1, Box::new(Value::Record(vec![("c".to_owned(), Value::Long(1))]))),
+        );
+        let nested = Value::Record(vec![
+            ("a".to_owned(), Value::Long(1)),
+            (
+                "b".to_owned(),
+                Value::Array(vec![Value::Map(HashMap::new()), Value::Map(map)]),
+            ),
+        ]);
+        let error = encode_error(nested, schema);
+        assert_eq!(error.path(), ".b[1]{key}.c");
+        assert_eq!(error.message(), "long value cannot be encoded as int");
+    }
 }
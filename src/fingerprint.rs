@@ -0,0 +1,127 @@
+//! Logic for fingerprinting schemas.
+use std::fmt;
+
+use md5;
+use sha2::{Digest, Sha256};
+
+/// The algorithms which can be used to fingerprint a [Schema](../schema/enum.Schema.html).
+///
+/// More information about schema fingerprints can be found in the
+/// [Avro Specification](https://avro.apache.org/docs/current/spec.html#schema_fingerprints)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FingerprintAlgorithm {
+    /// The 64-bit Rabin fingerprint (`CRC-64-AVRO`), recommended by the specification for
+    /// fingerprints used as keys of small schema caches.
+    Rabin,
+    /// The 128-bit MD5 message digest.
+    Md5,
+    /// The 256-bit SHA-256 message digest.
+    Sha256,
+}
+
+/// The fingerprint of a [Schema](../schema/enum.Schema.html), computed over its Parsing
+/// Canonical Form.
+///
+/// Fingerprints can be compared, hashed, and are displayed as lowercase hexadecimal strings.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SchemaFingerprint {
+    algorithm: FingerprintAlgorithm,
+    bytes: Vec<u8>,
+}
+
+impl SchemaFingerprint {
+    /// Compute the fingerprint of `data` using `algorithm`.
+    pub(crate) fn compute(algorithm: FingerprintAlgorithm, data: &[u8]) -> Self {
+        let bytes = match algorithm {
+            // As in the reference implementation, the 64 bits fingerprint is little-endian.
+            FingerprintAlgorithm::Rabin => rabin(data).to_le_bytes().to_vec(),
+            FingerprintAlgorithm::Md5 => md5::compute(data).0.to_vec(),
+            FingerprintAlgorithm::Sha256 => Sha256::digest(data).to_vec(),
+        };
+
+        SchemaFingerprint { algorithm, bytes }
+    }
+
+    /// Returns the algorithm used to compute this fingerprint.
+    pub fn algorithm(&self) -> FingerprintAlgorithm {
+        self.algorithm
+    }
+
+    /// Returns the bytes of this fingerprint.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl fmt::Display for SchemaFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.bytes {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+const RABIN_EMPTY: u64 = 0xc15d_213a_a4d7_a795;
+
+/// The Rabin fingerprint of every byte, computed at compile time.
+const RABIN_TABLE: [u64; 256] = rabin_table();
+
+const fn rabin_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut i = 0;
+    while i < table.len() {
+        let mut fp = i as u64;
+        let mut j = 0;
+        while j < 8 {
+            fp = (fp >> 1) ^ (RABIN_EMPTY & (fp & 1).wrapping_neg());
+            j += 1;
+        }
+        table[i] = fp;
+        i += 1;
+    }
+    table
+}
+
+/// The 64-bit Rabin fingerprint of `data`, as defined by the Avro specification.
+fn rabin(data: &[u8]) -> u64 {
+    data.iter().fold(RABIN_EMPTY, |fp, byte| {
+        (fp >> 8) ^ RABIN_TABLE[((fp ^ u64::from(*byte)) & 0xff) as usize]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rabin() {
+        // Reference values from the Apache Avro test suite, as signed 64 bits integers.
+        let fingerprints = vec![
+            (r#""null""#, 7195948357588979594i64),
+            (r#""boolean""#, -6970731678124411036),
+            (r#""int""#, 8247732601305521295),
+            (r#""long""#, -3434872931120570953),
+            (r#""float""#, 5583340709985441680),
+            (r#""double""#, -8181574048448539266),
+            (r#""bytes""#, 5746618253357095269),
+            (r#""string""#, -8142146995180207161),
+            (
+                r#"{"name":"foo","type":"record","fields":[]}"#,
+                -4824392279771201922,
+            ),
+        ];
+
+        for (canonical_form, expected) in fingerprints {
+            assert_eq!(rabin(canonical_form.as_bytes()) as i64, expected);
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let fingerprint = SchemaFingerprint::compute(FingerprintAlgorithm::Md5, b"\"null\"");
+        assert_eq!(fingerprint.to_string(), "9b41ef67651c18488a8b08bb67c75699");
+        assert_eq!(fingerprint.algorithm(), FingerprintAlgorithm::Md5);
+        assert_eq!(fingerprint.as_bytes().len(), 16);
+    }
+}
@@ -210,6 +210,15 @@
 //! let mut value = "foo".to_string();
 //! ```
 //!
+//! Rust enums whose variants carry no data are written as Avro *Enums*, by symbol. Other enums
+//! are written as *Unions*: struct and tuple variants as the *Record* named after the variant (the
+//! fields of tuple variants in order), and newtype variants as their value. The branch of the union
+//! is the named type called after the variant if there is one, or else the one at the position of
+//! the variant; unit variants are then written as a *Record* without fields. `to_value` has no
+//! schema to find that branch with, so it converts such variants to their value alone, and
+//! `to_value_with_schema` should be used instead; likewise, `from_value_with_schema` reads enums
+//! from *Unions*.
+//!
 //! ## Using codecs to compress data
 //!
 //! Avro supports three different compression codecs when encoding data:
@@ -427,6 +436,10 @@
 //! # }
 //! ```
 //!
+//! The values can also be deserialized straight from the data, without going through a `Value`
+//! first, with `Reader::into_typed`; fields which `Test` does not have are then skipped. A single
+//! value can be read that way with `from_avro_slice`, borrowing its strings from the data.
+//!
 //! # Putting everything together
 //!
 //! The following is an example of how to combine everything showed so far and it is meant to be a
@@ -495,11 +508,13 @@ extern crate failure;
 #[macro_use]
 extern crate failure_derive;
 extern crate libflate;
+extern crate md5;
 extern crate rand;
 #[macro_use]
 extern crate serde;
 
 extern crate serde_json;
+extern crate sha2;
 #[cfg(feature = "snappy")]
 extern crate snap;
 
@@ -507,13 +522,22 @@ extern crate snap;
 #[cfg(test)]
 #[macro_use]
 extern crate serde_derive;
+#[cfg(test)]
+extern crate quickcheck;
+#[cfg(test)]
+extern crate uuid;
 
 mod codec;
 mod de;
+mod de_schema;
+mod decimal;
 mod decode;
 mod encode;
+mod fingerprint;
 mod reader;
+mod resolve;
 mod ser;
+mod ser_schema;
 mod util;
 mod writer;
 
@@ -521,12 +545,15 @@ pub mod schema;
 pub mod types;
 
 pub use codec::Codec;
-pub use de::from_value;
-pub use reader::{from_avro_datum, Reader};
+pub use de::{from_value, from_value_with_schema};
+pub use decimal::Decimal;
+pub use fingerprint::{FingerprintAlgorithm, SchemaFingerprint};
+pub use reader::{from_avro_datum, from_avro_slice, Reader, TypedReader};
+pub use resolve::ResolvedSchema;
 pub use schema::{ParseSchemaError, Schema};
-pub use ser::to_value;
+pub use ser::{to_value, to_value_with_schema};
 pub use types::SchemaResolutionError;
-pub use util::{max_allocation_bytes, DecodeError};
+pub use util::{max_allocation_bytes, DecodeError, EncodeError};
 pub use writer::{to_avro_datum, ValidationError, Writer};
 
 #[cfg(test)]
@@ -688,6 +715,80 @@ mod tests {
         assert!(reader.next().is_none());
     }
 
+    //TODO: move where it fits better
+    #[test]
+    fn test_alias_resolution() {
+        let writer_raw_schema = r#"
+            {
+                "type": "record",
+                "name": "test",
+                "fields": [
+                    {"name": "a", "type": "long"},
+                    {
+                        "name": "b",
+                        "type": [
+                            "null",
+                            {
+                                "type": "record",
+                                "name": "point",
+                                "fields": [{"name": "x", "type": "int"}]
+                            }
+                        ]
+                    }
+                ]
+            }
+        "#;
+        let reader_raw_schema = r#"
+            {
+                "type": "record",
+                "name": "renamed",
+                "aliases": ["test"],
+                "fields": [
+                    {"name": "id", "type": "long", "aliases": ["a"]},
+                    {
+                        "name": "b",
+                        "type": [
+                            "null",
+                            {
+                                "type": "record",
+                                "name": "position",
+                                "aliases": ["point"],
+                                "fields": [{"name": "y", "type": "long", "aliases": ["x"]}]
+                            }
+                        ]
+                    }
+                ]
+            }
+        "#;
+        let writer_schema = Schema::parse_str(writer_raw_schema).unwrap();
+        let reader_schema = Schema::parse_str(reader_raw_schema).unwrap();
+        let mut writer = Writer::with_codec(&writer_schema, Vec::new(), Codec::Null);
+        let mut record = Record::new(writer.schema()).unwrap();
+        record.put("a", 27i64);
+        record.put(
+            "b",
+            Value::Union(1, Box::new(Value::Record(vec![("x".to_string(), Value::Int(3))]))),
+        );
+        writer.append(record).unwrap();
+        writer.flush().unwrap();
+        let input = writer.into_inner();
+        let mut reader = Reader::with_schema(&reader_schema, &input[..]).unwrap();
+        assert_eq!(
+            reader.next().unwrap().unwrap(),
+            Value::Record(vec![
+                ("id".to_string(), Value::Long(27)),
+                (
+                    "b".to_string(),
+                    Value::Union(
+                        1,
+                        Box::new(Value::Record(vec![("y".to_string(), Value::Long(3))]))
+                    )
+                ),
+            ])
+        );
+        assert!(reader.next().is_none());
+    }
+
     //TODO: move where it fits better
     #[test]
     fn test_enum_no_reader_schema() {
@@ -751,4 +852,283 @@ mod tests {
         let value = from_avro_datum(&schema, &mut &illformed[..], None);
         assert!(value.is_err());
     }
+
+    #[test]
+    fn test_recursive_record_round_trip() {
+        let raw_schema = r#"
+            {
+                "type": "record",
+                "name": "LongList",
+                "namespace": "com.acme",
+                "fields": [
+                    {"name": "value", "type": "long"},
+                    {"name": "next", "type": ["null", "com.acme.LongList"]}
+                ]
+            }
+        "#;
+        let schema = Schema::parse_str(raw_schema).unwrap();
+        let list = Value::Record(vec![
+            ("value".to_string(), Value::Long(1)),
+            (
+                "next".to_string(),
+                Value::Union(1, Box::new(Value::Record(vec![
+                    ("value".to_string(), Value::Long(2)),
+                    ("next".to_string(), Value::Union(0, Box::new(Value::Null))),
+                ]))),
+            ),
+        ]);
+
+        let mut writer = Writer::with_codec(&schema, Vec::new(), Codec::Null);
+        writer.append(list.clone()).unwrap();
+        writer.flush().unwrap();
+        let input = writer.into_inner();
+
+        let mut reader = Reader::with_schema(&schema, &input[..]).unwrap();
+        assert_eq!(reader.writer_schema(), &schema);
+        assert_eq!(reader.next().unwrap().unwrap(), list);
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_decimal_round_trip() {
+        let raw_schema = r#"
+            {
+                "type": "record",
+                "name": "payment",
+                "fields": [
+                    {
+                        "name": "amount",
+                        "type": {"type": "bytes", "logicalType": "decimal", "precision": 10, "scale": 2}
+                    },
+                    {
+                        "name": "fee",
+                        "type": {
+                            "type": "fixed",
+                            "name": "fee",
+                            "size": 3,
+                            "logicalType": "decimal",
+                            "precision": 6,
+                            "scale": 2
+                        }
+                    }
+                ]
+            }
+        "#;
+        let schema = Schema::parse_str(raw_schema).unwrap();
+        let mut record = Record::new(&schema).unwrap();
+        record.put("amount", Decimal::from(-123456));
+        record.put("fee", Decimal::from(-250));
+
+        let encoded = to_avro_datum(&schema, record.clone()).unwrap();
+        // 3 bytes long, unscaled value in minimal two's-complement form
+        assert_eq!(&encoded[..4], &[0x06, 0xFE, 0x1D, 0xC0]);
+        // sign extended to the size of the fixed
+        assert_eq!(&encoded[4..], &[0xFF, 0xFF, 0x06]);
+
+        let decoded = from_avro_datum(&schema, &mut &encoded[..], None).unwrap();
+        assert_eq!(decoded, Value::Record(record.fields));
+    }
+
+    #[test]
+    fn test_date_and_time_round_trip() {
+        let raw_schema = r#"
+            {
+                "type": "record",
+                "name": "appointment",
+                "fields": [
+                    {"name": "day", "type": {"type": "int", "logicalType": "date"}},
+                    {"name": "start", "type": {"type": "int", "logicalType": "time-millis"}},
+                    {"name": "end", "type": {"type": "long", "logicalType": "time-micros"}}
+                ]
+            }
+        "#;
+        let schema = Schema::parse_str(raw_schema).unwrap();
+        let appointment = Value::Record(vec![
+            ("day".to_string(), Value::Date(17_800)),
+            ("start".to_string(), Value::TimeMillis(36_000_000)),
+            ("end".to_string(), Value::TimeMicros(37_800_000_000)),
+        ]);
+
+        let mut writer = Writer::new(&schema, Vec::new());
+        writer.append(appointment.clone()).unwrap();
+        writer.flush().unwrap();
+        let input = writer.into_inner();
+
+        // the header keeps the logical types
+        let mut reader = Reader::new(&input[..]).unwrap();
+        assert_eq!(reader.writer_schema(), &schema);
+        assert_eq!(reader.next().unwrap().unwrap(), appointment);
+    }
+
+    #[test]
+    fn test_timestamp_round_trip() {
+        let raw_schema = r#"
+            {
+                "type": "record",
+                "name": "event",
+                "fields": [
+                    {"name": "at", "type": {"type": "long", "logicalType": "timestamp-millis"}},
+                    {"name": "at_us", "type": {"type": "long", "logicalType": "timestamp-micros"}},
+                    {
+                        "name": "local",
+                        "type": {"type": "long", "logicalType": "local-timestamp-millis"}
+                    },
+                    {
+                        "name": "local_us",
+                        "type": {"type": "long", "logicalType": "local-timestamp-micros"}
+                    }
+                ]
+            }
+        "#;
+        let schema = Schema::parse_str(raw_schema).unwrap();
+        let event = Value::Record(vec![
+            ("at".to_string(), Value::TimestampMillis(1_540_000_000_123)),
+            ("at_us".to_string(), Value::TimestampMicros(1_540_000_000_123_456)),
+            ("local".to_string(), Value::LocalTimestampMillis(-1)),
+            ("local_us".to_string(), Value::LocalTimestampMicros(0)),
+        ]);
+
+        let mut writer = Writer::new(&schema, Vec::new());
+        writer.append(event.clone()).unwrap();
+        writer.flush().unwrap();
+        let input = writer.into_inner();
+
+        // the header keeps the logical types
+        let mut reader = Reader::new(&input[..]).unwrap();
+        assert_eq!(reader.writer_schema(), &schema);
+        assert_eq!(reader.next().unwrap().unwrap(), event);
+    }
+
+    #[test]
+    fn test_uuid_serde_round_trip() {
+        #[derive(Debug, Deserialize, PartialEq, Serialize)]
+        struct Session {
+            id: uuid::Uuid,
+            parent: Option<uuid::Uuid>,
+        }
+
+        let raw_schema = r#"
+            {
+                "type": "record",
+                "name": "session",
+                "fields": [
+                    {"name": "id", "type": {"type": "string", "logicalType": "uuid"}},
+                    {"name": "parent", "type": ["null", {"type": "string", "logicalType": "uuid"}]}
+                ]
+            }
+        "#;
+        let schema = Schema::parse_str(raw_schema).unwrap();
+        let session = Session {
+            id: uuid::Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000").unwrap(),
+            parent: Some(uuid::Uuid::parse_str("6ba7b810-9dad-11d1-80b4-00c04fd430c8").unwrap()),
+        };
+
+        let mut writer = Writer::new(&schema, Vec::new());
+        writer.append_ser(&session).unwrap();
+        writer.flush().unwrap();
+        let input = writer.into_inner();
+
+        let mut reader = Reader::new(&input[..]).unwrap();
+        assert_eq!(reader.writer_schema(), &schema);
+        let value = reader.next().unwrap().unwrap();
+        assert_eq!(
+            value,
+            Value::Record(vec![
+                ("id".to_string(), Value::Uuid(*session.id.as_bytes())),
+                (
+                    "parent".to_string(),
+                    Value::Union(1, Box::new(Value::Uuid(*session.parent.unwrap().as_bytes())))
+                ),
+            ])
+        );
+        assert_eq!(from_value::<Session>(&value).unwrap(), session);
+    }
+
+    #[test]
+    fn test_duration_round_trip() {
+        let raw_schema = r#"
+            {
+                "type": "record",
+                "name": "lease",
+                "fields": [
+                    {
+                        "name": "term",
+                        "type": {
+                            "type": "fixed",
+                            "name": "interval",
+                            "size": 12,
+                            "logicalType": "duration"
+                        }
+                    },
+                    {"name": "renewal", "type": "interval"}
+                ]
+            }
+        "#;
+        let schema = Schema::parse_str(raw_schema).unwrap();
+        let lease = Value::Record(vec![
+            (
+                "term".to_string(),
+                Value::Duration {
+                    months: 12,
+                    days: 0,
+                    millis: 0,
+                },
+            ),
+            (
+                "renewal".to_string(),
+                Value::Duration {
+                    months: 1,
+                    days: 15,
+                    millis: 3_600_000,
+                },
+            ),
+        ]);
+
+        let mut writer = Writer::new(&schema, Vec::new());
+        writer.append(lease.clone()).unwrap();
+        writer.flush().unwrap();
+        let input = writer.into_inner();
+
+        let mut reader = Reader::new(&input[..]).unwrap();
+        assert_eq!(reader.writer_schema(), &schema);
+        assert_eq!(reader.next().unwrap().unwrap(), lease);
+    }
+
+    #[test]
+    fn test_custom_attributes_round_trip() {
+        let raw_schema = r#"
+            {
+                "type": "record",
+                "name": "user",
+                "connect.name": "com.example.user",
+                "fields": [
+                    {"name": "email", "type": {"type": "string", "avro.java.string": "String"}},
+                    {"name": "tags", "type": {"type": "array", "items": "string", "x": true}},
+                    {"name": "referrer", "type": ["null", {"type": "string", "x": true}]}
+                ]
+            }
+        "#;
+        let schema = Schema::parse_str(raw_schema).unwrap();
+        let user = Value::Record(vec![
+            ("email".to_string(), Value::String("foo@example.com".to_string())),
+            (
+                "tags".to_string(),
+                Value::Array(vec![Value::String("admin".to_string())]),
+            ),
+            (
+                "referrer".to_string(),
+                Value::Union(1, Box::new(Value::String("bar@example.com".to_string()))),
+            ),
+        ]);
+
+        let mut writer = Writer::new(&schema, Vec::new());
+        writer.append(user.clone()).unwrap();
+        writer.flush().unwrap();
+        let input = writer.into_inner();
+
+        // the header keeps the custom attributes
+        let mut reader = Reader::new(&input[..]).unwrap();
+        assert_eq!(reader.writer_schema(), &schema);
+        assert_eq!(reader.next().unwrap().unwrap(), user);
+    }
 }
@@ -1,11 +1,16 @@
 //! Logic handling reading from Avro format at user level.
 use std::io::{ErrorKind, Read};
+use std::marker::PhantomData;
 use std::str::{from_utf8, FromStr};
 
 use failure::Error;
+use serde::de::{Deserialize, DeserializeOwned};
 use serde_json::from_slice;
 
+use de::from_value_with_schema;
+use de_schema::deserialize;
 use decode::decode;
+use resolve::ResolvedSchema;
 use schema::ParseSchemaError;
 use schema::Schema;
 use types::Value;
@@ -55,22 +60,13 @@ impl<R: Read> Block<R> {
         }
 
         if let Value::Map(meta) = decode(&meta_schema, &mut self.reader)? {
-            // TODO: surface original parse schema errors instead of coalescing them here
-            let schema = meta
-                .get("avro.schema")
-                .and_then(|bytes| {
-                    if let Value::Bytes(ref bytes) = *bytes {
-                        from_slice(bytes.as_ref()).ok()
-                    } else {
-                        None
-                    }
-                })
-                .and_then(|json| Schema::parse(&json).ok());
-            if let Some(schema) = schema {
-                self.writer_schema = schema;
-            } else {
-                return Err(ParseSchemaError::new("unable to parse schema").into())
-            }
+            let json = match meta.get("avro.schema") {
+                Some(Value::Bytes(bytes)) => from_slice(bytes)?,
+                _ => return Err(ParseSchemaError::new("no schema in header").into()),
+            };
+            // Files written by other implementations may not conform strictly to the
+            // specification, their schema is parsed leniently to read them anyway.
+            self.writer_schema = Schema::parse_lenient(&json)?;
 
             if let Some(codec) = meta
                 .get("avro.codec")
@@ -152,7 +148,16 @@ impl<R: Read> Block<R> {
         self.len() == 0
     }
 
-    fn read_next(&mut self, read_schema: Option<&Schema>) -> Result<Option<Value>, Error> {
+    fn read_next(&mut self, resolved: &ResolvedSchema) -> Result<Option<Value>, Error> {
+        self.read_next_with(|_, block_bytes| resolved.decode(block_bytes))
+    }
+
+    /// Read the next object of the block with `read`, given the writer `Schema` and the bytes
+    /// left in the block.
+    fn read_next_with<T, F>(&mut self, read: F) -> Result<Option<T>, Error>
+    where
+        F: FnOnce(&Schema, &mut &[u8]) -> Result<T, Error>,
+    {
         if self.is_empty() {
             self.read_block_next()?;
             if self.is_empty() {
@@ -162,7 +167,7 @@ impl<R: Read> Block<R> {
 
         let mut block_bytes = &self.buf[self.buf_idx..];
         let b_original = block_bytes.len();
-        let item = from_avro_datum(&self.writer_schema, &mut block_bytes, read_schema)?;
+        let item = read(&self.writer_schema, &mut block_bytes)?;
         self.buf_idx += b_original - block_bytes.len();
         self.message_count -= 1;
         Ok(Some(item))
@@ -188,21 +193,30 @@ pub struct Reader<'a, R> {
     block: Block<R>,
     reader_schema: Option<&'a Schema>,
     errored: bool,
-    should_resolve_schema: bool,
+    // How to read the data as data of the reader schema, or of the writer schema if none was
+    // given, computed once rather than for every value.
+    resolved_schema: ResolvedSchema,
+    // Whether the reader and writer schemas disagree.
+    resolving: bool,
 }
 
 impl<'a, R: Read> Reader<'a, R> {
     /// Creates a `Reader` given something implementing the `io::Read` trait to read from.
     /// No reader `Schema` will be set.
     ///
+    /// How to decode the writer `Schema`, with the named types it refers to, is computed once, as
+    /// a `ResolvedSchema`, and then applied to every value.
+    ///
     /// **NOTE** The avro header is going to be read automatically upon creation of the `Reader`.
     pub fn new(reader: R) -> Result<Reader<'a, R>, Error> {
         let block = Block::new(reader)?;
+        let resolved_schema = ResolvedSchema::new(&block.writer_schema, &block.writer_schema)?;
         let reader = Reader {
             block,
             reader_schema: None,
             errored: false,
-            should_resolve_schema: false,
+            resolved_schema,
+            resolving: false,
         };
         Ok(reader)
     }
@@ -210,18 +224,22 @@ impl<'a, R: Read> Reader<'a, R> {
     /// Creates a `Reader` given a reader `Schema` and something implementing the `io::Read` trait
     /// to read from.
     ///
+    /// The resolution of the writer `Schema` into the reader `Schema` is computed once, as a
+    /// `ResolvedSchema`, and then applied to every value while decoding it.
+    ///
     /// **NOTE** The avro header is going to be read automatically upon creation of the `Reader`.
     pub fn with_schema(schema: &'a Schema, reader: R) -> Result<Reader<'a, R>, Error> {
         let block = Block::new(reader)?;
-        let mut reader = Reader {
+        let resolved_schema = ResolvedSchema::new(&block.writer_schema, schema)?;
+        // Check if the reader and writer schemas disagree.
+        let resolving = &block.writer_schema != schema;
+        Ok(Reader {
             block,
             reader_schema: Some(schema),
             errored: false,
-            should_resolve_schema: false,
-        };
-        // Check if the reader and writer schemas disagree.
-        reader.should_resolve_schema = reader.writer_schema() != schema;
-        Ok(reader)
+            resolved_schema,
+            resolving,
+        })
     }
 
     /// Get a reference to the writer `Schema`.
@@ -234,15 +252,21 @@ impl<'a, R: Read> Reader<'a, R> {
         self.reader_schema
     }
 
+    /// Turn the `Reader` into an iterator over values of type `T`, deserialized straight from
+    /// the data blocks instead of going through a `Value` first.
+    ///
+    /// When a reader `Schema` disagreeing with the writer `Schema` was given, values are still
+    /// resolved into a `Value` before being deserialized.
+    pub fn into_typed<T: DeserializeOwned>(self) -> TypedReader<'a, R, T> {
+        TypedReader {
+            reader: self,
+            target: PhantomData,
+        }
+    }
+
     #[inline]
     fn read_next(&mut self) -> Result<Option<Value>, Error> {
-        let read_schema = if self.should_resolve_schema {
-            self.reader_schema
-        } else {
-            None
-        };
-
-        self.block.read_next(read_schema)
+        self.block.read_next(&self.resolved_schema)
     }
 }
 
@@ -264,10 +288,71 @@ impl<'a, R: Read> Iterator for Reader<'a, R> {
     }
 }
 
+/// Iterator over the values of type `T` read by a [`Reader`](struct.Reader.html), created with
+/// `Reader::into_typed`.
+///
+/// ```no_run
+/// # #[macro_use] extern crate serde_derive;
+/// # extern crate avro_rs;
+/// # use avro_rs::Reader;
+/// # use std::io::Cursor;
+/// #[derive(Debug, Deserialize)]
+/// struct Test {
+///     a: i64,
+///     b: String,
+/// }
+///
+/// # fn main() {
+/// # let input = Cursor::new(Vec::<u8>::new());
+/// for test in Reader::new(input).unwrap().into_typed::<Test>() {
+///     println!("{:?}", test.unwrap());
+/// }
+/// # }
+/// ```
+pub struct TypedReader<'a, R, T> {
+    reader: Reader<'a, R>,
+    target: PhantomData<T>,
+}
+
+impl<'a, R: Read, T: DeserializeOwned> TypedReader<'a, R, T> {
+    fn read_next(&mut self) -> Result<Option<T>, Error> {
+        match self.reader.reader_schema {
+            Some(reader_schema) if self.reader.resolving => self
+                .reader
+                .read_next()?
+                .map(|value| from_value_with_schema(reader_schema, &value).map_err(Error::from))
+                .transpose(),
+            _ => self
+                .reader
+                .block
+                .read_next_with(|writer_schema, bytes| deserialize(writer_schema, bytes)),
+        }
+    }
+}
+
+impl<'a, R: Read, T: DeserializeOwned> Iterator for TypedReader<'a, R, T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // to prevent keep on reading after the first error occurs
+        if self.reader.errored {
+            return None
+        };
+        match self.read_next() {
+            Ok(opt) => opt.map(Ok),
+            Err(e) => {
+                self.reader.errored = true;
+                Some(Err(e))
+            },
+        }
+    }
+}
+
 /// Decode a `Value` encoded in Avro format given its `Schema` and anything implementing `io::Read`
 /// to read from.
 ///
-/// In case a reader `Schema` is provided, schema resolution will also be performed.
+/// In case a reader `Schema` is provided, schema resolution will also be performed. To decode
+/// several values, prefer computing the resolution once with a `ResolvedSchema`.
 ///
 /// **NOTE** This function has a quite small niche of usage and does NOT take care of reading the
 /// header and consecutive data blocks; use [`Reader`](struct.Reader.html) if you don't know what
@@ -277,18 +362,35 @@ pub fn from_avro_datum<R: Read>(
     reader: &mut R,
     reader_schema: Option<&Schema>,
 ) -> Result<Value, Error> {
-    let value = decode(writer_schema, reader)?;
     match reader_schema {
-        Some(ref schema) => value.resolve(schema),
-        None => Ok(value),
+        Some(schema) => ResolvedSchema::new(writer_schema, schema)?.decode(reader),
+        None => decode(writer_schema, reader),
     }
 }
 
+/// Deserialize a `T` straight from the bytes of a value encoded in Avro format given its
+/// `Schema`, borrowing strings and bytes from `bytes` when `T` allows it.
+///
+/// Fields which `T` does not have are skipped. No schema resolution is performed: to read data
+/// written with a different `Schema`, decode it with `from_avro_datum` and use
+/// `from_value_with_schema`.
+///
+/// **NOTE** Like `from_avro_datum`, this function does NOT take care of reading the header and
+/// consecutive data blocks; use [`Reader::into_typed`](struct.Reader.html#method.into_typed) for
+/// that instead.
+pub fn from_avro_slice<'de, T: Deserialize<'de>>(
+    writer_schema: &Schema,
+    bytes: &'de [u8],
+) -> Result<T, Error> {
+    deserialize(writer_schema, &mut &bytes[..])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::io::Cursor;
     use types::{Record, ToAvro};
+    use writer::Writer;
     use Reader;
 
     static SCHEMA: &'static str = r#"
@@ -344,7 +446,7 @@ mod tests {
 
         assert_eq!(
             from_avro_datum(&schema, &mut encoded, None).unwrap(),
-            Value::Union(Box::new(Value::Long(0)))
+            Value::Union(1, Box::new(Value::Long(0)))
         );
     }
 
@@ -368,6 +470,71 @@ mod tests {
         }
     }
 
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct TestRecord {
+        a: i64,
+        b: String,
+    }
+
+    #[test]
+    fn test_from_avro_slice() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Borrowed<'a> {
+            b: &'a str,
+        }
+
+        let schema = Schema::parse_str(SCHEMA).unwrap();
+        let encoded: &'static [u8] = &[54, 6, 102, 111, 111];
+        assert_eq!(
+            from_avro_slice::<TestRecord>(&schema, encoded).unwrap(),
+            TestRecord {
+                a: 27,
+                b: "foo".to_string(),
+            }
+        );
+        assert_eq!(
+            from_avro_slice::<Borrowed>(&schema, encoded).unwrap(),
+            Borrowed { b: "foo" }
+        );
+    }
+
+    #[test]
+    fn test_typed_reader_iterator() {
+        let expected = vec![
+            TestRecord {
+                a: 27,
+                b: "foo".to_string(),
+            },
+            TestRecord {
+                a: 42,
+                b: "bar".to_string(),
+            },
+        ];
+
+        let reader = Reader::new(ENCODED).unwrap().into_typed::<TestRecord>();
+        assert_eq!(reader.map(Result::unwrap).collect::<Vec<_>>(), expected);
+
+        // with a reader schema disagreeing with the writer schema
+        let schema = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "test",
+                "fields": [
+                    {"name": "b", "type": "string"},
+                    {"name": "a", "type": "long"},
+                    {"name": "c", "type": "int", "default": 0}
+                ]
+            }
+        "#,
+        )
+        .unwrap();
+        let reader = Reader::with_schema(&schema, ENCODED)
+            .unwrap()
+            .into_typed::<TestRecord>();
+        assert_eq!(reader.map(Result::unwrap).collect::<Vec<_>>(), expected);
+    }
+
     #[test]
     fn test_reader_invalid_header() {
         let schema = Schema::parse_str(SCHEMA).unwrap();
@@ -375,6 +542,39 @@ mod tests {
         assert!(Reader::with_schema(&schema, &invalid[..]).is_err());
     }
 
+    #[test]
+    fn test_reader_lenient_header() {
+        // Written by an implementation which does not check names nor default values.
+        let schema = Schema::parse_str_lenient(
+            r#"
+            {
+                "type": "record",
+                "name": "test-record",
+                "fields": [{"name": "a", "type": "long", "default": "none"}]
+            }
+            "#,
+        ).unwrap();
+        let mut writer = Writer::new(&schema, Vec::new());
+        writer
+            .append(Value::Record(vec![("a".to_string(), Value::Long(1))]))
+            .unwrap();
+        writer.flush().unwrap();
+        let encoded = writer.into_inner();
+
+        let reader = Reader::new(&encoded[..]).unwrap();
+        assert_eq!(reader.writer_schema(), &schema);
+        assert_eq!(
+            reader.map(Result::unwrap).collect::<Vec<_>>(),
+            vec![Value::Record(vec![("a".to_string(), Value::Long(1))])]
+        );
+
+        // Invalid schemas are still reported with their own error.
+        let mut invalid = ENCODED.to_owned();
+        invalid[19] = b'[';
+        let error = Reader::new(&invalid[..]).err().unwrap();
+        assert!(error.downcast::<::serde_json::Error>().is_ok());
+    }
+
     #[test]
     fn test_reader_invalid_block() {
         let schema = Schema::parse_str(SCHEMA).unwrap();
@@ -0,0 +1,1078 @@
+//! Logic for reading data written with a schema as data of another schema, resolving them as they
+//! are decoded.
+use std::collections::HashMap;
+use std::io::{self, ErrorKind, Read};
+
+use failure::Error;
+
+use decode::{decode_block_header, decode_internal, decode_len};
+use schema::{describe, Name, Names, RecordField, Schema, SchemaKind, UnionSchema};
+use types::{
+    check_names, find_named_variant, is_union_promotable, SchemaResolutionError, Value,
+};
+use util::{zag_i64, DecodeError};
+
+/// A plan to read data written with a writer `Schema` as data of a reader `Schema`, computed once
+/// for all the data to read.
+///
+/// Decoding a value with a `ResolvedSchema` gives the same result as decoding it with the writer
+/// `Schema`, then resolving it with `Value::resolve_with_writer`, in a single pass: the fields
+/// removed from the writer are skipped without being decoded, the default values of the fields
+/// added to the reader are computed once, and fields are reordered and values promoted as they
+/// are read.
+///
+/// As required by the specification, data which cannot be resolved only makes decoding fail
+/// when it is actually read.
+///
+/// ```
+/// # use avro_rs::{to_avro_datum, ResolvedSchema, Schema};
+/// # use avro_rs::types::{Record, Value};
+/// let writer_schema = Schema::parse_str(
+///     r#"
+///     {
+///         "type": "record",
+///         "name": "test",
+///         "fields": [
+///             {"name": "a", "type": "int"},
+///             {"name": "b", "type": "string"}
+///         ]
+///     }
+///     "#,
+/// ).unwrap();
+/// let reader_schema = Schema::parse_str(
+///     r#"
+///     {
+///         "type": "record",
+///         "name": "test",
+///         "fields": [
+///             {"name": "c", "type": "boolean", "default": true},
+///             {"name": "a", "type": "long"}
+///         ]
+///     }
+///     "#,
+/// ).unwrap();
+///
+/// let mut record = Record::new(&writer_schema).unwrap();
+/// record.put("a", 27);
+/// record.put("b", "foo");
+/// let encoded = to_avro_datum(&writer_schema, record).unwrap();
+///
+/// let resolved = ResolvedSchema::new(&writer_schema, &reader_schema).unwrap();
+/// assert_eq!(
+///     resolved.decode(&mut &encoded[..]).unwrap(),
+///     Value::Record(vec![
+///         ("c".to_string(), Value::Boolean(true)),
+///         ("a".to_string(), Value::Long(27)),
+///     ])
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct ResolvedSchema {
+    steps: Vec<Step>,
+    skips: Vec<Skip>,
+    root: usize,
+}
+
+/// How to read a value of the writer as a value of the reader. Nested steps are referred to by
+/// their position within `ResolvedSchema::steps`, which allows recursive schemas.
+#[derive(Clone, Debug)]
+enum Step {
+    /// Decode a value of a writer schema made of no other schema, as is.
+    Decode(Schema),
+    /// Decode a value of the writer schema `from`, made of no other schema, and resolve it as a
+    /// value of the reader schema `to`.
+    Promote { from: Schema, to: Schema },
+    /// Decode an enum symbol, given the position and the symbol of the reader for each symbol of
+    /// the writer.
+    Enum(Vec<Result<(i32, String), SchemaResolutionError>>),
+    /// Decode an array, given the step for its items.
+    Array(usize),
+    /// Decode a map, given the step for its values.
+    Map(usize),
+    /// Decode a union of the writer, given the step for each of its variants.
+    Union(Vec<usize>),
+    /// Read a value with the given step, as the variant at the given position of a reader union.
+    Variant(u32, usize),
+    /// Decode a record of the writer as a record of the reader.
+    Record {
+        /// What to do with each field of the writer, in the order they are written.
+        fields: Vec<FieldStep>,
+        /// The default values of the fields of the reader missing from the writer, along with
+        /// their position within the reader.
+        defaults: Vec<(usize, Value)>,
+        /// The names of the fields of the reader.
+        names: Vec<String>,
+    },
+    /// Fail, the value of the writer cannot be read as a value of the reader.
+    Fail(SchemaResolutionError),
+}
+
+/// What to do with a field of a writer record.
+#[derive(Clone, Debug)]
+enum FieldStep {
+    /// Read the field with the given step, as the field at the given position in the reader.
+    Read(usize, usize),
+    /// Skip the field, which is missing from the reader, with the given skip.
+    Skip(usize),
+}
+
+/// How to skip a value of the writer without decoding it. Nested skips are referred to by their
+/// position within `ResolvedSchema::skips`.
+#[derive(Clone, Debug)]
+enum Skip {
+    /// Skip a fixed number of bytes.
+    Bytes(u64),
+    /// Skip a variable-length zig-zag encoded integer.
+    Long,
+    /// Skip a number of bytes given by a leading `long`.
+    Length,
+    /// Skip an array, given the skip for its items.
+    Array(usize),
+    /// Skip a map, given the skip for its values.
+    Map(usize),
+    /// Skip a union, given the skip for each of its variants.
+    Union(Vec<usize>),
+    /// Skip a record, given the skip for each of its fields.
+    Record(Vec<usize>),
+}
+
+impl ResolvedSchema {
+    /// Compute how to read data written with the `writer` schema as data of the `reader` schema.
+    ///
+    /// Fails if one of the schemas refers to a named type it does not define.
+    pub fn new(writer: &Schema, reader: &Schema) -> Result<ResolvedSchema, Error> {
+        let mut planner = Planner {
+            writer_names: writer.names(),
+            reader_names: reader.names(),
+            steps: Vec::new(),
+            skips: Vec::new(),
+            records: HashMap::new(),
+            skipped_records: HashMap::new(),
+        };
+        let root = planner.plan(writer, reader)?;
+
+        Ok(ResolvedSchema {
+            steps: planner.steps,
+            skips: planner.skips,
+            root,
+        })
+    }
+
+    /// Decode a value written with the writer `Schema` from anything implementing `io::Read`, as
+    /// a value of the reader `Schema`.
+    pub fn decode<R: Read>(&self, reader: &mut R) -> Result<Value, Error> {
+        self.decode_step(self.root, reader)
+    }
+
+    fn decode_step<R: Read>(&self, step: usize, reader: &mut R) -> Result<Value, Error> {
+        match self.steps[step] {
+            Step::Decode(ref schema) => decode_internal(schema, &Names::new(), reader),
+            Step::Promote { ref from, ref to } => {
+                decode_internal(from, &Names::new(), reader)?.resolve_internal(to, &Names::new())
+            },
+            Step::Enum(ref symbols) => {
+                let index = zag_i64(reader)?;
+                match symbols.get(index as usize) {
+                    Some(Ok((index, symbol))) => Ok(Value::Enum(*index, symbol.clone())),
+                    Some(Err(error)) => Err(error.clone().into()),
+                    None => Err(DecodeError::new("enum symbol index out of bounds").into()),
+                }
+            },
+            Step::Array(items_step) => {
+                let mut items = Vec::new();
+                loop {
+                    let (len, _) = decode_block_header(reader)?;
+                    if len == 0 {
+                        break
+                    }
+
+                    items.reserve(len);
+                    for _ in 0..len {
+                        items.push(self.decode_step(items_step, reader)?);
+                    }
+                }
+                Ok(Value::Array(items))
+            },
+            Step::Map(values_step) => {
+                let mut items = HashMap::new();
+                loop {
+                    let (len, _) = decode_block_header(reader)?;
+                    if len == 0 {
+                        break
+                    }
+
+                    items.reserve(len);
+                    for _ in 0..len {
+                        match decode_internal(&Schema::String, &Names::new(), reader)? {
+                            Value::String(key) => {
+                                items.insert(key, self.decode_step(values_step, reader)?);
+                            },
+                            _ => return Err(DecodeError::new("map key is not a string").into()),
+                        }
+                    }
+                }
+                Ok(Value::Map(items))
+            },
+            Step::Union(ref variants) => {
+                let index = zag_i64(reader)?;
+                match variants.get(index as usize) {
+                    Some(&variant) => self.decode_step(variant, reader),
+                    None => Err(DecodeError::new("Union index out of bounds").into()),
+                }
+            },
+            Step::Variant(index, variant) => Ok(Value::Union(
+                index,
+                Box::new(self.decode_step(variant, reader)?),
+            )),
+            Step::Record {
+                ref fields,
+                ref defaults,
+                ref names,
+            } => {
+                let mut values = vec![None; names.len()];
+                for field in fields {
+                    match *field {
+                        FieldStep::Read(position, step) => {
+                            values[position] = Some(self.decode_step(step, reader)?);
+                        },
+                        FieldStep::Skip(skip) => self.skip(skip, reader)?,
+                    }
+                }
+                for (position, value) in defaults {
+                    values[*position] = Some(value.clone());
+                }
+
+                names
+                    .iter()
+                    .zip(values)
+                    .map(|(name, value)| value.map(|value| (name.clone(), value)))
+                    .collect::<Option<_>>()
+                    .map(Value::Record)
+                    .ok_or_else(|| SchemaResolutionError::new("missing field in record").into())
+            },
+            Step::Fail(ref error) => Err(error.clone().into()),
+        }
+    }
+
+    fn skip<R: Read>(&self, skip: usize, reader: &mut R) -> Result<(), Error> {
+        match self.skips[skip] {
+            Skip::Bytes(len) => skip_bytes(len, reader),
+            Skip::Long => zag_i64(reader).map(|_| ()),
+            Skip::Length => {
+                let len = decode_len(reader)?;
+                skip_bytes(len as u64, reader)
+            },
+            Skip::Array(items_skip) => loop {
+                match decode_block_header(reader)? {
+                    (0, _) => return Ok(()),
+                    (_, Some(size)) => skip_bytes(size, reader)?,
+                    (len, None) => {
+                        for _ in 0..len {
+                            self.skip(items_skip, reader)?;
+                        }
+                    },
+                }
+            },
+            Skip::Map(values_skip) => loop {
+                match decode_block_header(reader)? {
+                    (0, _) => return Ok(()),
+                    (_, Some(size)) => skip_bytes(size, reader)?,
+                    (len, None) => {
+                        for _ in 0..len {
+                            let key_len = decode_len(reader)?;
+                            skip_bytes(key_len as u64, reader)?;
+                            self.skip(values_skip, reader)?;
+                        }
+                    },
+                }
+            },
+            Skip::Union(ref variants) => {
+                let index = zag_i64(reader)?;
+                match variants.get(index as usize) {
+                    Some(&variant) => self.skip(variant, reader),
+                    None => Err(DecodeError::new("Union index out of bounds").into()),
+                }
+            },
+            Skip::Record(ref fields) => fields
+                .iter()
+                .try_for_each(|&field| self.skip(field, reader)),
+        }
+    }
+}
+
+/// Skip `len` bytes of `reader`.
+fn skip_bytes<R: Read>(len: u64, reader: &mut R) -> Result<(), Error> {
+    let skipped = io::copy(&mut reader.by_ref().take(len), &mut io::sink())?;
+    if skipped < len {
+        return Err(io::Error::new(ErrorKind::UnexpectedEof, "failed to skip bytes").into())
+    }
+    Ok(())
+}
+
+/// Computes the steps and skips of a `ResolvedSchema`.
+struct Planner<'a> {
+    writer_names: Names<'a>,
+    reader_names: Names<'a>,
+    steps: Vec<Step>,
+    skips: Vec<Skip>,
+    // The steps of the pairs of writer and reader records already planned, by their fullnames.
+    records: HashMap<(String, String), usize>,
+    // The skips of the writer records already planned, by their fullname.
+    skipped_records: HashMap<String, usize>,
+}
+
+impl<'a> Planner<'a> {
+    fn push(&mut self, step: Step) -> usize {
+        self.steps.push(step);
+        self.steps.len() - 1
+    }
+
+    fn fail(&mut self, message: String) -> usize {
+        self.push(Step::Fail(SchemaResolutionError::new(message)))
+    }
+
+    /// Plan how to read a value of `writer` as a value of `reader`.
+    fn plan(&mut self, writer: &'a Schema, reader: &'a Schema) -> Result<usize, Error> {
+        let writer = resolve_ref(writer, &self.writer_names)?;
+        let reader = resolve_ref(reader, &self.reader_names)?;
+
+        match (writer, reader) {
+            (Schema::Union(writer_union), _) => {
+                let variants = writer_union
+                    .variants()
+                    .iter()
+                    .map(|variant| self.plan(variant, reader))
+                    .collect::<Result<_, _>>()?;
+                Ok(self.push(Step::Union(variants)))
+            },
+            (_, Schema::Union(reader_union)) => match self.find_variant(writer, reader_union) {
+                Some((index, variant)) => {
+                    let step = self.plan(writer, variant)?;
+                    Ok(self.push(Step::Variant(index as u32, step)))
+                },
+                None => Ok(self.fail(format!(
+                    "Could not find type matching {} in union",
+                    describe(writer)
+                ))),
+            },
+            (
+                Schema::Record {
+                    name: writer_name,
+                    fields: writer_fields,
+                    ..
+                },
+                Schema::Record {
+                    name: reader_name,
+                    fields: reader_fields,
+                    ..
+                },
+            ) => match check_names(reader_name, writer_name) {
+                Ok(()) => self.plan_record(writer_name, writer_fields, reader_name, reader_fields),
+                Err(error) => Ok(self.push(Step::Fail(error))),
+            },
+            (
+                Schema::Enum {
+                    name: writer_name,
+                    symbols: writer_symbols,
+                    ..
+                },
+                Schema::Enum {
+                    name: reader_name,
+                    symbols: reader_symbols,
+                    ..
+                },
+            ) => {
+                if let Err(error) = check_names(reader_name, writer_name) {
+                    return Ok(self.push(Step::Fail(error)))
+                }
+                let symbols = writer_symbols
+                    .iter()
+                    .map(|symbol| match reader_symbols.iter().position(|s| s == symbol) {
+                        Some(index) => Ok((index as i32, symbol.clone())),
+                        None => Err(SchemaResolutionError::new(format!(
+                            "Enum symbol {} of the writer is not among the reader symbols {:?}",
+                            symbol, reader_symbols
+                        ))),
+                    }).collect();
+                Ok(self.push(Step::Enum(symbols)))
+            },
+            (
+                Schema::Fixed {
+                    name: writer_name, ..
+                },
+                Schema::Fixed {
+                    name: reader_name, ..
+                },
+            ) => match check_names(reader_name, writer_name) {
+                Ok(()) => Ok(self.plan_leaf(writer, reader)),
+                Err(error) => Ok(self.push(Step::Fail(error))),
+            },
+            (Schema::Array(writer_items), Schema::Array(reader_items)) => {
+                let step = self.plan(writer_items, reader_items)?;
+                Ok(self.push(Step::Array(step)))
+            },
+            (Schema::Map(writer_values), Schema::Map(reader_values)) => {
+                let step = self.plan(writer_values, reader_values)?;
+                Ok(self.push(Step::Map(step)))
+            },
+            _ if is_leaf(writer) && is_leaf(reader) => Ok(self.plan_leaf(writer, reader)),
+            _ => Ok(self.fail(format!(
+                "{} cannot be read as {}",
+                describe(writer),
+                describe(reader)
+            ))),
+        }
+    }
+
+    /// Plan how to read a value of `writer` as a value of `reader`, neither of them being made of
+    /// other schemas.
+    fn plan_leaf(&mut self, writer: &Schema, reader: &Schema) -> usize {
+        let from = self.leaf(writer, true);
+        if writer == reader {
+            self.push(Step::Decode(from))
+        } else {
+            let to = self.leaf(reader, false);
+            self.push(Step::Promote { from, to })
+        }
+    }
+
+    /// A copy of `schema` which does not refer to named types, so that it can be decoded or
+    /// resolved without names.
+    fn leaf(&self, schema: &Schema, writer: bool) -> Schema {
+        let names = if writer {
+            &self.writer_names
+        } else {
+            &self.reader_names
+        };
+        match *schema {
+            Schema::Decimal {
+                precision,
+                scale,
+                ref inner,
+            } => Schema::Decimal {
+                precision,
+                scale,
+                inner: Box::new(inner.resolve_ref(names).unwrap_or(inner).clone()),
+            },
+            ref schema => schema.clone(),
+        }
+    }
+
+    /// Find the variant of the `reader` union a value of `writer` is read as.
+    fn find_variant(
+        &self,
+        writer: &Schema,
+        reader: &'a UnionSchema,
+    ) -> Option<(usize, &'a Schema)> {
+        if writer.name().is_some() {
+            return find_named_variant(writer, reader, &self.reader_names)
+        }
+
+        // The first variant of the same type if any, or else the first one the value can be
+        // promoted to.
+        let kind = SchemaKind::from(writer);
+        let variants = reader
+            .variants()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, variant)| {
+                variant
+                    .resolve_ref(&self.reader_names)
+                    .map(|variant| (i, variant))
+            }).collect::<Vec<_>>();
+        variants
+            .iter()
+            .find(|(_, variant)| SchemaKind::from(*variant) == kind)
+            .or_else(|| {
+                variants
+                    .iter()
+                    .find(|(_, variant)| is_union_promotable(SchemaKind::from(*variant), kind))
+            }).cloned()
+    }
+
+    /// Plan how to read a record of the writer as a record of the reader, whose names match.
+    fn plan_record(
+        &mut self,
+        writer_name: &Name,
+        writer_fields: &'a [RecordField],
+        reader_name: &Name,
+        reader_fields: &'a [RecordField],
+    ) -> Result<usize, Error> {
+        let key = (writer_name.fullname(None), reader_name.fullname(None));
+        if let Some(&step) = self.records.get(&key) {
+            return Ok(step)
+        }
+        // Recursive references to this record are resolved to this step, replaced below.
+        let step = self.push(Step::Fail(SchemaResolutionError::new("recursive record")));
+        self.records.insert(key, step);
+
+        // The position within the reader of each field of the writer, if any.
+        let mut positions = vec![None; writer_fields.len()];
+        let mut defaults = Vec::new();
+        for (position, reader_field) in reader_fields.iter().enumerate() {
+            let written = writer_fields
+                .iter()
+                .position(|writer_field| reader_field.matches(&writer_field.name))
+                .filter(|&written| positions[written].is_none());
+            if let Some(written) = written {
+                positions[written] = Some(position);
+                continue
+            }
+            // Fields built by hand may have a default value which was never parsed.
+            match reader_field.parsed_default(&self.reader_names) {
+                Some(default) => {
+                    match default.resolve_internal(&reader_field.schema, &self.reader_names) {
+                        Ok(default) => defaults.push((position, default)),
+                        Err(error) => {
+                            self.steps[step] = Step::Fail(SchemaResolutionError::new(format!(
+                                "invalid default of field {} in record: {}",
+                                reader_field.name, error
+                            )));
+                            return Ok(step)
+                        },
+                    }
+                },
+                None => {
+                    self.steps[step] = Step::Fail(SchemaResolutionError::new(format!(
+                        "missing field {} in record",
+                        reader_field.name
+                    )));
+                    return Ok(step)
+                },
+            }
+        }
+
+        let mut fields = Vec::with_capacity(writer_fields.len());
+        for (writer_field, position) in writer_fields.iter().zip(positions) {
+            fields.push(match position {
+                Some(position) => {
+                    let reader_schema = &reader_fields[position].schema;
+                    FieldStep::Read(position, self.plan(&writer_field.schema, reader_schema)?)
+                },
+                None => FieldStep::Skip(self.plan_skip(&writer_field.schema)?),
+            });
+        }
+
+        self.steps[step] = Step::Record {
+            fields,
+            defaults,
+            names: reader_fields.iter().map(|field| field.name.clone()).collect(),
+        };
+        Ok(step)
+    }
+
+    /// Plan how to skip a value of `writer`.
+    fn plan_skip(&mut self, writer: &'a Schema) -> Result<usize, Error> {
+        let writer = resolve_ref(writer, &self.writer_names)?;
+        let skip = match *writer {
+            Schema::Null => Skip::Bytes(0),
+            Schema::Boolean => Skip::Bytes(1),
+            Schema::Int
+            | Schema::Long
+            | Schema::Enum { .. }
+            | Schema::Date
+            | Schema::TimeMillis
+            | Schema::TimeMicros
+            | Schema::TimestampMillis
+            | Schema::TimestampMicros
+            | Schema::LocalTimestampMillis
+            | Schema::LocalTimestampMicros => Skip::Long,
+            Schema::Float => Skip::Bytes(4),
+            Schema::Double => Skip::Bytes(8),
+            Schema::Bytes | Schema::String | Schema::Uuid => Skip::Length,
+            Schema::Fixed { size, .. } => Skip::Bytes(size as u64),
+            Schema::Duration { .. } => Skip::Bytes(12),
+            Schema::Decimal { ref inner, .. } => return self.plan_skip(inner),
+            Schema::Array(ref items) => Skip::Array(self.plan_skip(items)?),
+            Schema::Map(ref values) => Skip::Map(self.plan_skip(values)?),
+            Schema::Union(ref union) => Skip::Union(
+                union
+                    .variants()
+                    .iter()
+                    .map(|variant| self.plan_skip(variant))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Schema::Record {
+                ref name,
+                ref fields,
+                ..
+            } => {
+                let fullname = name.fullname(None);
+                if let Some(&skip) = self.skipped_records.get(&fullname) {
+                    return Ok(skip)
+                }
+                // Recursive references to this record are resolved to this skip, replaced below.
+                self.skips.push(Skip::Record(Vec::new()));
+                let skip = self.skips.len() - 1;
+                self.skipped_records.insert(fullname, skip);
+
+                let fields = fields
+                    .iter()
+                    .map(|field| self.plan_skip(&field.schema))
+                    .collect::<Result<_, _>>()?;
+                self.skips[skip] = Skip::Record(fields);
+                return Ok(skip)
+            },
+            Schema::Annotated { .. } | Schema::Ref { .. } => {
+                return Err(SchemaResolutionError::new(format!(
+                    "Unresolved schema reference: {:?}",
+                    writer
+                )).into())
+            },
+        };
+        self.skips.push(skip);
+        Ok(self.skips.len() - 1)
+    }
+}
+
+/// Follow `schema` through `names` if it is a reference to a named type or annotated by custom
+/// attributes.
+fn resolve_ref<'a>(schema: &'a Schema, names: &Names<'a>) -> Result<&'a Schema, Error> {
+    schema.resolve_ref(names).ok_or_else(|| {
+        SchemaResolutionError::new(format!("Unresolved schema reference: {:?}", schema)).into()
+    })
+}
+
+/// Whether `schema` is made of no other schema, apart from the `fixed` of a `decimal`.
+fn is_leaf(schema: &Schema) -> bool {
+    !matches!(
+        *schema,
+        Schema::Array(_)
+            | Schema::Map(_)
+            | Schema::Union(_)
+            | Schema::Record { .. }
+            | Schema::Annotated { .. }
+            | Schema::Ref { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use decode::decode;
+    use util::zig_i64;
+    use writer::to_avro_datum;
+
+    /// Check that decoding `value` written with `writer` as `reader` gives the same result as
+    /// resolving it, and return that result. Errors are reported with different messages.
+    fn read_as(writer: &str, reader: &str, value: Value) -> Result<Value, String> {
+        let writer = Schema::parse_str(writer).unwrap();
+        let reader = Schema::parse_str(reader).unwrap();
+        let encoded = to_avro_datum(&writer, value).unwrap();
+
+        let resolved = ResolvedSchema::new(&writer, &reader)
+            .unwrap()
+            .decode(&mut &encoded[..])
+            .map_err(|e| e.to_string());
+        let expected = decode(&writer, &mut &encoded[..])
+            .unwrap()
+            .resolve_with_writer(&writer, &reader);
+        assert_eq!(resolved.as_ref().ok(), expected.as_ref().ok());
+        resolved
+    }
+
+    #[test]
+    fn test_primitives_and_promotions() {
+        assert_eq!(read_as(r#""int""#, r#""int""#, Value::Int(1)), Ok(Value::Int(1)));
+        assert_eq!(read_as(r#""int""#, r#""long""#, Value::Int(1)), Ok(Value::Long(1)));
+        assert_eq!(read_as(r#""long""#, r#""double""#, Value::Long(1)), Ok(Value::Double(1.0)));
+        assert_eq!(
+            read_as(r#""string""#, r#""bytes""#, Value::String("a".to_string())),
+            Ok(Value::Bytes(b"a".to_vec()))
+        );
+        assert_eq!(
+            read_as(
+                r#"{"type": "long", "logicalType": "timestamp-millis"}"#,
+                r#"{"type": "long", "logicalType": "timestamp-micros"}"#,
+                Value::TimestampMillis(1)
+            ),
+            Ok(Value::TimestampMicros(1000))
+        );
+        assert!(read_as(r#""boolean""#, r#""int""#, Value::Boolean(true)).is_err());
+        let array = r#"{"type": "array", "items": "int"}"#;
+        assert!(read_as(r#""int""#, array, Value::Int(1)).is_err());
+    }
+
+    #[test]
+    fn test_unions() {
+        assert_eq!(
+            read_as(r#""int""#, r#"["null", "long"]"#, Value::Int(1)),
+            Ok(Value::Union(1, Box::new(Value::Long(1))))
+        );
+        assert_eq!(
+            read_as(
+                r#"["null", "int"]"#,
+                r#"["double", "null"]"#,
+                Value::Union(1, Box::new(Value::Int(1)))
+            ),
+            Ok(Value::Union(0, Box::new(Value::Double(1.0))))
+        );
+        assert_eq!(
+            read_as(
+                r#"["null", "int"]"#,
+                r#""long""#,
+                Value::Union(1, Box::new(Value::Int(1)))
+            ),
+            Ok(Value::Long(1))
+        );
+
+        // Variants which cannot be read only fail when they are actually read.
+        let writer = r#"["null", "boolean", "int"]"#;
+        assert_eq!(
+            read_as(writer, r#"["int", "null"]"#, Value::Union(0, Box::new(Value::Null))),
+            Ok(Value::Union(1, Box::new(Value::Null)))
+        );
+        assert!(
+            read_as(writer, r#"["int", "null"]"#, Value::Union(1, Box::new(Value::Boolean(true))))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_named_types() {
+        let writer = r#"
+            {
+                "type": "record",
+                "name": "r",
+                "fields": [
+                    {
+                        "name": "e",
+                        "type": {"type": "enum", "name": "e", "symbols": ["A", "B", "C"]}
+                    },
+                    {"name": "f", "type": {"type": "fixed", "name": "f", "size": 2}},
+                    {
+                        "name": "u",
+                        "type": [
+                            "null",
+                            {
+                                "type": "record",
+                                "name": "a",
+                                "fields": [{"name": "x", "type": "int"}]
+                            },
+                            {
+                                "type": "record",
+                                "name": "b",
+                                "fields": [{"name": "x", "type": "int"}]
+                            }
+                        ]
+                    }
+                ]
+            }
+        "#;
+        let reader = r#"
+            {
+                "type": "record",
+                "name": "r2",
+                "aliases": ["r"],
+                "fields": [
+                    {
+                        "name": "u",
+                        "type": [
+                            {
+                                "type": "record",
+                                "name": "b",
+                                "fields": [{"name": "y", "type": "long", "aliases": ["x"]}]
+                            },
+                            {
+                                "type": "record",
+                                "name": "c",
+                                "aliases": ["a"],
+                                "fields": [{"name": "x", "type": "int"}]
+                            }
+                        ]
+                    },
+                    {"name": "f", "type": {"type": "fixed", "name": "f", "size": 2}},
+                    {"name": "e", "type": {"type": "enum", "name": "e", "symbols": ["C", "A"]}}
+                ]
+            }
+        "#;
+        let record = |symbol: &str, union| {
+            Value::Record(vec![
+                ("e".to_string(), Value::String(symbol.to_string())),
+                ("f".to_string(), Value::Fixed(2, vec![1, 2])),
+                ("u".to_string(), union),
+            ])
+        };
+
+        let b = Value::Record(vec![("x".to_string(), Value::Int(1))]);
+        let b = record("A", Value::Union(2, Box::new(b)));
+        assert_eq!(
+            read_as(writer, reader, b),
+            Ok(Value::Record(vec![
+                (
+                    "u".to_string(),
+                    Value::Union(
+                        0,
+                        Box::new(Value::Record(vec![("y".to_string(), Value::Long(1))]))
+                    )
+                ),
+                ("f".to_string(), Value::Fixed(2, vec![1, 2])),
+                ("e".to_string(), Value::Enum(1, "A".to_string())),
+            ]))
+        );
+
+        let a = Value::Record(vec![("x".to_string(), Value::Int(1))]);
+        let a = record("C", Value::Union(1, Box::new(a)));
+        assert_eq!(
+            read_as(writer, reader, a),
+            Ok(Value::Record(vec![
+                (
+                    "u".to_string(),
+                    Value::Union(
+                        1,
+                        Box::new(Value::Record(vec![("x".to_string(), Value::Int(1))]))
+                    )
+                ),
+                ("f".to_string(), Value::Fixed(2, vec![1, 2])),
+                ("e".to_string(), Value::Enum(0, "C".to_string())),
+            ]))
+        );
+
+        // `null` is missing from the reader, and so is the symbol `B`.
+        let null = record("A", Value::Union(0, Box::new(Value::Null)));
+        assert!(read_as(writer, reader, null).is_err());
+        let b = Value::Record(vec![("x".to_string(), Value::Int(1))]);
+        let error = read_as(writer, reader, record("B", Value::Union(2, Box::new(b)))).unwrap_err();
+        let expected = "symbol B of the writer is not among the reader symbols";
+        assert!(error.contains(expected), "{}", error);
+    }
+
+    #[test]
+    fn test_skip_removed_fields() {
+        let writer = r#"
+            {
+                "type": "record",
+                "name": "r",
+                "fields": [
+                    {"name": "a", "type": "string"},
+                    {"name": "b", "type": {"type": "array", "items": "double"}},
+                    {"name": "c", "type": {"type": "map", "values": ["null", "string"]}},
+                    {
+                        "name": "d",
+                        "type": {
+                            "type": "record",
+                            "name": "inner",
+                            "fields": [
+                                {"name": "x", "type": "long"},
+                                {"name": "y", "type": {"type": "fixed", "name": "f", "size": 3}},
+                                {"name": "z", "type": "boolean"},
+                                {"name": "w", "type": ["null", "float", "bytes"]}
+                            ]
+                        }
+                    },
+                    {"name": "e", "type": "int"}
+                ]
+            }
+        "#;
+        let reader = r#"
+            {
+                "type": "record",
+                "name": "r",
+                "fields": [
+                    {"name": "e", "type": "long"},
+                    {"name": "g", "type": "string", "default": "new"}
+                ]
+            }
+        "#;
+        let mut map = HashMap::new();
+        map.insert("k".to_string(), Value::Union(1, Box::new(Value::String("v".to_string()))));
+        map.insert("l".to_string(), Value::Union(0, Box::new(Value::Null)));
+        let value = Value::Record(vec![
+            ("a".to_string(), Value::String("foo".to_string())),
+            ("b".to_string(), Value::Array(vec![Value::Double(1.0), Value::Double(2.0)])),
+            ("c".to_string(), Value::Map(map)),
+            (
+                "d".to_string(),
+                Value::Record(vec![
+                    ("x".to_string(), Value::Long(-300)),
+                    ("y".to_string(), Value::Fixed(3, vec![1, 2, 3])),
+                    ("z".to_string(), Value::Boolean(true)),
+                    ("w".to_string(), Value::Union(2, Box::new(Value::Bytes(vec![4, 5])))),
+                ]),
+            ),
+            ("e".to_string(), Value::Int(42)),
+        ]);
+
+        assert_eq!(
+            read_as(writer, reader, value),
+            Ok(Value::Record(vec![
+                ("e".to_string(), Value::Long(42)),
+                ("g".to_string(), Value::String("new".to_string())),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_skip_blocks_with_size() {
+        let writer = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "r",
+                "fields": [
+                    {"name": "a", "type": {"type": "array", "items": "string"}},
+                    {"name": "b", "type": {"type": "map", "values": "long"}},
+                    {"name": "c", "type": {"type": "array", "items": "int"}}
+                ]
+            }
+        "#,
+        ).unwrap();
+        let reader = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "r",
+                "fields": [{"name": "c", "type": {"type": "array", "items": "long"}}]
+            }
+        "#,
+        ).unwrap();
+
+        let mut encoded = Vec::new();
+        // a: one block of two strings with its size, the size being trusted to skip it
+        zig_i64(-2, &mut encoded);
+        zig_i64(4, &mut encoded);
+        encoded.extend(vec![2u8, b'x', 2u8, b'y', 0u8]);
+        // b: one block of one entry with its size
+        zig_i64(-1, &mut encoded);
+        zig_i64(3, &mut encoded);
+        encoded.extend(vec![2u8, b'k', 6u8, 0u8]);
+        // c: one block of one int with its size, then one without
+        zig_i64(-1, &mut encoded);
+        zig_i64(1, &mut encoded);
+        zig_i64(7, &mut encoded);
+        zig_i64(1, &mut encoded);
+        zig_i64(8, &mut encoded);
+        encoded.push(0u8);
+
+        assert_eq!(
+            ResolvedSchema::new(&writer, &reader)
+                .unwrap()
+                .decode(&mut &encoded[..])
+                .unwrap(),
+            Value::Record(vec![(
+                "c".to_string(),
+                Value::Array(vec![Value::Long(7), Value::Long(8)])
+            )])
+        );
+    }
+
+    #[test]
+    fn test_recursive_records() {
+        let writer = r#"
+            {
+                "type": "record",
+                "name": "list",
+                "fields": [
+                    {"name": "value", "type": "int"},
+                    {"name": "removed", "type": ["null", "list"]},
+                    {"name": "next", "type": ["null", "list"]}
+                ]
+            }
+        "#;
+        let reader = r#"
+            {
+                "type": "record",
+                "name": "list",
+                "fields": [
+                    {"name": "next", "type": ["null", "list"]},
+                    {"name": "item", "type": "long", "aliases": ["value"]}
+                ]
+            }
+        "#;
+        let node = |value, removed, next| {
+            Value::Record(vec![
+                ("value".to_string(), Value::Int(value)),
+                ("removed".to_string(), removed),
+                ("next".to_string(), next),
+            ])
+        };
+        let null = || Value::Union(0, Box::new(Value::Null));
+        let removed = Value::Union(1, Box::new(node(3, null(), null())));
+        let value = node(1, removed, Value::Union(1, Box::new(node(2, null(), null()))));
+
+        let expected = Value::Record(vec![
+            (
+                "next".to_string(),
+                Value::Union(
+                    1,
+                    Box::new(Value::Record(vec![
+                        ("next".to_string(), null()),
+                        ("item".to_string(), Value::Long(2)),
+                    ])),
+                ),
+            ),
+            ("item".to_string(), Value::Long(1)),
+        ]);
+        assert_eq!(read_as(writer, reader, value), Ok(expected));
+    }
+
+    #[test]
+    fn test_missing_field_without_default() {
+        let writer = r#"{"type": "record", "name": "r", "fields": [{"name": "a", "type": "int"}]}"#;
+        let reader = r#"{"type": "record", "name": "r", "fields": [{"name": "b", "type": "int"}]}"#;
+        let value = Value::Record(vec![("a".to_string(), Value::Int(1))]);
+        assert!(read_as(writer, reader, value).is_err());
+
+        // The records are only read when present.
+        let writer = format!(r#"["null", {}]"#, writer);
+        let reader = format!(r#"["null", {}]"#, reader);
+        let value = Value::Union(0, Box::new(Value::Null));
+        assert_eq!(
+            read_as(&writer, &reader, value),
+            Ok(Value::Union(0, Box::new(Value::Null)))
+        );
+    }
+
+    #[test]
+    fn test_default_of_field_built_by_hand() {
+        let writer = Schema::parse_str(r#"{"type": "record", "name": "r", "fields": []}"#).unwrap();
+        let mut reader = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "r",
+                "fields": [{"name": "a", "type": "int", "default": 1}]
+            }
+        "#,
+        ).unwrap();
+        // Only the JSON default is known for fields constructed by hand.
+        if let Schema::Record { ref mut fields, .. } = reader {
+            fields[0].default_value = None;
+        }
+
+        let encoded = to_avro_datum(&writer, Value::Record(vec![])).unwrap();
+        let resolved = ResolvedSchema::new(&writer, &reader).unwrap();
+        assert_eq!(
+            resolved.decode(&mut &encoded[..]).unwrap(),
+            Value::Record(vec![("a".to_string(), Value::Int(1))])
+        );
+    }
+
+    #[test]
+    fn test_invalid_default() {
+        let writer = r#"{"type": "record", "name": "r", "fields": []}"#;
+        let reader = r#"
+            {
+                "type": "record",
+                "name": "r",
+                "fields": [{
+                    "name": "a",
+                    "type": {"type": "bytes", "logicalType": "decimal", "precision": 1},
+                    "default": "\u007f\u00ff"
+                }]
+            }
+        "#;
+        let value = Value::Record(vec![]);
+        let error = read_as(writer, reader, value).unwrap_err();
+        assert!(error.contains("invalid default of field a"), "{}", error);
+
+        // The default is only needed when the record is present.
+        let writer = format!(r#"["null", {}]"#, writer);
+        let reader = format!(r#"["null", {}]"#, reader);
+        let value = Value::Union(0, Box::new(Value::Null));
+        assert_eq!(
+            read_as(&writer, &reader, value),
+            Ok(Value::Union(0, Box::new(Value::Null)))
+        );
+    }
+}
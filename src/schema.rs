@@ -1,13 +1,22 @@
 //! Logic for parsing and interacting with schemas in Avro format.
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 
 use failure::Error;
-use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use serde::ser::{Error as SerError, Serialize, SerializeMap, SerializeSeq, Serializer};
 use serde_json::{self, Map, Value};
 
+use decimal::Decimal;
+use fingerprint::{FingerprintAlgorithm, SchemaFingerprint};
 use types;
-use util::MapHelper;
+use util::{parse_uuid, MapHelper};
+
+pub mod builder;
+pub mod compatibility;
+mod set;
+
+pub use self::set::SchemaSet;
 
 /// Describes errors happened while parsing Avro schemas.
 #[derive(Fail, Debug)]
@@ -26,7 +35,9 @@ impl ParseSchemaError {
 /// Represents any valid Avro schema
 /// More information about Avro schemas can be found in the
 /// [Avro Specification](https://avro.apache.org/docs/current/spec.html#schemas)
-#[derive(Clone, Debug, PartialEq)]
+///
+/// Schemas are compared regardless of their custom attributes, which have no effect on the data.
+#[derive(Clone, Debug)]
 pub enum Schema {
     /// A `null` Avro schema.
     Null,
@@ -64,15 +75,127 @@ pub enum Schema {
         doc: Documentation,
         fields: Vec<RecordField>,
         lookup: HashMap<String, usize>,
+        props: Props,
     },
     /// An `enum` Avro schema.
     Enum {
         name: Name,
         doc: Documentation,
         symbols: Vec<String>,
+        props: Props,
     },
     /// A `fixed` Avro schema.
-    Fixed { name: Name, size: usize },
+    Fixed {
+        name: Name,
+        size: usize,
+        props: Props,
+    },
+    /// A `decimal` logical type, annotating a `bytes` or `fixed` Avro schema.
+    ///
+    /// `precision` is the maximum number of digits of the unscaled value, `scale` is the number of
+    /// those digits found to the right of the decimal point, and `inner` is the annotated schema.
+    Decimal {
+        precision: usize,
+        scale: usize,
+        inner: Box<Schema>,
+    },
+    /// A `date` logical type, annotating an `int` Avro schema.
+    /// `Date` represents the number of days since the unix epoch.
+    Date,
+    /// A `time-millis` logical type, annotating an `int` Avro schema.
+    /// `TimeMillis` represents the number of milliseconds after midnight.
+    TimeMillis,
+    /// A `time-micros` logical type, annotating a `long` Avro schema.
+    /// `TimeMicros` represents the number of microseconds after midnight.
+    TimeMicros,
+    /// A `timestamp-millis` logical type, annotating a `long` Avro schema.
+    /// `TimestampMillis` represents the number of milliseconds since the unix epoch, in UTC.
+    TimestampMillis,
+    /// A `timestamp-micros` logical type, annotating a `long` Avro schema.
+    /// `TimestampMicros` represents the number of microseconds since the unix epoch, in UTC.
+    TimestampMicros,
+    /// A `local-timestamp-millis` logical type, annotating a `long` Avro schema.
+    /// `LocalTimestampMillis` represents the number of milliseconds since the unix epoch, in an
+    /// unspecified local timezone.
+    LocalTimestampMillis,
+    /// A `local-timestamp-micros` logical type, annotating a `long` Avro schema.
+    /// `LocalTimestampMicros` represents the number of microseconds since the unix epoch, in an
+    /// unspecified local timezone.
+    LocalTimestampMicros,
+    /// A `uuid` logical type, annotating a `string` Avro schema.
+    /// `Uuid` values are written using their canonical, 36 characters long, representation.
+    Uuid,
+    /// A `duration` logical type, annotating a `fixed` Avro schema of size 12.
+    /// `Duration` represents an amount of time as a number of months, days and milliseconds.
+    Duration { name: Name },
+    /// A schema carrying custom attributes (`props`), for the schemas which cannot hold them
+    /// themselves: primitives, logical types, arrays, maps and references to named types.
+    ///
+    /// `record`, `enum` and `fixed` schemas keep their custom attributes in their own `props`.
+    Annotated { inner: Box<Schema>, props: Props },
+    /// A reference to a named type (`record`, `enum` or `fixed`) defined elsewhere in the schema.
+    ///
+    /// The `name` is always fully qualified, so the referenced definition can be found by its
+    /// `fullname` regardless of the enclosing namespace.
+    Ref { name: Name },
+}
+
+impl PartialEq for Schema {
+    fn eq(&self, other: &Schema) -> bool {
+        match (self, other) {
+            (Schema::Annotated { inner, .. }, other) => **inner == *other,
+            (schema, Schema::Annotated { inner, .. }) => *schema == **inner,
+            (Schema::Array(items), Schema::Array(other)) => items == other,
+            (Schema::Map(values), Schema::Map(other)) => values == other,
+            (Schema::Union(union), Schema::Union(other)) => union == other,
+            (
+                Schema::Record {
+                    name, doc, fields, ..
+                },
+                Schema::Record {
+                    name: other_name,
+                    doc: other_doc,
+                    fields: other_fields,
+                    ..
+                },
+            ) => name == other_name && doc == other_doc && fields == other_fields,
+            (
+                Schema::Enum {
+                    name, doc, symbols, ..
+                },
+                Schema::Enum {
+                    name: other_name,
+                    doc: other_doc,
+                    symbols: other_symbols,
+                    ..
+                },
+            ) => name == other_name && doc == other_doc && symbols == other_symbols,
+            (
+                Schema::Fixed { name, size, .. },
+                Schema::Fixed {
+                    name: other_name,
+                    size: other_size,
+                    ..
+                },
+            ) => name == other_name && size == other_size,
+            (
+                Schema::Decimal {
+                    precision,
+                    scale,
+                    inner,
+                },
+                Schema::Decimal {
+                    precision: other_precision,
+                    scale: other_scale,
+                    inner: other_inner,
+                },
+            ) => precision == other_precision && scale == other_scale && inner == other_inner,
+            (Schema::Duration { name }, Schema::Duration { name: other })
+            | (Schema::Ref { name }, Schema::Ref { name: other }) => name == other,
+            // The remaining schemas are made of nothing else than their type.
+            _ => SchemaKind::from(self) == SchemaKind::from(other),
+        }
+    }
 }
 
 /// This type is used to simplify enum variant comparison between `Schema` and `types::Value`.
@@ -99,6 +222,17 @@ pub(crate) enum SchemaKind {
     Record,
     Enum,
     Fixed,
+    Decimal,
+    Date,
+    TimeMillis,
+    TimeMicros,
+    TimestampMillis,
+    TimestampMicros,
+    LocalTimestampMillis,
+    LocalTimestampMicros,
+    Uuid,
+    Duration,
+    Ref,
 }
 
 impl<'a> From<&'a Schema> for SchemaKind {
@@ -120,6 +254,18 @@ impl<'a> From<&'a Schema> for SchemaKind {
             Schema::Record { .. } => SchemaKind::Record,
             Schema::Enum { .. } => SchemaKind::Enum,
             Schema::Fixed { .. } => SchemaKind::Fixed,
+            Schema::Decimal { .. } => SchemaKind::Decimal,
+            Schema::Date => SchemaKind::Date,
+            Schema::TimeMillis => SchemaKind::TimeMillis,
+            Schema::TimeMicros => SchemaKind::TimeMicros,
+            Schema::TimestampMillis => SchemaKind::TimestampMillis,
+            Schema::TimestampMicros => SchemaKind::TimestampMicros,
+            Schema::LocalTimestampMillis => SchemaKind::LocalTimestampMillis,
+            Schema::LocalTimestampMicros => SchemaKind::LocalTimestampMicros,
+            Schema::Uuid => SchemaKind::Uuid,
+            Schema::Duration { .. } => SchemaKind::Duration,
+            Schema::Annotated { ref inner, .. } => SchemaKind::from(&**inner),
+            Schema::Ref { .. } => SchemaKind::Ref,
         }
     }
 }
@@ -138,10 +284,20 @@ impl<'a> From<&'a types::Value> for SchemaKind {
             types::Value::String(_) => SchemaKind::String,
             types::Value::Array(_) => SchemaKind::Array,
             types::Value::Map(_) => SchemaKind::Map,
-            types::Value::Union(_) => SchemaKind::Union,
+            types::Value::Union(..) => SchemaKind::Union,
             types::Value::Record(_) => SchemaKind::Record,
             types::Value::Enum(_, _) => SchemaKind::Enum,
             types::Value::Fixed(_, _) => SchemaKind::Fixed,
+            types::Value::Decimal(_) => SchemaKind::Decimal,
+            types::Value::Date(_) => SchemaKind::Date,
+            types::Value::TimeMillis(_) => SchemaKind::TimeMillis,
+            types::Value::TimeMicros(_) => SchemaKind::TimeMicros,
+            types::Value::TimestampMillis(_) => SchemaKind::TimestampMillis,
+            types::Value::TimestampMicros(_) => SchemaKind::TimestampMicros,
+            types::Value::LocalTimestampMillis(_) => SchemaKind::LocalTimestampMillis,
+            types::Value::LocalTimestampMicros(_) => SchemaKind::LocalTimestampMicros,
+            types::Value::Uuid(_) => SchemaKind::Uuid,
+            types::Value::Duration { .. } => SchemaKind::Duration,
         }
     }
 }
@@ -166,6 +322,10 @@ pub struct Name {
 /// Represents documentation for complex Avro schemas.
 pub type Documentation = Option<String>;
 
+/// Represents the custom attributes of an Avro schema or record field: every attribute which is not
+/// defined by the Avro specification, such as `avro.java.string` or `connect.name`.
+pub type Props = Map<String, Value>;
+
 impl Name {
     /// Create a new `Name`.
     /// No `namespace` nor `aliases` will be defined.
@@ -183,23 +343,10 @@ impl Name {
             .name()
             .ok_or_else(|| ParseSchemaError::new("No `name` field"))?;
 
-        let namespace = complex.string("namespace");
-
-        let aliases: Option<Vec<String>> = complex
-            .get("aliases")
-            .and_then(|aliases| aliases.as_array())
-            .and_then(|aliases| {
-                aliases
-                    .iter()
-                    .map(|alias| alias.as_str())
-                    .map(|alias| alias.map(|a| a.to_string()))
-                    .collect::<Option<_>>()
-            });
-
         Ok(Name {
             name,
-            namespace,
-            aliases,
+            namespace: complex.string("namespace"),
+            aliases: complex.aliases(),
         })
     }
 
@@ -223,19 +370,71 @@ impl Name {
             }
         }
     }
+
+    /// Returns whether a named type called `writer` can be read as a named type called `self`, as
+    /// defined by the schema resolution rules: both must have the same unqualified name, or the
+    /// fullname of `writer` must be one of the aliases of `self`.
+    ///
+    /// Aliases which are not fully qualified are relative to the namespace of `self`.
+    pub fn matches(&self, writer: &Name) -> bool {
+        let unqualified = |name: &Name| name.name.rsplit('.').next().map(|n| n.to_owned());
+        if unqualified(self) == unqualified(writer) {
+            return true
+        }
+
+        let namespace = self.namespace.as_ref().map(|namespace| namespace.as_ref());
+        let writer_fullname = writer.fullname(None);
+        self.aliases
+            .iter()
+            .flatten()
+            .any(|alias| Name::new(alias).fullname(namespace) == writer_fullname)
+    }
+
+    /// Return a copy of this `Name` where `name` is never dotted and `namespace` is set to the
+    /// namespace this `Name` effectively belongs to, inheriting `enclosing_namespace` if needed.
+    fn qualified(&self, enclosing_namespace: Option<&str>) -> Name {
+        let (name, namespace) = match self.name.rfind('.') {
+            Some(idx) => (
+                self.name[idx + 1..].to_owned(),
+                Some(self.name[..idx].to_owned()),
+            ),
+            None => (
+                self.name.clone(),
+                self.namespace
+                    .clone()
+                    .or_else(|| enclosing_namespace.map(|ns| ns.to_owned())),
+            ),
+        };
+
+        Name {
+            name,
+            // An empty namespace stands for the null namespace.
+            namespace: namespace.filter(|ns| !ns.is_empty()),
+            aliases: self.aliases.clone(),
+        }
+    }
 }
 
 /// Represents a `field` in a `record` Avro schema.
-#[derive(Clone, Debug, PartialEq)]
+///
+/// Like schemas, fields are compared regardless of their custom attributes.
+#[derive(Clone, Debug)]
 pub struct RecordField {
     /// Name of the field.
     pub name: String,
     /// Documentation of the field.
     pub doc: Documentation,
-    /// Default value of the field.
+    /// Alternate names of the field, used when resolving it against a writer schema.
+    pub aliases: Option<Vec<String>>,
+    /// Default value of the field, as written in the JSON schema.
+    pub default: Option<Value>,
+    /// Default value of the field, parsed according to the schema of the field.
     /// This value will be used when reading Avro datum if schema resolution
     /// is enabled.
-    pub default: Option<Value>,
+    ///
+    /// It is set when parsing a schema, and is `None` if the field has no default value, or if
+    /// its default value is invalid and the schema was parsed leniently.
+    pub default_value: Option<types::Value>,
     /// Schema of the field.
     pub schema: Schema,
     /// Order of the field.
@@ -244,6 +443,21 @@ pub struct RecordField {
     pub order: RecordFieldOrder,
     /// Position of the field in the list of `field` of its parent `Schema`
     pub position: usize,
+    /// Custom attributes of the field.
+    pub props: Props,
+}
+
+impl PartialEq for RecordField {
+    fn eq(&self, other: &RecordField) -> bool {
+        self.name == other.name
+            && self.doc == other.doc
+            && self.aliases == other.aliases
+            && self.default == other.default
+            && self.default_value == other.default_value
+            && self.schema == other.schema
+            && self.order == other.order
+            && self.position == other.position
+    }
 }
 
 /// Represents any valid order for a `field` in a `record` Avro schema.
@@ -256,16 +470,20 @@ pub enum RecordFieldOrder {
 
 impl RecordField {
     /// Parse a `serde_json::Value` into a `RecordField`.
-    fn parse(field: &Map<String, Value>, position: usize) -> Result<Self, Error> {
+    fn parse(
+        field: &Map<String, Value>,
+        position: usize,
+        parser: &mut Parser,
+        enclosing_namespace: Option<&str>,
+    ) -> Result<Self, Error> {
         let name = field
             .name()
             .ok_or_else(|| ParseSchemaError::new("No `name` in record field"))?;
 
-        // TODO: "type" = "<record name>"
         let schema = field
             .get("type")
             .ok_or_else(|| ParseSchemaError::new("No `type` in record field").into())
-            .and_then(|type_| Schema::parse(type_))?;
+            .and_then(|type_| parser.parse(type_, enclosing_namespace))?;
 
         let default = field.get("default").cloned();
 
@@ -282,35 +500,95 @@ impl RecordField {
         Ok(RecordField {
             name,
             doc: field.doc(),
+            aliases: field.aliases(),
             default,
+            // Defaults can only be parsed once all the named types are known.
+            default_value: None,
             schema,
             order,
             position,
+            props: custom_attributes(
+                field,
+                &["name", "doc", "aliases", "type", "default", "order"],
+            ),
         })
     }
+
+    /// Returns the default value of the field parsed according to the schema of the field: its
+    /// `default_value`, or else its `default` parsed following references to named types through
+    /// `names`, for fields built by hand.
+    pub(crate) fn parsed_default(&self, names: &Names) -> Option<types::Value> {
+        match self.default_value {
+            Some(ref default_value) => Some(default_value.clone()),
+            // The default values accepted when parsing strictly are parsed the same way leniently.
+            None => self
+                .default
+                .as_ref()
+                .and_then(|default| parse_default(default, &self.schema, names, true)),
+        }
+    }
+
+    /// Returns the custom attribute `key` of the field, if any.
+    pub fn prop(&self, key: &str) -> Option<&Value> {
+        self.props.get(key)
+    }
+
+    /// Returns whether a writer field called `name` can be read as this field, that is if `name`
+    /// is the name or one of the aliases of this field.
+    pub fn matches(&self, name: &str) -> bool {
+        self.name == name || self.aliases.iter().flatten().any(|alias| alias == name)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct UnionSchema {
     schemas: Vec<Schema>,
-    // Used to ensure uniqueness of schema inputs, and provide constant time finding of the
-    // schema index given a value.
-    // **NOTE** that this approach does not work for named types, and will have to be modified
-    // to support that. A simple solution is to also keep a mapping of the names used.
+    // Used to ensure uniqueness of unnamed schema inputs, and provide constant time finding of
+    // the schema index given a value.
     variant_index: HashMap<SchemaKind, usize>,
+    // Used to ensure uniqueness of named schema inputs, which can share the same kind, and
+    // provide constant time finding of the schema index given a fullname.
+    named_index: HashMap<String, usize>,
+}
+
+/// The kind of the type underlying an unnamed schema, ignoring its logical type.
+fn underlying_kind(schema: &Schema) -> SchemaKind {
+    match *schema {
+        Schema::Date | Schema::TimeMillis => SchemaKind::Int,
+        Schema::TimeMicros
+        | Schema::TimestampMillis
+        | Schema::TimestampMicros
+        | Schema::LocalTimestampMillis
+        | Schema::LocalTimestampMicros => SchemaKind::Long,
+        Schema::Uuid => SchemaKind::String,
+        Schema::Decimal { ref inner, .. } | Schema::Annotated { ref inner, .. } => {
+            underlying_kind(inner)
+        },
+        ref other => SchemaKind::from(other),
+    }
 }
 
 impl UnionSchema {
     pub(crate) fn new(schemas: Vec<Schema>) -> Result<Self, Error> {
         let mut vindex = HashMap::new();
+        let mut nindex = HashMap::new();
+        // Logical types are encoded as their underlying type, so they cannot be told apart from it.
+        let mut underlying = HashSet::new();
         for (i, schema) in schemas.iter().enumerate() {
             if let Schema::Union(_) = schema {
                 Err(ParseSchemaError::new(
                     "Unions may not directly contain a union",
                 ))?;
             }
-            let kind = SchemaKind::from(schema);
-            if vindex.insert(kind, i).is_some() {
+            let duplicate = match schema.name() {
+                // Named types, references included, are distinct as long as their names are.
+                Some(name) => nindex.insert(name.fullname(None), i).is_some(),
+                None => {
+                    vindex.insert(SchemaKind::from(schema), i);
+                    !underlying.insert(underlying_kind(schema))
+                },
+            };
+            if duplicate {
                 Err(ParseSchemaError::new(
                     "Unions cannot contain duplicate types",
                 ))?;
@@ -319,6 +597,7 @@ impl UnionSchema {
         Ok(UnionSchema {
             schemas,
             variant_index: vindex,
+            named_index: nindex,
         })
     }
 
@@ -334,22 +613,89 @@ impl UnionSchema {
 
     /// Optionally returns a reference to the schema matched by this value, as well as its position
     /// within this enum.
+    ///
+    /// When several named types of the kind of the value are part of this union, the first one the
+    /// value is valid against is returned.
+    ///
+    /// **NOTE** Variants referring to named types by name are not considered; see `find_schema_in`.
     pub fn find_schema(&self, value: &::types::Value) -> Option<(usize, &Schema)> {
         let kind = SchemaKind::from(value);
-        self.variant_index
-            .get(&kind)
-            .cloned()
-            .map(|i| (i, &self.schemas[i]))
+        match self.variant_index.get(&kind) {
+            Some(&i) => Some((i, &self.schemas[i])),
+            None => self.find_named_schema(value, &HashMap::new()),
+        }
+    }
+
+    /// Optionally returns a reference to the named type called `fullname` within this union, as
+    /// well as its position. The schema can be a `Schema::Ref`.
+    pub fn find_named(&self, fullname: &str) -> Option<(usize, &Schema)> {
+        self.named_index
+            .get(fullname)
+            .map(|&i| (i, &self.schemas[i]))
+    }
+
+    /// Find the named type matched by `value`, following references through `names`.
+    fn find_named_schema<'a>(
+        &'a self,
+        value: &::types::Value,
+        names: &Names<'a>,
+    ) -> Option<(usize, &'a Schema)> {
+        let kind = SchemaKind::from(value);
+        let candidates = self
+            .schemas
+            .iter()
+            .enumerate()
+            .filter(|(_, schema)| schema.name().is_some())
+            .filter_map(|(i, schema)| schema.resolve_ref(names).map(|schema| (i, schema)))
+            .filter(|(_, schema)| SchemaKind::from(*schema) == kind)
+            .collect::<Vec<_>>();
+
+        // Values do not carry the name of their type, so tell named types of the same kind apart
+        // by their structure.
+        match candidates.len() {
+            1 => Some(candidates[0]),
+            _ => candidates
+                .into_iter()
+                .find(|(_, schema)| value.validate_internal(schema, names)),
+        }
+    }
+
+    /// Like `find_schema`, but also follows the variants referring to named types through `names`.
+    ///
+    /// The returned schema is always the definition, never a `Schema::Ref`.
+    pub(crate) fn find_schema_in<'a>(
+        &'a self,
+        value: &::types::Value,
+        names: &Names<'a>,
+    ) -> Option<(usize, &'a Schema)> {
+        if let Some(&i) = self.variant_index.get(&SchemaKind::from(value)) {
+            return Some((i, &self.schemas[i]))
+        }
+
+        // A string in its canonical UUID form can be written as a uuid.
+        if let ::types::Value::String(ref s) = *value {
+            if parse_uuid(s).is_some() {
+                if let Some(&i) = self.variant_index.get(&SchemaKind::Uuid) {
+                    return Some((i, &self.schemas[i]))
+                }
+            }
+        }
+
+        self.find_named_schema(value, names)
     }
 }
 
-// No need to compare variant_index, it is derivative of schemas.
+// No need to compare variant_index and named_index, they are derivative of schemas.
 impl PartialEq for UnionSchema {
     fn eq(&self, other: &UnionSchema) -> bool {
         self.schemas.eq(&other.schemas)
     }
 }
 
+/// Lookup table from the `fullname` of every named type defined within a `Schema` to its
+/// definition. Used to follow `Schema::Ref`s while encoding, decoding and resolving values.
+pub(crate) type Names<'a> = HashMap<String, &'a Schema>;
+
 impl Schema {
     /// Create a `Schema` from a string representing a JSON Avro schema.
     pub fn parse_str(input: &str) -> Result<Self, Error> {
@@ -359,13 +705,26 @@ impl Schema {
 
     /// Create a `Schema` from a `serde_json::Value` representing a JSON Avro
     /// schema.
+    ///
+    /// The schema must conform to the Avro specification: names must be made of
+    /// `[A-Za-z0-9_]` characters without starting with a digit, fields of a record and symbols
+    /// of an enum must be unique, and default values must match the type of their field.
     pub fn parse(value: &Value) -> Result<Self, Error> {
-        match *value {
-            Value::String(ref t) => Schema::parse_primitive(t.as_str()),
-            Value::Object(ref data) => Schema::parse_complex(data),
-            Value::Array(ref data) => Schema::parse_union(data),
-            _ => Err(ParseSchemaError::new("Must be a JSON string, object or array").into()),
-        }
+        Parser::default().parse_root(value)
+    }
+
+    /// Like `parse_str`, but using the lenient rules of `parse_lenient`.
+    pub fn parse_str_lenient(input: &str) -> Result<Self, Error> {
+        let value = serde_json::from_str(input)?;
+        Self::parse_lenient(&value)
+    }
+
+    /// Like `parse`, but accepting schemas which do not conform to the Avro specification as long
+    /// as they can be used, as older versions of this library did. Names are not checked,
+    /// duplicate fields and symbols are allowed, record fields which are not JSON objects are
+    /// ignored, and default values are not checked.
+    pub fn parse_lenient(value: &Value) -> Result<Self, Error> {
+        Parser::lenient().parse_root(value)
     }
 
     /// Converts `self` into its [Parsing Canonical Form].
@@ -377,10 +736,236 @@ impl Schema {
         parsing_canonical_form(&json)
     }
 
-    /// Parse a `serde_json::Value` representing a primitive Avro type into a
-    /// `Schema`.
-    fn parse_primitive(primitive: &str) -> Result<Self, Error> {
-        match primitive {
+    /// Computes the fingerprint of `self` using `algorithm`, over its [Parsing Canonical Form].
+    ///
+    /// [Parsing Canonical Form]:
+    /// https://avro.apache.org/docs/1.8.2/spec.html#Parsing+Canonical+Form+for+Schemas
+    pub fn fingerprint(&self, algorithm: FingerprintAlgorithm) -> SchemaFingerprint {
+        SchemaFingerprint::compute(algorithm, self.canonical_form().as_bytes())
+    }
+
+    /// Collect all the named types defined within `self`, indexed by their `fullname`.
+    pub(crate) fn names(&self) -> Names<'_> {
+        let mut names = HashMap::new();
+        self.collect_names(&mut names);
+        names
+    }
+
+    fn collect_names<'a>(&'a self, names: &mut Names<'a>) {
+        match *self {
+            Schema::Array(ref inner) | Schema::Map(ref inner) => inner.collect_names(names),
+            Schema::Union(ref inner) => for variant in inner.variants() {
+                variant.collect_names(names);
+            },
+            Schema::Record {
+                ref name,
+                ref fields,
+                ..
+            } => {
+                names.insert(name.fullname(None), self);
+                for field in fields {
+                    field.schema.collect_names(names);
+                }
+            },
+            Schema::Enum { ref name, .. }
+            | Schema::Fixed { ref name, .. }
+            | Schema::Duration { ref name } => {
+                names.insert(name.fullname(None), self);
+            },
+            Schema::Annotated { ref inner, .. } => inner.collect_names(names),
+            // References to a `fixed` annotated by a logical type keep the logical type.
+            Schema::Decimal { ref inner, .. } => {
+                if let Schema::Fixed { ref name, .. } = **inner {
+                    names.insert(name.fullname(None), self);
+                }
+            },
+            _ => (),
+        }
+    }
+
+    /// Follow `self` to its definition in `names` if it is a `Schema::Ref`, and past its custom
+    /// attributes if it is a `Schema::Annotated`, otherwise return `self`. Returns `None` for
+    /// references to names that are not defined.
+    pub(crate) fn resolve_ref<'a>(&'a self, names: &Names<'a>) -> Option<&'a Schema> {
+        match *self {
+            Schema::Ref { ref name } => names.get(&name.fullname(None)).cloned(),
+            Schema::Annotated { ref inner, .. } => inner.resolve_ref(names),
+            ref schema => Some(schema),
+        }
+    }
+
+    /// Returns the schema of the field called `name`, if this schema is a record which has one.
+    pub(crate) fn field_schema(&self, name: &str) -> Option<&Schema> {
+        match *self {
+            Schema::Record {
+                ref fields,
+                ref lookup,
+                ..
+            } => lookup.get(name).map(|&position| &fields[position].schema),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of this schema if it is a named type (`record`, `enum` or `fixed`),
+    /// possibly annotated by a logical type, or a reference to one.
+    pub fn name(&self) -> Option<&Name> {
+        match *self {
+            Schema::Record { ref name, .. }
+            | Schema::Enum { ref name, .. }
+            | Schema::Fixed { ref name, .. }
+            | Schema::Duration { ref name }
+            | Schema::Ref { ref name } => Some(name),
+            Schema::Decimal { ref inner, .. } | Schema::Annotated { ref inner, .. } => inner.name(),
+            _ => None,
+        }
+    }
+
+    /// Returns the custom attributes of this schema, if it has any.
+    ///
+    /// Custom attributes are all the attributes which are not defined by the Avro specification,
+    /// they are kept when parsing a schema and written back when serializing it, but never take
+    /// part in its Parsing Canonical Form.
+    pub fn props(&self) -> Option<&Props> {
+        let props = match *self {
+            Schema::Record { ref props, .. }
+            | Schema::Enum { ref props, .. }
+            | Schema::Fixed { ref props, .. }
+            | Schema::Annotated { ref props, .. } => props,
+            _ => return None,
+        };
+
+        if props.is_empty() {
+            None
+        } else {
+            Some(props)
+        }
+    }
+
+    /// Returns the custom attribute `key` of this schema, if any.
+    pub fn prop(&self, key: &str) -> Option<&Value> {
+        self.props().and_then(|props| props.get(key))
+    }
+
+    /// Attach the custom attributes `props` to this schema.
+    fn with_props(mut self, props: Props) -> Schema {
+        if props.is_empty() {
+            return self
+        }
+
+        match self {
+            Schema::Record {
+                props: ref mut own, ..
+            }
+            | Schema::Enum {
+                props: ref mut own, ..
+            }
+            | Schema::Fixed {
+                props: ref mut own, ..
+            } => *own = props,
+            // References, like the schemas which cannot hold custom attributes, are annotated.
+            inner => {
+                return Schema::Annotated {
+                    inner: Box::new(inner),
+                    props,
+                }
+            },
+        }
+        self
+    }
+
+    /// Attributes defined by the Avro specification for this schema, as opposed to custom ones.
+    fn reserved_attributes(&self) -> &'static [&'static str] {
+        match *self {
+            Schema::Record { .. } => &["type", "name", "namespace", "aliases", "doc", "fields"],
+            Schema::Enum { .. } => &["type", "name", "namespace", "aliases", "doc", "symbols"],
+            Schema::Fixed { .. } => &["type", "name", "namespace", "aliases", "size"],
+            Schema::Array(_) => &["type", "items"],
+            Schema::Map(_) => &["type", "values"],
+            Schema::Decimal { ref inner, .. } => match **inner {
+                Schema::Fixed { .. } => &[
+                    "type",
+                    "name",
+                    "namespace",
+                    "aliases",
+                    "size",
+                    "logicalType",
+                    "precision",
+                    "scale",
+                ],
+                _ => &["type", "logicalType", "precision", "scale"],
+            },
+            Schema::Duration { .. } => {
+                &["type", "name", "namespace", "aliases", "size", "logicalType"]
+            },
+            Schema::Date
+            | Schema::TimeMillis
+            | Schema::TimeMicros
+            | Schema::TimestampMillis
+            | Schema::TimestampMicros
+            | Schema::LocalTimestampMillis
+            | Schema::LocalTimestampMicros
+            | Schema::Uuid => &["type", "logicalType"],
+            _ => &["type"],
+        }
+    }
+}
+
+/// Parser of JSON Avro schemas.
+///
+/// Keeps track of the named types defined so far, so that they can be referred to by name (and
+/// recursively) later in the same schema.
+#[derive(Default)]
+struct Parser {
+    named_types: HashMap<String, SchemaKind>,
+    /// Named types defined outside of the schema being parsed, by the other sources of a
+    /// `SchemaSet`. They are parsed where they are first referred to.
+    external_types: HashMap<String, ExternalType>,
+    /// Whether to skip the checks ensuring that the schema conforms to the Avro specification.
+    lenient: bool,
+}
+
+/// The JSON definition of a named type, along with the namespace enclosing it.
+#[derive(Clone)]
+struct ExternalType {
+    definition: Value,
+    enclosing_namespace: Option<String>,
+}
+
+impl Parser {
+    fn lenient() -> Parser {
+        Parser {
+            lenient: true,
+            ..Parser::default()
+        }
+    }
+
+    /// Parse a `serde_json::Value` representing a whole Avro schema into a `Schema`.
+    fn parse_root(&mut self, value: &Value) -> Result<Schema, Error> {
+        let mut schema = self.parse(value, None)?;
+        let mut defaults = Vec::new();
+        parse_defaults(&schema, &schema.names(), self.lenient, &mut defaults)?;
+        set_defaults(&mut schema, &mut defaults.into_iter());
+        Ok(schema)
+    }
+
+    /// Parse a `serde_json::Value` representing any Avro type into a `Schema`.
+    fn parse(&mut self, value: &Value, enclosing_namespace: Option<&str>) -> Result<Schema, Error> {
+        match *value {
+            Value::String(ref t) => self.parse_known_schema(t.as_str(), enclosing_namespace),
+            Value::Object(ref data) => self.parse_complex(data, enclosing_namespace),
+            Value::Array(ref data) => self.parse_union(data, enclosing_namespace),
+            _ => Err(ParseSchemaError::new("Must be a JSON string, object or array").into()),
+        }
+    }
+
+    /// Parse a type name, which is either a primitive Avro type or a reference to a named type
+    /// defined earlier, into a `Schema`.
+    fn parse_known_schema(
+        &mut self,
+        name: &str,
+        enclosing_namespace: Option<&str>,
+    ) -> Result<Schema, Error> {
+        match name {
             "null" => Ok(Schema::Null),
             "boolean" => Ok(Schema::Boolean),
             "int" => Ok(Schema::Int),
@@ -389,8 +974,49 @@ impl Schema {
             "float" => Ok(Schema::Float),
             "bytes" => Ok(Schema::Bytes),
             "string" => Ok(Schema::String),
-            other => Err(ParseSchemaError::new(format!("Unknown type: {}", other)).into()),
+            other => self.parse_reference(other, enclosing_namespace),
+        }
+    }
+
+    /// Resolve a reference to a named type into a `Schema::Ref`.
+    ///
+    /// Unqualified names are first looked up in the enclosing namespace, then in the null
+    /// namespace. References to external types not defined yet are replaced by their definition.
+    fn parse_reference(
+        &mut self,
+        name: &str,
+        enclosing_namespace: Option<&str>,
+    ) -> Result<Schema, Error> {
+        let candidates = [
+            Name::new(name).qualified(enclosing_namespace),
+            Name::new(name).qualified(None),
+        ];
+
+        for name in &candidates {
+            if self.named_types.contains_key(&name.fullname(None)) {
+                return Ok(Schema::Ref { name: name.clone() })
+            }
+        }
+
+        for name in &candidates {
+            if let Some(external) = self.external_types.get(&name.fullname(None)).cloned() {
+                let enclosing_namespace = external.enclosing_namespace.as_deref();
+                return self.parse(&external.definition, enclosing_namespace)
+            }
+        }
+
+        Err(ParseSchemaError::new(format!("Unknown type: {}", name)).into())
+    }
+
+    /// Register a named type, failing if its `fullname` has already been defined.
+    fn register(&mut self, name: &Name, kind: SchemaKind) -> Result<(), Error> {
+        let fullname = name.fullname(None);
+        if self.named_types.insert(fullname.clone(), kind).is_some() {
+            return Err(
+                ParseSchemaError::new(format!("Duplicate definition of type {}", fullname)).into(),
+            )
         }
+        Ok(())
     }
 
     /// Parse a `serde_json::Value` representing a complex Avro type into a
@@ -398,48 +1024,229 @@ impl Schema {
     ///
     /// Avro supports "recursive" definition of types.
     /// e.g: {"type": {"type": "string"}}
-    fn parse_complex(complex: &Map<String, Value>) -> Result<Self, Error> {
+    fn parse_complex(
+        &mut self,
+        complex: &Map<String, Value>,
+        enclosing_namespace: Option<&str>,
+    ) -> Result<Schema, Error> {
         match complex.get("type") {
-            Some(&Value::String(ref t)) => match t.as_str() {
-                "record" => Schema::parse_record(complex),
-                "enum" => Schema::parse_enum(complex),
-                "array" => Schema::parse_array(complex),
-                "map" => Schema::parse_map(complex),
-                "fixed" => Schema::parse_fixed(complex),
-                other => Schema::parse_primitive(other),
-            },
-            Some(&Value::Object(ref data)) => match data.get("type") {
-                Some(ref value) => Schema::parse(value),
-                None => Err(
-                    ParseSchemaError::new(format!("Unknown complex type: {:?}", complex)).into(),
-                ),
+            Some(&Value::String(ref t)) => {
+                if let Some(name) = self.already_inlined(complex, t, enclosing_namespace) {
+                    return Ok(Schema::Ref { name })
+                }
+
+                let schema = match t.as_str() {
+                    "record" => self.parse_record(complex, enclosing_namespace),
+                    "enum" => self.parse_enum(complex, enclosing_namespace),
+                    "array" => self.parse_array(complex, enclosing_namespace),
+                    "map" => self.parse_map(complex, enclosing_namespace),
+                    "fixed" => self.parse_fixed(complex, enclosing_namespace),
+                    other => self.parse_known_schema(other, enclosing_namespace),
+                }?;
+                let schema = Parser::parse_logical_type(complex, schema)?;
+                let props = custom_attributes(complex, schema.reserved_attributes());
+                Ok(schema.with_props(props))
             },
+            Some(&Value::Object(ref data)) => self.parse_complex(data, enclosing_namespace),
             _ => Err(ParseSchemaError::new("No `type` in complex type").into()),
         }
     }
 
+    /// Check that the name, namespace and aliases of a named type are valid.
+    fn check_name(&self, name: &Name, type_: &str) -> Result<(), Error> {
+        if self.lenient {
+            return Ok(())
+        }
+
+        if !is_valid_name(&name.name) {
+            Err(ParseSchemaError::new(format!(
+                "Invalid name of {}: {:?}",
+                type_, name.name
+            )))?;
+        }
+        if let Some(ref namespace) = name.namespace {
+            if !is_valid_fullname(namespace) {
+                Err(ParseSchemaError::new(format!(
+                    "Invalid namespace of {} {}: {:?}",
+                    type_, name.name, namespace
+                )))?;
+            }
+        }
+        for alias in name.aliases.iter().flatten() {
+            if !is_valid_fullname(alias) {
+                Err(ParseSchemaError::new(format!(
+                    "Invalid alias of {} {}: {:?}",
+                    type_,
+                    name.fullname(None),
+                    alias
+                )))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the name and aliases of a field of the record `record` are valid, and that its
+    /// name is not already used by one of the fields in `lookup`.
+    fn check_field(
+        &self,
+        field: &RecordField,
+        record: &Name,
+        lookup: &HashMap<String, usize>,
+    ) -> Result<(), Error> {
+        if self.lenient {
+            return Ok(())
+        }
+
+        if !is_valid_name(&field.name) {
+            Err(ParseSchemaError::new(format!(
+                "Invalid name of field in record {}: {:?}",
+                record.fullname(None),
+                field.name
+            )))?;
+        }
+        if lookup.contains_key(&field.name) {
+            Err(ParseSchemaError::new(format!(
+                "Duplicate field {} in record {}",
+                field.name,
+                record.fullname(None)
+            )))?;
+        }
+        for alias in field.aliases.iter().flatten() {
+            if !is_valid_name(alias) {
+                Err(ParseSchemaError::new(format!(
+                    "Invalid alias of field {} in record {}: {:?}",
+                    field.name,
+                    record.fullname(None),
+                    alias
+                )))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the name of the named type defined by `complex` if it is an external type which has
+    /// already been parsed where it was first referred to, in which case `complex` must be
+    /// replaced by a reference rather than defining the type twice.
+    fn already_inlined(
+        &self,
+        complex: &Map<String, Value>,
+        type_: &str,
+        enclosing_namespace: Option<&str>,
+    ) -> Option<Name> {
+        match type_ {
+            "record" | "enum" | "fixed" => (),
+            _ => return None,
+        }
+
+        let name = Name::parse(complex).ok()?.qualified(enclosing_namespace);
+        let fullname = name.fullname(None);
+        if self.external_types.contains_key(&fullname) && self.named_types.contains_key(&fullname)
+        {
+            Some(Name::new(&fullname).qualified(None))
+        } else {
+            None
+        }
+    }
+
+    /// Annotate `schema` with the `logicalType` defined in `complex`, if any.
+    ///
+    /// As required by the Avro specification, unknown logical types and logical types with invalid
+    /// attributes are ignored, and the underlying `schema` is used instead.
+    fn parse_logical_type(complex: &Map<String, Value>, schema: Schema) -> Result<Schema, Error> {
+        let logical_type = match complex.string("logicalType") {
+            Some(logical_type) => logical_type,
+            None => return Ok(schema),
+        };
+
+        Ok(match (logical_type.as_str(), schema) {
+            ("decimal", schema @ Schema::Bytes) | ("decimal", schema @ Schema::Fixed { .. }) => {
+                Parser::parse_decimal(complex, schema)
+            },
+            ("date", Schema::Int) => Schema::Date,
+            ("time-millis", Schema::Int) => Schema::TimeMillis,
+            ("time-micros", Schema::Long) => Schema::TimeMicros,
+            ("timestamp-millis", Schema::Long) => Schema::TimestampMillis,
+            ("timestamp-micros", Schema::Long) => Schema::TimestampMicros,
+            ("local-timestamp-millis", Schema::Long) => Schema::LocalTimestampMillis,
+            ("local-timestamp-micros", Schema::Long) => Schema::LocalTimestampMicros,
+            ("uuid", Schema::String) => Schema::Uuid,
+            ("duration", Schema::Fixed { name, size, .. }) => {
+                if size != 12 {
+                    return Err(ParseSchemaError::new(format!(
+                        "`duration` must annotate a fixed of size 12, got {}",
+                        size
+                    )).into())
+                }
+                Schema::Duration { name }
+            },
+            (_, schema) => schema,
+        })
+    }
+
+    /// Parse the attributes of a `decimal` logical type annotating `inner`.
+    fn parse_decimal(complex: &Map<String, Value>, inner: Schema) -> Schema {
+        let precision = complex.get("precision").and_then(|v| v.as_u64());
+        let scale = match complex.get("scale") {
+            Some(scale) => scale.as_u64(),
+            None => Some(0),
+        };
+
+        let max_precision = match inner {
+            Schema::Fixed { size, .. } => max_decimal_precision(size),
+            _ => usize::MAX,
+        };
+
+        match (precision, scale) {
+            (Some(precision), Some(scale))
+                if precision > 0 && scale <= precision && precision as usize <= max_precision =>
+            {
+                Schema::Decimal {
+                    precision: precision as usize,
+                    scale: scale as usize,
+                    inner: Box::new(inner),
+                }
+            },
+            _ => inner,
+        }
+    }
+
     /// Parse a `serde_json::Value` representing a Avro record type into a
     /// `Schema`.
-    fn parse_record(complex: &Map<String, Value>) -> Result<Self, Error> {
-        let name = Name::parse(complex)?;
-
+    fn parse_record(
+        &mut self,
+        complex: &Map<String, Value>,
+        enclosing_namespace: Option<&str>,
+    ) -> Result<Schema, Error> {
+        let name = Name::parse(complex)?.qualified(enclosing_namespace);
+        self.check_name(&name, "record")?;
+
+        // Registering the name before parsing the fields allows recursive records.
+        self.register(&name, SchemaKind::Record)?;
+
+        let namespace = name.namespace.clone();
+        let enclosing_namespace = namespace.as_deref();
         let mut lookup = HashMap::new();
 
-        let fields: Vec<RecordField> = complex
+        let json_fields = complex
             .get("fields")
             .and_then(|fields| fields.as_array())
-            .ok_or_else(|| ParseSchemaError::new("No `fields` in record").into())
-            .and_then(|fields| {
-                fields
-                    .iter()
-                    .filter_map(|field| field.as_object())
-                    .enumerate()
-                    .map(|(position, field)| RecordField::parse(field, position))
-                    .collect::<Result<_, _>>()
-            })?;
-
-        for field in &fields {
+            .ok_or_else(|| ParseSchemaError::new("No `fields` in record"))?;
+
+        let mut fields = Vec::with_capacity(json_fields.len());
+        for field in json_fields {
+            let field = match field.as_object() {
+                Some(field) => field,
+                None if self.lenient => continue,
+                None => Err(ParseSchemaError::new(format!(
+                    "Invalid field in record {}: {} is not a JSON object",
+                    name.fullname(None),
+                    field
+                )))?,
+            };
+            let field = RecordField::parse(field, fields.len(), self, enclosing_namespace)?;
+            self.check_field(&field, &name, &lookup)?;
             lookup.insert(field.name.clone(), field.position);
+            fields.push(field);
         }
 
         Ok(Schema::Record {
@@ -447,15 +1254,21 @@ impl Schema {
             doc: complex.doc(),
             fields,
             lookup,
+            props: Props::new(),
         })
     }
 
     /// Parse a `serde_json::Value` representing a Avro enum type into a
     /// `Schema`.
-    fn parse_enum(complex: &Map<String, Value>) -> Result<Self, Error> {
-        let name = Name::parse(complex)?;
-
-        let symbols = complex
+    fn parse_enum(
+        &mut self,
+        complex: &Map<String, Value>,
+        enclosing_namespace: Option<&str>,
+    ) -> Result<Schema, Error> {
+        let name = Name::parse(complex)?.qualified(enclosing_namespace);
+        self.check_name(&name, "enum")?;
+
+        let symbols: Vec<String> = complex
             .get("symbols")
             .and_then(|v| v.as_array())
             .ok_or_else(|| ParseSchemaError::new("No `symbols` field in enum"))
@@ -467,56 +1280,106 @@ impl Schema {
                     .ok_or_else(|| ParseSchemaError::new("Unable to parse `symbols` in enum"))
             })?;
 
+        if !self.lenient {
+            let mut unique = HashSet::new();
+            for symbol in &symbols {
+                if !is_valid_name(symbol) {
+                    Err(ParseSchemaError::new(format!(
+                        "Invalid symbol {:?} in enum {}",
+                        symbol,
+                        name.fullname(None)
+                    )))?;
+                }
+                if !unique.insert(symbol) {
+                    Err(ParseSchemaError::new(format!(
+                        "Duplicate symbol {} in enum {}",
+                        symbol,
+                        name.fullname(None)
+                    )))?;
+                }
+            }
+        }
+
+        self.register(&name, SchemaKind::Enum)?;
+
         Ok(Schema::Enum {
             name,
             doc: complex.doc(),
             symbols,
+            props: Props::new(),
         })
     }
 
     /// Parse a `serde_json::Value` representing a Avro array type into a
     /// `Schema`.
-    fn parse_array(complex: &Map<String, Value>) -> Result<Self, Error> {
+    fn parse_array(
+        &mut self,
+        complex: &Map<String, Value>,
+        enclosing_namespace: Option<&str>,
+    ) -> Result<Schema, Error> {
         complex
             .get("items")
             .ok_or_else(|| ParseSchemaError::new("No `items` in array").into())
-            .and_then(|items| Schema::parse(items))
+            .and_then(|items| self.parse(items, enclosing_namespace))
             .map(|schema| Schema::Array(Box::new(schema)))
     }
 
     /// Parse a `serde_json::Value` representing a Avro map type into a
     /// `Schema`.
-    fn parse_map(complex: &Map<String, Value>) -> Result<Self, Error> {
+    fn parse_map(
+        &mut self,
+        complex: &Map<String, Value>,
+        enclosing_namespace: Option<&str>,
+    ) -> Result<Schema, Error> {
         complex
             .get("values")
             .ok_or_else(|| ParseSchemaError::new("No `values` in map").into())
-            .and_then(|items| Schema::parse(items))
+            .and_then(|items| self.parse(items, enclosing_namespace))
             .map(|schema| Schema::Map(Box::new(schema)))
     }
 
     /// Parse a `serde_json::Value` representing a Avro union type into a
     /// `Schema`.
-    fn parse_union(items: &[Value]) -> Result<Self, Error> {
-        items
+    fn parse_union(
+        &mut self,
+        items: &[Value],
+        enclosing_namespace: Option<&str>,
+    ) -> Result<Schema, Error> {
+        let schemas = items
             .iter()
-            .map(Schema::parse)
-            .collect::<Result<Vec<_>, _>>()
-            .and_then(|schemas| Ok(Schema::Union(UnionSchema::new(schemas)?)))
+            .map(|item| self.parse(item, enclosing_namespace))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Schema::Union(UnionSchema::new(schemas)?))
     }
 
     /// Parse a `serde_json::Value` representing a Avro fixed type into a
     /// `Schema`.
-    fn parse_fixed(complex: &Map<String, Value>) -> Result<Self, Error> {
-        let name = Name::parse(complex)?;
+    fn parse_fixed(
+        &mut self,
+        complex: &Map<String, Value>,
+        enclosing_namespace: Option<&str>,
+    ) -> Result<Schema, Error> {
+        let name = Name::parse(complex)?.qualified(enclosing_namespace);
+        self.check_name(&name, "fixed")?;
 
         let size = complex
             .get("size")
-            .and_then(|v| v.as_i64())
             .ok_or_else(|| ParseSchemaError::new("No `size` in fixed"))?;
+        let size = size.as_u64().ok_or_else(|| {
+            ParseSchemaError::new(format!(
+                "Invalid size of fixed {}: {} is not a non-negative integer",
+                name.fullname(None),
+                size
+            ))
+        })?;
+
+        self.register(&name, SchemaKind::Fixed)?;
 
         Ok(Schema::Fixed {
             name,
             size: size as usize,
+            props: Props::new(),
         })
     }
 }
@@ -526,32 +1389,85 @@ impl Serialize for Schema {
     where
         S: Serializer,
     {
-        match *self {
-            Schema::Null => serializer.serialize_str("null"),
-            Schema::Boolean => serializer.serialize_str("boolean"),
-            Schema::Int => serializer.serialize_str("int"),
-            Schema::Long => serializer.serialize_str("long"),
-            Schema::Float => serializer.serialize_str("float"),
-            Schema::Double => serializer.serialize_str("double"),
-            Schema::Bytes => serializer.serialize_str("bytes"),
-            Schema::String => serializer.serialize_str("string"),
-            Schema::Array(ref inner) => {
-                let mut map = serializer.serialize_map(Some(2))?;
-                map.serialize_entry("type", "array")?;
-                map.serialize_entry("items", &*inner.clone())?;
+        InNamespace::new(self, None).serialize(serializer)
+    }
+}
+
+impl Serialize for RecordField {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        InNamespace::new(self, None).serialize(serializer)
+    }
+}
+
+/// A schema, or a record field, serialized within the namespace of its enclosing named type.
+///
+/// Named types of the null namespace defined within a namespace need an explicitly empty
+/// `namespace` attribute, otherwise they would inherit the enclosing one when parsed back.
+struct InNamespace<'a, T: 'a> {
+    inner: &'a T,
+    namespace: Option<&'a str>,
+}
+
+impl<'a, T> InNamespace<'a, T> {
+    fn new(inner: &'a T, namespace: Option<&'a str>) -> Self {
+        InNamespace { inner, namespace }
+    }
+
+    fn nested<U>(&self, inner: &'a U) -> InNamespace<'a, U> {
+        InNamespace::new(inner, self.namespace)
+    }
+}
+
+/// Serialize the `namespace` attribute of a named type defined within `enclosing_namespace`.
+fn serialize_namespace<M>(
+    map: &mut M,
+    name: &Name,
+    enclosing_namespace: Option<&str>,
+) -> Result<(), M::Error>
+where
+    M: SerializeMap,
+{
+    match (name.namespace.as_ref(), enclosing_namespace) {
+        (Some(namespace), _) => map.serialize_entry("namespace", namespace),
+        (None, Some(_)) => map.serialize_entry("namespace", ""),
+        (None, None) => Ok(()),
+    }
+}
+
+impl<'a> Serialize for InNamespace<'a, Schema> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self.inner {
+            Schema::Null => serializer.serialize_str("null"),
+            Schema::Boolean => serializer.serialize_str("boolean"),
+            Schema::Int => serializer.serialize_str("int"),
+            Schema::Long => serializer.serialize_str("long"),
+            Schema::Float => serializer.serialize_str("float"),
+            Schema::Double => serializer.serialize_str("double"),
+            Schema::Bytes => serializer.serialize_str("bytes"),
+            Schema::String => serializer.serialize_str("string"),
+            Schema::Array(ref inner) => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("type", "array")?;
+                map.serialize_entry("items", &self.nested(&**inner))?;
                 map.end()
             },
             Schema::Map(ref inner) => {
                 let mut map = serializer.serialize_map(Some(2))?;
                 map.serialize_entry("type", "map")?;
-                map.serialize_entry("values", &*inner.clone())?;
+                map.serialize_entry("values", &self.nested(&**inner))?;
                 map.end()
             },
             Schema::Union(ref inner) => {
                 let variants = inner.variants();
                 let mut seq = serializer.serialize_seq(Some(variants.len()))?;
                 for v in variants {
-                    seq.serialize_element(v)?;
+                    seq.serialize_element(&self.nested(v))?;
                 }
                 seq.end()
             },
@@ -559,13 +1475,12 @@ impl Serialize for Schema {
                 ref name,
                 ref doc,
                 ref fields,
+                ref props,
                 ..
             } => {
                 let mut map = serializer.serialize_map(None)?;
                 map.serialize_entry("type", "record")?;
-                if let Some(ref n) = name.namespace {
-                    map.serialize_entry("namespace", n)?;
-                }
+                serialize_namespace(&mut map, name, self.namespace)?;
                 map.serialize_entry("name", &name.name)?;
                 if let Some(ref docstr) = doc {
                     map.serialize_entry("doc", docstr)?;
@@ -573,48 +1488,453 @@ impl Serialize for Schema {
                 if let Some(ref aliases) = name.aliases {
                     map.serialize_entry("aliases", aliases)?;
                 }
-                map.serialize_entry("fields", fields)?;
+                let fields = fields
+                    .iter()
+                    .map(|field| InNamespace::new(field, name.namespace.as_deref()))
+                    .collect::<Vec<_>>();
+                map.serialize_entry("fields", &fields)?;
+                for (key, value) in props {
+                    map.serialize_entry(key, value)?;
+                }
                 map.end()
             },
             Schema::Enum {
                 ref name,
+                ref doc,
                 ref symbols,
-                ..
+                ref props,
             } => {
                 let mut map = serializer.serialize_map(None)?;
                 map.serialize_entry("type", "enum")?;
+                serialize_namespace(&mut map, name, self.namespace)?;
                 map.serialize_entry("name", &name.name)?;
+                if let Some(ref docstr) = doc {
+                    map.serialize_entry("doc", docstr)?;
+                }
+                if let Some(ref aliases) = name.aliases {
+                    map.serialize_entry("aliases", aliases)?;
+                }
                 map.serialize_entry("symbols", symbols)?;
+                for (key, value) in props {
+                    map.serialize_entry(key, value)?;
+                }
                 map.end()
             },
-            Schema::Fixed { ref name, ref size } => {
+            Schema::Fixed {
+                ref name,
+                ref size,
+                ref props,
+            } => {
                 let mut map = serializer.serialize_map(None)?;
                 map.serialize_entry("type", "fixed")?;
+                serialize_namespace(&mut map, name, self.namespace)?;
                 map.serialize_entry("name", &name.name)?;
+                if let Some(ref aliases) = name.aliases {
+                    map.serialize_entry("aliases", aliases)?;
+                }
                 map.serialize_entry("size", size)?;
+                for (key, value) in props {
+                    map.serialize_entry(key, value)?;
+                }
                 map.end()
             },
+            Schema::Decimal {
+                precision,
+                scale,
+                ref inner,
+            } => serialize_logical_type(
+                serializer,
+                &self.nested(&**inner),
+                &[
+                    ("logicalType", Value::from("decimal")),
+                    ("precision", Value::from(precision)),
+                    ("scale", Value::from(scale)),
+                ],
+            ),
+            Schema::Date => serialize_logical_type(
+                serializer,
+                &self.nested(&Schema::Int),
+                &[("logicalType", Value::from("date"))],
+            ),
+            Schema::TimeMillis => serialize_logical_type(
+                serializer,
+                &self.nested(&Schema::Int),
+                &[("logicalType", Value::from("time-millis"))],
+            ),
+            Schema::TimeMicros => serialize_logical_type(
+                serializer,
+                &self.nested(&Schema::Long),
+                &[("logicalType", Value::from("time-micros"))],
+            ),
+            Schema::TimestampMillis => serialize_logical_type(
+                serializer,
+                &self.nested(&Schema::Long),
+                &[("logicalType", Value::from("timestamp-millis"))],
+            ),
+            Schema::TimestampMicros => serialize_logical_type(
+                serializer,
+                &self.nested(&Schema::Long),
+                &[("logicalType", Value::from("timestamp-micros"))],
+            ),
+            Schema::LocalTimestampMillis => serialize_logical_type(
+                serializer,
+                &self.nested(&Schema::Long),
+                &[("logicalType", Value::from("local-timestamp-millis"))],
+            ),
+            Schema::LocalTimestampMicros => serialize_logical_type(
+                serializer,
+                &self.nested(&Schema::Long),
+                &[("logicalType", Value::from("local-timestamp-micros"))],
+            ),
+            Schema::Uuid => serialize_logical_type(
+                serializer,
+                &self.nested(&Schema::String),
+                &[("logicalType", Value::from("uuid"))],
+            ),
+            Schema::Duration { ref name } => {
+                let fixed = Schema::Fixed {
+                    name: name.clone(),
+                    size: 12,
+                    props: Props::new(),
+                };
+                serialize_logical_type(
+                    serializer,
+                    &InNamespace::new(&fixed, self.namespace),
+                    &[("logicalType", Value::from("duration"))],
+                )
+            },
+            Schema::Annotated {
+                ref inner,
+                ref props,
+            } => {
+                let props = props
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), value.clone()))
+                    .collect::<Vec<_>>();
+                serialize_logical_type(serializer, &self.nested(&**inner), &props)
+            },
+            Schema::Ref { ref name } => serializer.serialize_str(&name.fullname(None)),
         }
     }
 }
 
-impl Serialize for RecordField {
+impl<'a> Serialize for InNamespace<'a, RecordField> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
+        let field = self.inner;
         let mut map = serializer.serialize_map(None)?;
-        map.serialize_entry("name", &self.name)?;
-        map.serialize_entry("type", &self.schema)?;
+        map.serialize_entry("name", &field.name)?;
+        if let Some(ref docstr) = field.doc {
+            map.serialize_entry("doc", docstr)?;
+        }
+        if let Some(ref aliases) = field.aliases {
+            map.serialize_entry("aliases", aliases)?;
+        }
+        map.serialize_entry("type", &self.nested(&field.schema))?;
 
-        if let Some(ref default) = self.default {
+        if let Some(ref default) = field.default {
             map.serialize_entry("default", default)?;
         }
 
+        match field.order {
+            RecordFieldOrder::Ascending => (),
+            RecordFieldOrder::Descending => map.serialize_entry("order", "descending")?,
+            RecordFieldOrder::Ignore => map.serialize_entry("order", "ignore")?,
+        }
+
+        for (key, value) in &field.props {
+            map.serialize_entry(key, value)?;
+        }
+
         map.end()
     }
 }
 
+/// Returns the maximum number of decimal digits that fit in a two's-complement integer of `size`
+/// bytes.
+fn max_decimal_precision(size: usize) -> usize {
+    (2f64.powi(8 * size as i32 - 1) - 1f64).log10().floor() as usize
+}
+
+/// Collect the attributes of `complex` which are not part of `reserved`.
+fn custom_attributes(complex: &Map<String, Value>, reserved: &[&str]) -> Props {
+    complex
+        .iter()
+        .filter(|(key, _)| !reserved.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+/// Returns whether `name` matches `[A-Za-z_][A-Za-z0-9_]*`, as required by the Avro specification.
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => {
+            (first.is_ascii_alphabetic() || first == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        },
+        None => false,
+    }
+}
+
+/// Returns whether `fullname` is a dot-separated sequence of valid names.
+fn is_valid_fullname(fullname: &str) -> bool {
+    fullname.split('.').all(is_valid_name)
+}
+
+/// Parse the default values of the fields of all the records defined within `schema` according
+/// to the types of their fields, into `defaults` in the order of the fields, whether they have a
+/// default value or not.
+///
+/// Invalid default values are errors, unless `lenient` is set, in which case they are ignored.
+fn parse_defaults(
+    schema: &Schema,
+    names: &Names,
+    lenient: bool,
+    defaults: &mut Vec<Option<types::Value>>,
+) -> Result<(), Error> {
+    match *schema {
+        Schema::Array(ref inner) | Schema::Map(ref inner) => {
+            parse_defaults(inner, names, lenient, defaults)
+        },
+        Schema::Union(ref inner) => inner
+            .variants()
+            .iter()
+            .try_for_each(|variant| parse_defaults(variant, names, lenient, defaults)),
+        Schema::Annotated { ref inner, .. } => parse_defaults(inner, names, lenient, defaults),
+        Schema::Record {
+            ref name,
+            ref fields,
+            ..
+        } => {
+            for field in fields {
+                let default_value = field
+                    .default
+                    .as_ref()
+                    .and_then(|default| parse_default(default, &field.schema, names, lenient));
+                if let Some(ref default) = field.default {
+                    if default_value.is_none() && !lenient {
+                        let expected = match field.schema {
+                            Schema::Union(ref inner) => match inner.variants().first() {
+                                Some(first) => {
+                                    format!("{} (the first type of the union)", describe(first))
+                                },
+                                None => "empty union".to_owned(),
+                            },
+                            ref schema => describe(schema),
+                        };
+                        Err(ParseSchemaError::new(format!(
+                            "Invalid default value of field {} in record {}: {} is not a valid {}",
+                            field.name,
+                            name.fullname(None),
+                            default,
+                            expected
+                        )))?;
+                    }
+                }
+                defaults.push(default_value);
+                parse_defaults(&field.schema, names, lenient, defaults)?;
+            }
+            Ok(())
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Set the default values parsed by `parse_defaults`, in the same order, into the `default_value`
+/// of the fields of all the records defined within `schema`.
+fn set_defaults<I>(schema: &mut Schema, defaults: &mut I)
+where
+    I: Iterator<Item = Option<types::Value>>,
+{
+    match *schema {
+        Schema::Array(ref mut inner) | Schema::Map(ref mut inner) => set_defaults(inner, defaults),
+        Schema::Union(ref mut inner) => {
+            for variant in &mut inner.schemas {
+                set_defaults(variant, defaults);
+            }
+        },
+        Schema::Annotated { ref mut inner, .. } => set_defaults(inner, defaults),
+        Schema::Record { ref mut fields, .. } => {
+            for field in fields {
+                field.default_value = defaults.next().and_then(|default_value| default_value);
+                set_defaults(&mut field.schema, defaults);
+            }
+        },
+        _ => {},
+    }
+}
+
+/// Parse the JSON `default` into the value of type `schema` it stands for, or `None` if it is not
+/// a valid default value for `schema`.
+///
+/// As required by the Avro specification, the default value of a union must match the first type
+/// of the union, and the default value of `bytes` and `fixed` is a string whose characters are the
+/// bytes (ISO-8859-1). When `lenient` is set, the default value of a union can match any of its
+/// types.
+fn parse_default(
+    default: &Value,
+    schema: &Schema,
+    names: &Names,
+    lenient: bool,
+) -> Option<types::Value> {
+    let bytes = || {
+        default.as_str().and_then(|s| {
+            s.chars()
+                .map(|c| u8::try_from(u32::from(c)).ok())
+                .collect::<Option<Vec<_>>>()
+        })
+    };
+    let int = || default.as_i64().and_then(|n| i32::try_from(n).ok());
+
+    match *schema {
+        Schema::Null => default.as_null().map(|_| types::Value::Null),
+        Schema::Boolean => default.as_bool().map(types::Value::Boolean),
+        Schema::Int => int().map(types::Value::Int),
+        Schema::Long => default.as_i64().map(types::Value::Long),
+        Schema::Float => default.as_f64().map(|n| types::Value::Float(n as f32)),
+        Schema::Double => default.as_f64().map(types::Value::Double),
+        Schema::Bytes => bytes().map(types::Value::Bytes),
+        Schema::String => default.as_str().map(|s| types::Value::String(s.to_owned())),
+        Schema::Fixed { size, .. } => bytes()
+            .filter(|bytes| bytes.len() == size)
+            .map(|bytes| types::Value::Fixed(size, bytes)),
+        Schema::Enum { ref symbols, .. } => default.as_str().and_then(|symbol| {
+            symbols
+                .iter()
+                .position(|s| s == symbol)
+                .map(|i| types::Value::Enum(i as i32, symbol.to_owned()))
+        }),
+        Schema::Array(ref items) => match *default {
+            Value::Array(ref values) => values
+                .iter()
+                .map(|value| parse_default(value, items, names, lenient))
+                .collect::<Option<_>>()
+                .map(types::Value::Array),
+            _ => None,
+        },
+        Schema::Map(ref inner) => match *default {
+            Value::Object(ref values) => values
+                .iter()
+                .map(|(key, value)| {
+                    parse_default(value, inner, names, lenient).map(|value| (key.clone(), value))
+                }).collect::<Option<_>>()
+                .map(types::Value::Map),
+            _ => None,
+        },
+        Schema::Union(ref inner) => {
+            let variants = inner.variants();
+            let candidates = if lenient {
+                variants
+            } else {
+                &variants[..variants.len().min(1)]
+            };
+            candidates
+                .iter()
+                .enumerate()
+                .filter_map(|(i, variant)| {
+                    parse_default(default, variant, names, lenient)
+                        .map(|value| types::Value::Union(i as u32, Box::new(value)))
+                }).next()
+        },
+        Schema::Record { ref fields, .. } => match *default {
+            Value::Object(ref values) => fields
+                .iter()
+                .map(|field| {
+                    let value = match values.get(&field.name) {
+                        Some(value) => parse_default(value, &field.schema, names, lenient),
+                        None => field.default.as_ref().and_then(|default| {
+                            parse_default(default, &field.schema, names, lenient)
+                        }),
+                    };
+                    value.map(|value| (field.name.clone(), value))
+                }).collect::<Option<_>>()
+                .map(types::Value::Record),
+            _ => None,
+        },
+        Schema::Decimal { ref inner, .. } => match parse_default(default, inner, names, lenient) {
+            Some(types::Value::Bytes(bytes)) | Some(types::Value::Fixed(_, bytes)) => {
+                Some(types::Value::Decimal(Decimal::from(bytes)))
+            },
+            _ => None,
+        },
+        Schema::Date => int().map(types::Value::Date),
+        Schema::TimeMillis => int().map(types::Value::TimeMillis),
+        Schema::TimeMicros => default.as_i64().map(types::Value::TimeMicros),
+        Schema::TimestampMillis => default.as_i64().map(types::Value::TimestampMillis),
+        Schema::TimestampMicros => default.as_i64().map(types::Value::TimestampMicros),
+        Schema::LocalTimestampMillis => default.as_i64().map(types::Value::LocalTimestampMillis),
+        Schema::LocalTimestampMicros => default.as_i64().map(types::Value::LocalTimestampMicros),
+        Schema::Uuid => default
+            .as_str()
+            .and_then(parse_uuid)
+            .map(types::Value::Uuid),
+        Schema::Duration { .. } => bytes()
+            .filter(|bytes| bytes.len() == 12)
+            .map(|bytes| types::duration_from_bytes(&bytes)),
+        Schema::Annotated { ref inner, .. } => parse_default(default, inner, names, lenient),
+        Schema::Ref { .. } => schema
+            .resolve_ref(names)
+            .and_then(|schema| parse_default(default, schema, names, lenient)),
+    }
+}
+
+/// A short description of `schema`, for error messages.
+pub(crate) fn describe(schema: &Schema) -> String {
+    match *schema {
+        Schema::Null => "null".to_owned(),
+        Schema::Boolean => "boolean".to_owned(),
+        Schema::Int => "int".to_owned(),
+        Schema::Long => "long".to_owned(),
+        Schema::Float => "float".to_owned(),
+        Schema::Double => "double".to_owned(),
+        Schema::Bytes => "bytes".to_owned(),
+        Schema::String => "string".to_owned(),
+        Schema::Array(_) => "array".to_owned(),
+        Schema::Map(_) => "map".to_owned(),
+        Schema::Union(_) => "union".to_owned(),
+        Schema::Record { ref name, .. } => format!("record {}", name.fullname(None)),
+        Schema::Enum { ref name, .. } => format!("enum {}", name.fullname(None)),
+        Schema::Fixed { ref name, .. } => format!("fixed {}", name.fullname(None)),
+        Schema::Decimal { .. } => "decimal".to_owned(),
+        Schema::Date => "date".to_owned(),
+        Schema::TimeMillis => "time-millis".to_owned(),
+        Schema::TimeMicros => "time-micros".to_owned(),
+        Schema::TimestampMillis => "timestamp-millis".to_owned(),
+        Schema::TimestampMicros => "timestamp-micros".to_owned(),
+        Schema::LocalTimestampMillis => "local-timestamp-millis".to_owned(),
+        Schema::LocalTimestampMicros => "local-timestamp-micros".to_owned(),
+        Schema::Uuid => "uuid".to_owned(),
+        Schema::Duration { .. } => "duration".to_owned(),
+        Schema::Annotated { ref inner, .. } => describe(inner),
+        Schema::Ref { ref name } => name.fullname(None),
+    }
+}
+
+/// Serialize `inner` as a JSON object extended with the attributes of its logical type.
+fn serialize_logical_type<S>(
+    serializer: S,
+    inner: &InNamespace<Schema>,
+    attributes: &[(&str, Value)],
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut map = serializer.serialize_map(None)?;
+    match serde_json::to_value(inner).map_err(S::Error::custom)? {
+        Value::Object(entries) => for (key, value) in entries {
+            map.serialize_entry(&key, &value)?;
+        },
+        other => map.serialize_entry("type", &other)?,
+    }
+    for &(key, ref value) in attributes {
+        map.serialize_entry(key, value)?;
+    }
+    map.end()
+}
+
 /// Parses a **valid** avro schema into the Parsing Canonical Form.
 /// https://avro.apache.org/docs/1.8.2/spec.html#Parsing+Canonical+Form+for+Schemas
 fn parsing_canonical_form(schema: &serde_json::Value) -> String {
@@ -632,10 +1952,14 @@ fn pcf_map(schema: &Map<String, serde_json::Value>) -> String {
     let mut fields = Vec::new();
     for (k, v) in schema {
         // Reduce primitive types to their simple form. ([PRIMITIVE] rule)
-        if schema.len() == 1 && k == "type" {
+        // Primitive types and references to named types may carry extra attributes (e.g. a
+        // logical type) which are stripped, but are never named, unlike record fields.
+        if k == "type" {
             // Invariant: function is only callable from a valid schema, so this is acceptable.
             if let serde_json::Value::String(s) = v {
-                return pcf_string(s)
+                if schema.len() == 1 || (!is_complex(s) && !schema.contains_key("name")) {
+                    return pcf_string(s)
+                }
             }
         }
 
@@ -649,7 +1973,7 @@ fn pcf_map(schema: &Map<String, serde_json::Value>) -> String {
             // Invariant: Only valid schemas. Must be a string.
             let name = v.as_str().unwrap();
             let n = match ns {
-                Some(namespace) if !name.contains('.') => {
+                Some(namespace) if !namespace.is_empty() && !name.contains('.') => {
                     Cow::Owned(format!("{}.{}", namespace, name))
                 },
                 _ => Cow::Borrowed(name),
@@ -695,6 +2019,10 @@ fn pcf_array(arr: &[serde_json::Value]) -> String {
     format!("[{}]", inter)
 }
 
+fn is_complex(name: &str) -> bool {
+    matches!(name, "record" | "enum" | "array" | "map" | "fixed")
+}
+
 fn pcf_string(s: &str) -> String {
     format!("\"{}\"", s)
 }
@@ -718,6 +2046,10 @@ fn field_ordering_position(field: &str) -> Option<usize> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use decode::decode;
+    use encode::encode_to_vec;
+    use quickcheck::{quickcheck, Arbitrary, Gen};
+    use serde_json::json;
 
     #[test]
     fn test_invalid_schema() {
@@ -787,6 +2119,98 @@ mod tests {
         assert_eq!(variants.next(), None);
     }
 
+    #[test]
+    fn test_union_of_named_types() {
+        let schema = Schema::parse_str(
+            r#"
+            [
+                "null",
+                {
+                    "type": "record",
+                    "name": "created",
+                    "namespace": "orders",
+                    "fields": [{"name": "id", "type": "long"}, {"name": "total", "type": "int"}]
+                },
+                {
+                    "type": "record",
+                    "name": "cancelled",
+                    "namespace": "orders",
+                    "fields": [{"name": "id", "type": "long"}]
+                },
+                {"type": "enum", "name": "state", "symbols": ["A", "B"]},
+                {"type": "enum", "name": "other", "symbols": ["C"]}
+            ]
+            "#,
+        ).unwrap();
+        let union_schema = match schema {
+            Schema::Union(u) => u,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(union_schema.find_named("orders.cancelled").unwrap().0, 2);
+        assert_eq!(union_schema.find_named("other").unwrap().0, 4);
+        assert!(union_schema.find_named("cancelled").is_none());
+
+        let cancelled = types::Value::Record(vec![("id".to_string(), types::Value::Long(1))]);
+        assert_eq!(union_schema.find_schema(&cancelled).unwrap().0, 2);
+        let created = types::Value::Record(vec![
+            ("id".to_string(), types::Value::Long(1)),
+            ("total".to_string(), types::Value::Int(2)),
+        ]);
+        assert_eq!(union_schema.find_schema(&created).unwrap().0, 1);
+        let other = types::Value::Enum(0, "C".to_string());
+        assert_eq!(union_schema.find_schema(&other).unwrap().0, 4);
+        let invalid = types::Value::Record(vec![("x".to_string(), types::Value::Long(1))]);
+        assert!(union_schema.find_schema(&invalid).is_none());
+    }
+
+    #[test]
+    fn test_union_duplicate_named_types() {
+        let record = r#"{"type": "record", "name": "r", "fields": []}"#;
+        let schemas = vec![
+            format!(r#"[{}, "r"]"#, record),
+            r#"["int", {"type": "fixed", "name": "f", "size": 1}, "int"]"#.to_string(),
+        ];
+        for schema in schemas {
+            assert_eq!(
+                Schema::parse_str(&schema).unwrap_err().to_string(),
+                "Failed to parse schema: Unions cannot contain duplicate types"
+            );
+        }
+
+        // A namespace makes two names distinct.
+        let schema = r#"
+            [
+                {"type": "fixed", "name": "f", "size": 1},
+                {"type": "fixed", "name": "f", "namespace": "n", "size": 1}
+            ]
+        "#;
+        assert!(Schema::parse_str(schema).is_ok());
+    }
+
+    #[test]
+    fn test_union_duplicate_underlying_types() {
+        let schemas = [
+            r#"["long", {"type": "long", "logicalType": "timestamp-millis"}]"#,
+            r#"[
+                {"type": "int", "logicalType": "date"},
+                {"type": "int", "logicalType": "time-millis"}
+            ]"#,
+            r#"["string", {"type": "string", "logicalType": "uuid"}]"#,
+            r#"["bytes", {"type": "bytes", "logicalType": "decimal", "precision": 4}]"#,
+            r#"["int", {"type": "int", "foo": "bar"}]"#,
+        ];
+        for schema in schemas.iter() {
+            assert_eq!(
+                Schema::parse_str(schema).unwrap_err().to_string(),
+                "Failed to parse schema: Unions cannot contain duplicate types"
+            );
+        }
+
+        let schema = r#"["int", {"type": "long", "logicalType": "timestamp-millis"}]"#;
+        assert!(Schema::parse_str(schema).is_ok());
+    }
+
     #[test]
     fn test_record_schema() {
         let schema = Schema::parse_str(
@@ -813,21 +2237,28 @@ mod tests {
                 RecordField {
                     name: "a".to_string(),
                     doc: None,
+                    aliases: None,
                     default: Some(Value::Number(42i64.into())),
+                    default_value: Some(types::Value::Long(42)),
                     schema: Schema::Long,
                     order: RecordFieldOrder::Ascending,
                     position: 0,
+                    props: Props::new(),
                 },
                 RecordField {
                     name: "b".to_string(),
                     doc: None,
+                    aliases: None,
                     default: None,
+                    default_value: None,
                     schema: Schema::String,
                     order: RecordFieldOrder::Ascending,
                     position: 1,
+                    props: Props::new(),
                 },
             ],
             lookup,
+            props: Props::new(),
         };
 
         assert_eq!(expected, schema);
@@ -848,6 +2279,7 @@ mod tests {
                 "clubs".to_owned(),
                 "hearts".to_owned(),
             ],
+            props: Props::new(),
         };
 
         assert_eq!(expected, schema);
@@ -860,6 +2292,7 @@ mod tests {
         let expected = Schema::Fixed {
             name: Name::new("test"),
             size: 16usize,
+            props: Props::new(),
         };
 
         assert_eq!(expected, schema);
@@ -893,6 +2326,1211 @@ mod tests {
         assert_eq!("Some documentation".to_owned(), doc.unwrap());
     }
 
+    #[test]
+    fn test_named_type_reference() {
+        let schema = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "Customer",
+                "namespace": "com.acme",
+                "fields": [
+                    {
+                        "name": "billing",
+                        "type": {
+                            "type": "record",
+                            "name": "Address",
+                            "fields": [{"name": "zip", "type": "string"}]
+                        }
+                    },
+                    {"name": "shipping", "type": "Address"},
+                    {"name": "previous", "type": ["null", "com.acme.Address"]}
+                ]
+            }
+        "#,
+        ).unwrap();
+
+        let address = Name {
+            name: "Address".to_owned(),
+            namespace: Some("com.acme".to_owned()),
+            aliases: None,
+        };
+
+        match schema {
+            Schema::Record { ref fields, .. } => {
+                assert_eq!(
+                    SchemaKind::from(&fields[0].schema),
+                    SchemaKind::Record
+                );
+                assert_eq!(
+                    fields[1].schema,
+                    Schema::Ref {
+                        name: address.clone()
+                    }
+                );
+                assert_eq!(
+                    fields[2].schema,
+                    Schema::Union(
+                        UnionSchema::new(vec![Schema::Null, Schema::Ref { name: address }])
+                            .unwrap()
+                    )
+                );
+            },
+            _ => unreachable!(),
+        }
+
+        let names = schema.names();
+        assert!(names.contains_key("com.acme.Customer"));
+        assert!(names.contains_key("com.acme.Address"));
+    }
+
+    #[test]
+    fn test_recursive_record_schema() {
+        let schema = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "LongList",
+                "fields": [
+                    {"name": "value", "type": "long"},
+                    {"name": "next", "type": ["null", "LongList"]}
+                ]
+            }
+        "#,
+        ).unwrap();
+
+        match schema {
+            Schema::Record { ref fields, .. } => assert_eq!(
+                fields[1].schema,
+                Schema::Union(
+                    UnionSchema::new(vec![
+                        Schema::Null,
+                        Schema::Ref {
+                            name: Name::new("LongList")
+                        },
+                    ]).unwrap()
+                )
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_reference_namespace_resolution() {
+        // `Inner` inherits the namespace of `Outer`, while `Other` lives in the null namespace.
+        let schema = Schema::parse_str(
+            r#"
+            [
+                {"type": "fixed", "name": "Other", "size": 2},
+                {
+                    "type": "record",
+                    "name": "Outer",
+                    "namespace": "space",
+                    "fields": [
+                        {"name": "a", "type": {"type": "enum", "name": "Inner", "symbols": ["x"]}},
+                        {"name": "b", "type": "Inner"},
+                        {"name": "c", "type": "space.Inner"},
+                        {"name": "d", "type": "Other"}
+                    ]
+                }
+            ]
+        "#,
+        ).unwrap();
+
+        let names = schema.names();
+        let mut fullnames = names.keys().cloned().collect::<Vec<_>>();
+        fullnames.sort();
+        assert_eq!(fullnames, vec!["Other", "space.Inner", "space.Outer"]);
+
+        let fields = match names["space.Outer"] {
+            Schema::Record { ref fields, .. } => fields,
+            _ => unreachable!(),
+        };
+        assert_eq!(fields[1].schema, fields[2].schema);
+        assert_eq!(
+            fields[3].schema,
+            Schema::Ref {
+                name: Name::new("Other")
+            }
+        );
+    }
+
+    #[test]
+    fn test_unknown_reference() {
+        let schema = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "test",
+                "fields": [{"name": "a", "type": "Unknown"}]
+            }
+        "#,
+        );
+        assert!(schema.is_err());
+    }
+
+    #[test]
+    fn test_duplicate_named_type() {
+        let schema = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "test",
+                "fields": [
+                    {"name": "a", "type": {"type": "fixed", "name": "test", "size": 1}}
+                ]
+            }
+        "#,
+        );
+        assert!(schema.is_err());
+    }
+
+    #[test]
+    fn test_reference_serialization() {
+        let raw_schema = r#"{"type":"record","name":"LongList","fields":[{"name":"value","type":"long"},{"name":"next","type":["null","LongList"]}]}"#;
+        let schema = Schema::parse_str(raw_schema).unwrap();
+        assert_eq!(serde_json::to_string(&schema).unwrap(), raw_schema);
+        assert_eq!(
+            schema.canonical_form(),
+            r#"{"name":"LongList","type":"record","fields":[{"name":"value","type":"long"},{"name":"next","type":["null","LongList"]}]}"#
+        );
+    }
+
+    #[test]
+    fn test_decimal_schema() {
+        let schema = Schema::parse_str(
+            r#"{"type": "bytes", "logicalType": "decimal", "precision": 10, "scale": 2}"#,
+        ).unwrap();
+        assert_eq!(
+            schema,
+            Schema::Decimal {
+                precision: 10,
+                scale: 2,
+                inner: Box::new(Schema::Bytes),
+            }
+        );
+
+        let schema = Schema::parse_str(
+            r#"{"type": "fixed", "name": "amount", "size": 4, "logicalType": "decimal", "precision": 9}"#,
+        ).unwrap();
+        assert_eq!(
+            schema,
+            Schema::Decimal {
+                precision: 9,
+                scale: 0,
+                inner: Box::new(Schema::Fixed {
+                    name: Name::new("amount"),
+                    size: 4,
+                    props: Props::new(),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_decimal_schema() {
+        // Invalid logical types fall back to their underlying type.
+        // Their attributes are kept as custom attributes.
+        let invalid = vec![
+            (
+                r#"{"type": "bytes", "logicalType": "decimal"}"#,
+                SchemaKind::Bytes,
+            ),
+            (
+                r#"{"type": "bytes", "logicalType": "decimal", "precision": 2, "scale": 3}"#,
+                SchemaKind::Bytes,
+            ),
+            (
+                r#"{"type": "bytes", "logicalType": "decimal", "precision": -2}"#,
+                SchemaKind::Bytes,
+            ),
+            (
+                r#"{"type": "fixed", "name": "f", "size": 4, "logicalType": "decimal", "precision": 10}"#,
+                SchemaKind::Fixed,
+            ),
+            (
+                r#"{"type": "string", "logicalType": "decimal", "precision": 10}"#,
+                SchemaKind::String,
+            ),
+        ];
+
+        for (raw_schema, expected) in invalid {
+            let schema = Schema::parse_str(raw_schema).unwrap();
+            assert_eq!(SchemaKind::from(&schema), expected);
+            assert_eq!(schema.prop("logicalType"), Some(&Value::from("decimal")));
+        }
+    }
+
+    #[test]
+    fn test_decimal_serialization() {
+        let raw_schemas = vec![
+            r#"{"logicalType":"decimal","precision":10,"scale":2,"type":"bytes"}"#,
+            r#"{"logicalType":"decimal","name":"amount","precision":9,"scale":0,"size":4,"type":"fixed"}"#,
+        ];
+
+        for raw_schema in &raw_schemas {
+            let schema = Schema::parse_str(raw_schema).unwrap();
+            let json = serde_json::to_value(&schema).unwrap();
+            assert_eq!(json, serde_json::from_str::<Value>(raw_schema).unwrap());
+            assert_eq!(Schema::parse(&json).unwrap(), schema);
+        }
+
+        let schema = Schema::parse_str(raw_schemas[0]).unwrap();
+        assert_eq!(schema.canonical_form(), r#""bytes""#);
+        let schema = Schema::parse_str(raw_schemas[1]).unwrap();
+        assert_eq!(
+            schema.canonical_form(),
+            r#"{"name":"amount","type":"fixed","size":4}"#
+        );
+    }
+
+    #[test]
+    fn test_date_and_time_schemas() {
+        let schemas = vec![
+            (r#"{"type": "int", "logicalType": "date"}"#, Schema::Date),
+            (
+                r#"{"type": "int", "logicalType": "time-millis"}"#,
+                Schema::TimeMillis,
+            ),
+            (
+                r#"{"type": "long", "logicalType": "time-micros"}"#,
+                Schema::TimeMicros,
+            ),
+        ];
+
+        for (raw_schema, expected) in schemas {
+            let schema = Schema::parse_str(raw_schema).unwrap();
+            assert_eq!(schema, expected);
+            // the logical type survives serialization
+            let json = serde_json::to_value(&schema).unwrap();
+            assert_eq!(Schema::parse(&json).unwrap(), expected);
+        }
+
+        // logical types annotating the wrong type are ignored, and kept as custom attributes
+        let invalid = vec![
+            (r#"{"type": "long", "logicalType": "date"}"#, SchemaKind::Long),
+            (
+                r#"{"type": "long", "logicalType": "time-millis"}"#,
+                SchemaKind::Long,
+            ),
+            (
+                r#"{"type": "int", "logicalType": "time-micros"}"#,
+                SchemaKind::Int,
+            ),
+        ];
+
+        for (raw_schema, expected) in invalid {
+            let schema = Schema::parse_str(raw_schema).unwrap();
+            assert_eq!(SchemaKind::from(&schema), expected);
+            assert!(schema.prop("logicalType").is_some());
+        }
+
+        assert_eq!(Schema::Date.canonical_form(), r#""int""#);
+        assert_eq!(Schema::TimeMicros.canonical_form(), r#""long""#);
+    }
+
+    #[test]
+    fn test_timestamp_schemas() {
+        let schemas = vec![
+            ("timestamp-millis", Schema::TimestampMillis),
+            ("timestamp-micros", Schema::TimestampMicros),
+            ("local-timestamp-millis", Schema::LocalTimestampMillis),
+            ("local-timestamp-micros", Schema::LocalTimestampMicros),
+        ];
+
+        for (logical_type, expected) in schemas {
+            let raw_schema = format!(r#"{{"type": "long", "logicalType": "{}"}}"#, logical_type);
+            let schema = Schema::parse_str(&raw_schema).unwrap();
+            assert_eq!(schema, expected);
+            let json = serde_json::to_value(&schema).unwrap();
+            assert_eq!(Schema::parse(&json).unwrap(), expected);
+            assert_eq!(schema.canonical_form(), r#""long""#);
+
+            let raw_schema = format!(r#"{{"type": "int", "logicalType": "{}"}}"#, logical_type);
+            let schema = Schema::parse_str(&raw_schema).unwrap();
+            assert_eq!(SchemaKind::from(&schema), SchemaKind::Int);
+        }
+    }
+
+    #[test]
+    fn test_uuid_schema() {
+        let schema = Schema::parse_str(r#"{"type": "string", "logicalType": "uuid"}"#).unwrap();
+        assert_eq!(schema, Schema::Uuid);
+        let json = serde_json::to_value(&schema).unwrap();
+        assert_eq!(Schema::parse(&json).unwrap(), Schema::Uuid);
+        assert_eq!(schema.canonical_form(), r#""string""#);
+
+        let schema = Schema::parse_str(r#"{"type": "bytes", "logicalType": "uuid"}"#).unwrap();
+        assert_eq!(SchemaKind::from(&schema), SchemaKind::Bytes);
+    }
+
+    #[test]
+    fn test_duration_schema() {
+        let raw_schema = r#"
+            {"type": "fixed", "name": "interval", "size": 12, "logicalType": "duration"}
+        "#;
+        let schema = Schema::parse_str(raw_schema).unwrap();
+        let expected = Schema::Duration {
+            name: Name::new("interval"),
+        };
+        assert_eq!(schema, expected);
+        let json = serde_json::to_value(&schema).unwrap();
+        assert_eq!(Schema::parse(&json).unwrap(), expected);
+        assert_eq!(
+            schema.canonical_form(),
+            r#"{"name":"interval","type":"fixed","size":12}"#
+        );
+
+        let raw_schema = r#"
+            {"type": "fixed", "name": "interval", "size": 16, "logicalType": "duration"}
+        "#;
+        assert!(Schema::parse_str(raw_schema).is_err());
+
+        let raw_schema = r#"{"type": "bytes", "logicalType": "duration"}"#;
+        let schema = Schema::parse_str(raw_schema).unwrap();
+        assert_eq!(SchemaKind::from(&schema), SchemaKind::Bytes);
+    }
+
+    #[test]
+    fn test_custom_attributes() {
+        let raw_schema = r#"
+            {
+                "type": "record",
+                "name": "user",
+                "connect.name": "com.example.user",
+                "fields": [
+                    {
+                        "name": "email",
+                        "type": {"type": "string", "avro.java.string": "String"},
+                        "pii": true
+                    },
+                    {
+                        "name": "status",
+                        "type": {"type": "enum", "name": "status", "symbols": ["on"], "x": 1}
+                    },
+                    {
+                        "name": "hash",
+                        "type": {"type": "fixed", "name": "md5", "size": 16, "x": [2]}
+                    },
+                    {
+                        "name": "tags",
+                        "type": {"type": "array", "items": "string", "x": {"y": 3}}
+                    },
+                    {
+                        "name": "at",
+                        "type": {"type": "long", "logicalType": "timestamp-millis", "x": 4}
+                    },
+                    {"name": "previous", "type": {"type": "status", "x": 5}}
+                ]
+            }
+        "#;
+        let schema = Schema::parse_str(raw_schema).unwrap();
+        assert_eq!(
+            schema.prop("connect.name"),
+            Some(&Value::from("com.example.user"))
+        );
+
+        let fields = match schema {
+            Schema::Record { ref fields, .. } => fields,
+            _ => panic!("record expected"),
+        };
+        assert_eq!(fields[0].prop("pii"), Some(&Value::Bool(true)));
+        assert_eq!(fields[0].prop("name"), None);
+        assert_eq!(
+            fields[0].schema.prop("avro.java.string"),
+            Some(&Value::from("String"))
+        );
+        assert_eq!(SchemaKind::from(&fields[0].schema), SchemaKind::String);
+        assert_eq!(fields[1].schema.prop("x"), Some(&Value::from(1)));
+        assert_eq!(fields[2].schema.prop("x"), Some(&serde_json::json!([2])));
+        assert_eq!(fields[3].schema.prop("x"), Some(&serde_json::json!({"y": 3})));
+        assert_eq!(fields[4].schema.prop("x"), Some(&Value::from(4)));
+        assert_eq!(fields[4].schema.prop("logicalType"), None);
+        assert_eq!(fields[5].schema.prop("x"), Some(&Value::from(5)));
+        assert_eq!(fields[5].schema.name(), Some(&Name::new("status")));
+        assert_eq!(fields[1].prop("x"), None);
+        assert_eq!(Schema::Long.props(), None);
+
+        // custom attributes do not take part in equality
+        assert_eq!(fields[0].schema, Schema::String);
+        assert_eq!(Schema::String, fields[0].schema);
+        assert_eq!(
+            Schema::parse_str(r#"{"type": "enum", "name": "status", "symbols": ["on"]}"#).unwrap(),
+            fields[1].schema
+        );
+
+        // custom attributes are written back, but are not part of the canonical form
+        let json = serde_json::to_value(&schema).unwrap();
+        assert_eq!(json["connect.name"], serde_json::json!("com.example.user"));
+        assert_eq!(json["fields"][0]["pii"], serde_json::json!(true));
+        assert_eq!(json["fields"][5]["type"], serde_json::json!({"type": "status", "x": 5}));
+        assert_eq!(serde_json::to_value(Schema::parse(&json).unwrap()).unwrap(), json);
+        assert!(!schema.canonical_form().contains("connect.name"));
+        assert!(!schema.canonical_form().contains("avro.java.string"));
+        assert!(
+            schema
+                .canonical_form()
+                .contains(r#"{"name":"previous","type":"status"}"#)
+        );
+    }
+
+    #[test]
+    fn test_serialization_round_trip() {
+        let raw_schemas = vec![
+            r#""null""#,
+            r#""boolean""#,
+            r#""int""#,
+            r#""long""#,
+            r#""float""#,
+            r#""double""#,
+            r#""bytes""#,
+            r#""string""#,
+            r#"{"type": "array", "items": "long"}"#,
+            r#"{"type": "map", "values": {"type": "array", "items": "string"}}"#,
+            r#"["null", "int", {"type": "map", "values": "string"}]"#,
+            r#"{"type": "enum", "name": "Suit", "symbols": ["hearts", "spades"]}"#,
+            r#"
+            {
+                "type": "enum",
+                "namespace": "cards",
+                "name": "Suit",
+                "doc": "A suit of cards",
+                "aliases": ["Color", "other.Suit"],
+                "symbols": ["hearts", "spades"]
+            }
+            "#,
+            r#"{"type": "fixed", "name": "md5", "size": 16}"#,
+            r#"
+            {"type": "fixed", "namespace": "hash", "name": "md5", "aliases": ["digest"], "size": 16}
+            "#,
+            r#"
+            {
+                "type": "record",
+                "name": "LongList",
+                "namespace": "lists",
+                "doc": "A linked list of longs",
+                "aliases": ["LinkedLongs"],
+                "fields": [
+                    {"name": "value", "type": "long", "doc": "The value", "aliases": ["v"]},
+                    {"name": "next", "type": ["null", "LongList"], "default": null},
+                    {"name": "rank", "type": "int", "order": "descending"},
+                    {"name": "debug", "type": "string", "order": "ignore"}
+                ]
+            }
+            "#,
+            r#"
+            {
+                "type": "record",
+                "name": "outer",
+                "namespace": "space",
+                "fields": [
+                    {
+                        "name": "suit",
+                        "type": {"type": "enum", "name": "Suit", "symbols": ["hearts"]}
+                    },
+                    {
+                        "name": "hash",
+                        "type": {"type": "fixed", "name": "other.md5", "size": 16}
+                    },
+                    {
+                        "name": "inner",
+                        "type": {
+                            "type": "record",
+                            "name": "inner",
+                            "namespace": "",
+                            "fields": [{"name": "suit", "type": "space.Suit"}]
+                        }
+                    },
+                    {"name": "hashes", "type": {"type": "array", "items": "other.md5"}}
+                ]
+            }
+            "#,
+            r#"{"type": "bytes", "logicalType": "decimal", "precision": 4, "scale": 2}"#,
+            r#"
+            {
+                "type": "fixed",
+                "namespace": "money",
+                "name": "amount",
+                "size": 4,
+                "logicalType": "decimal",
+                "precision": 9
+            }
+            "#,
+            r#"{"type": "int", "logicalType": "date"}"#,
+            r#"{"type": "int", "logicalType": "time-millis"}"#,
+            r#"{"type": "long", "logicalType": "time-micros"}"#,
+            r#"{"type": "long", "logicalType": "timestamp-millis"}"#,
+            r#"{"type": "long", "logicalType": "timestamp-micros"}"#,
+            r#"{"type": "long", "logicalType": "local-timestamp-millis"}"#,
+            r#"{"type": "long", "logicalType": "local-timestamp-micros"}"#,
+            r#"{"type": "string", "logicalType": "uuid"}"#,
+            r#"{"type": "fixed", "name": "interval", "size": 12, "logicalType": "duration"}"#,
+            r#"{"type": "long", "logicalType": "unknown", "x": [1, 2]}"#,
+            r#"
+            {
+                "type": "record",
+                "name": "props",
+                "x": {"y": "z"},
+                "fields": [{"name": "f", "type": "int", "pii": true}]
+            }
+            "#,
+        ];
+
+        for raw_schema in raw_schemas {
+            let schema = Schema::parse_str(raw_schema).unwrap();
+            let serialized = serde_json::to_string(&schema).unwrap();
+            let parsed = Schema::parse_str(&serialized).unwrap();
+            assert_eq!(parsed, schema, "{} was serialized as {}", raw_schema, serialized);
+            assert_eq!(serde_json::to_string(&parsed).unwrap(), serialized);
+            assert_eq!(parsed.canonical_form(), schema.canonical_form());
+        }
+    }
+
+    /// A schema generated by `quickcheck`, as written in JSON, with values of this schema.
+    #[derive(Clone, Debug)]
+    struct ArbitrarySchema {
+        json: Value,
+        values: Vec<types::Value>,
+    }
+
+    impl Arbitrary for ArbitrarySchema {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            let mut generator = SchemaGenerator {
+                g,
+                defined: Vec::new(),
+                count: 0,
+            };
+            let json = generator.schema(None, 3);
+            let schema = Schema::parse(&json).unwrap();
+            let names = schema.names();
+            let values = (0..3)
+                .map(|_| generator.value(&schema, &names, 3))
+                .collect();
+            ArbitrarySchema { json, values }
+        }
+    }
+
+    /// Generates valid schemas, made of every kind of schema, along with values of them.
+    struct SchemaGenerator<'a, G: 'a> {
+        g: &'a mut G,
+        // The fullnames of the named types already defined, which can be referred to.
+        defined: Vec<String>,
+        // The number of named types defined, used to give them unique names.
+        count: usize,
+    }
+
+    impl<'a, G: Gen> SchemaGenerator<'a, G> {
+        fn pick(&mut self, n: usize) -> usize {
+            usize::arbitrary(self.g) % n
+        }
+
+        fn chance(&mut self) -> bool {
+            bool::arbitrary(self.g)
+        }
+
+        /// Generate a schema within the `namespace` of its enclosing named type, made of other
+        /// schemas down to `depth` levels.
+        fn schema(&mut self, namespace: Option<&str>, depth: usize) -> Value {
+            let kinds = if depth == 0 { 8 } else { 12 };
+            match self.pick(kinds) {
+                0 => self.primitive(),
+                1 => {
+                    let mut schema = json!({ "type": self.primitive() });
+                    self.props(&mut schema);
+                    schema
+                },
+                2 => self.logical(),
+                3 => {
+                    let precision = 1 + self.pick(30);
+                    let scale = self.pick(precision + 1);
+                    json!({
+                        "type": "bytes",
+                        "logicalType": "decimal",
+                        "precision": precision,
+                        "scale": scale,
+                    })
+                },
+                4 => self.enumeration(namespace),
+                5 => self.fixed(namespace),
+                6 => {
+                    let mut schema = self.fixed(namespace);
+                    schema["logicalType"] = if self.chance() {
+                        schema["size"] = json!(12);
+                        json!("duration")
+                    } else {
+                        // Up to 18 digits fit in 8 bytes.
+                        schema["size"] = json!(8);
+                        schema["precision"] = json!(1 + self.pick(18));
+                        json!("decimal")
+                    };
+                    schema
+                },
+                7 => match self.reference(namespace) {
+                    Some(reference) => reference,
+                    None => self.primitive(),
+                },
+                8 => {
+                    let mut schema = json!({
+                        "type": "array",
+                        "items": self.schema(namespace, depth - 1),
+                    });
+                    self.props(&mut schema);
+                    schema
+                },
+                9 => {
+                    let mut schema = json!({
+                        "type": "map",
+                        "values": self.schema(namespace, depth - 1),
+                    });
+                    self.props(&mut schema);
+                    schema
+                },
+                10 => self.union(namespace, depth - 1),
+                _ => self.record(namespace, depth - 1),
+            }
+        }
+
+        fn primitive(&mut self) -> Value {
+            let primitives = [
+                "null", "boolean", "int", "long", "float", "double", "bytes", "string",
+            ];
+            json!(primitives[self.pick(primitives.len())])
+        }
+
+        fn logical(&mut self) -> Value {
+            let logical_types = [
+                ("int", "date"),
+                ("int", "time-millis"),
+                ("long", "time-micros"),
+                ("long", "timestamp-millis"),
+                ("long", "timestamp-micros"),
+                ("long", "local-timestamp-millis"),
+                ("long", "local-timestamp-micros"),
+                ("string", "uuid"),
+            ];
+            let (type_, logical_type) = logical_types[self.pick(logical_types.len())];
+            json!({ "type": type_, "logicalType": logical_type })
+        }
+
+        /// Maybe add a custom attribute to `schema`.
+        fn props(&mut self, schema: &mut Value) {
+            if self.chance() {
+                schema["x"] = json!({ "y": self.pick(100) });
+            }
+        }
+
+        /// Start the definition of a named type, within the `namespace` of its enclosing named
+        /// type. Returns the definition and the namespace of the named type.
+        fn named(&mut self, type_: &str, namespace: Option<&str>) -> (Value, Option<String>) {
+            self.count += 1;
+            let name = format!("T{}", self.count);
+            let mut schema = json!({ "type": type_, "name": name });
+            let namespace = match self.pick(3) {
+                0 => namespace.map(str::to_owned),
+                1 => {
+                    schema["namespace"] = json!("");
+                    None
+                },
+                _ => {
+                    let namespace = format!("space{}.inner", self.pick(3));
+                    schema["namespace"] = json!(namespace);
+                    Some(namespace)
+                },
+            };
+            if self.chance() {
+                schema["aliases"] = json!([format!("A{}", self.count), "other.A"]);
+            }
+            self.props(&mut schema);
+            (schema, namespace)
+        }
+
+        fn define(&mut self, name: &Value, namespace: Option<&str>) {
+            let name = name.as_str().unwrap();
+            self.defined.push(match namespace {
+                Some(namespace) => format!("{}.{}", namespace, name),
+                None => name.to_owned(),
+            });
+        }
+
+        fn doc(&mut self, schema: &mut Value) {
+            if self.chance() {
+                schema["doc"] = json!(String::arbitrary(self.g));
+            }
+        }
+
+        fn enumeration(&mut self, namespace: Option<&str>) -> Value {
+            let (mut schema, namespace) = self.named("enum", namespace);
+            self.doc(&mut schema);
+            let symbols = 1 + self.pick(4);
+            schema["symbols"] = (0..symbols).map(|i| format!("S{}", i)).collect();
+            self.define(&schema["name"], namespace.as_deref());
+            schema
+        }
+
+        fn fixed(&mut self, namespace: Option<&str>) -> Value {
+            let (mut schema, namespace) = self.named("fixed", namespace);
+            schema["size"] = json!(self.pick(20));
+            self.define(&schema["name"], namespace.as_deref());
+            schema
+        }
+
+        /// Refer to a named type already defined, if any which can be referred to from within
+        /// `namespace`.
+        fn reference(&mut self, namespace: Option<&str>) -> Option<Value> {
+            // Names of the null namespace cannot be referred to from within another namespace.
+            let candidates = self
+                .defined
+                .iter()
+                .filter(|fullname| namespace.is_none() || fullname.contains('.'))
+                .cloned()
+                .collect::<Vec<_>>();
+            if candidates.is_empty() {
+                return None
+            }
+            let reference = json!(candidates[self.pick(candidates.len())]);
+            if self.chance() {
+                Some(json!({ "type": reference, "x": 1 }))
+            } else {
+                Some(reference)
+            }
+        }
+
+        fn union(&mut self, namespace: Option<&str>, depth: usize) -> Value {
+            let mut variants = Vec::new();
+            // The underlying types of the variants, which must be distinct.
+            let mut types = HashSet::new();
+            for _ in 0..1 + self.pick(4) {
+                // The named types defined by a variant which is left out cannot be referred to.
+                let defined = self.defined.len();
+                let variant = self.schema(namespace, depth);
+                let type_ = match variant {
+                    Value::String(ref type_) => type_,
+                    Value::Object(ref schema) => match schema["type"] {
+                        Value::String(ref type_) => type_,
+                        _ => unreachable!(),
+                    },
+                    // A union is not allowed within a union.
+                    _ => {
+                        self.defined.truncate(defined);
+                        continue
+                    },
+                };
+                // Named types are told apart by their name, which is unique.
+                let type_ = match variant.get("name") {
+                    Some(name) => name.as_str().unwrap().to_owned(),
+                    None => type_.rsplit('.').next().unwrap().to_owned(),
+                };
+                if types.insert(type_) {
+                    variants.push(variant);
+                } else {
+                    self.defined.truncate(defined);
+                }
+            }
+            if variants.is_empty() {
+                variants.push(json!("null"));
+            }
+            Value::Array(variants)
+        }
+
+        fn record(&mut self, namespace: Option<&str>, depth: usize) -> Value {
+            let (mut schema, namespace) = self.named("record", namespace);
+            self.doc(&mut schema);
+            let orders = ["ascending", "descending", "ignore"];
+            let fields = (0..self.pick(5))
+                .map(|i| {
+                    let mut field = json!({
+                        "name": format!("f{}", i),
+                        "type": self.schema(namespace.as_deref(), depth),
+                    });
+                    self.doc(&mut field);
+                    if self.chance() {
+                        field["aliases"] = json!([format!("g{}", i)]);
+                    }
+                    if self.chance() {
+                        field["order"] = json!(orders[self.pick(orders.len())]);
+                    }
+                    if self.chance() {
+                        field["pii"] = json!(true);
+                    }
+                    field
+                }).collect::<Vec<_>>();
+            schema["fields"] = json!(fields);
+            self.define(&schema["name"], namespace.as_deref());
+            schema
+        }
+
+        /// Generate a value of `schema`, made of other values down to `depth` levels.
+        fn value(&mut self, schema: &Schema, names: &Names, depth: usize) -> types::Value {
+            let small = |g: &mut G| i64::from(i32::arbitrary(g));
+            match *schema.resolve_ref(names).unwrap() {
+                Schema::Null => types::Value::Null,
+                Schema::Boolean => types::Value::Boolean(self.chance()),
+                Schema::Int => types::Value::Int(i32::arbitrary(self.g)),
+                Schema::Long => types::Value::Long(i64::arbitrary(self.g)),
+                // Integral floats, to never generate NaN which is not equal to itself.
+                Schema::Float => types::Value::Float(i16::arbitrary(self.g).into()),
+                Schema::Double => types::Value::Double(i32::arbitrary(self.g).into()),
+                Schema::Bytes => types::Value::Bytes(Vec::arbitrary(self.g)),
+                Schema::String => types::Value::String(String::arbitrary(self.g)),
+                Schema::Array(ref items) => types::Value::Array(
+                    (0..self.pick(depth + 1))
+                        .map(|_| self.value(items, names, depth.saturating_sub(1)))
+                        .collect(),
+                ),
+                Schema::Map(ref values) => types::Value::Map(
+                    (0..self.pick(depth + 1))
+                        .map(|i| {
+                            let value = self.value(values, names, depth.saturating_sub(1));
+                            (format!("k{}", i), value)
+                        }).collect(),
+                ),
+                Schema::Union(ref union) => {
+                    let index = self.pick(union.variants().len());
+                    let value = self.value(&union.variants()[index], names, depth);
+                    types::Value::Union(index as u32, Box::new(value))
+                },
+                Schema::Record { ref fields, .. } => types::Value::Record(
+                    fields
+                        .iter()
+                        .map(|field| (field.name.clone(), self.value(&field.schema, names, depth)))
+                        .collect(),
+                ),
+                Schema::Enum { ref symbols, .. } => {
+                    let index = self.pick(symbols.len());
+                    types::Value::Enum(index as i32, symbols[index].clone())
+                },
+                Schema::Fixed { size, .. } => {
+                    types::Value::Fixed(size, (0..size).map(|_| u8::arbitrary(self.g)).collect())
+                },
+                Schema::Decimal { precision, .. } => {
+                    let bound = 10i64.pow(precision.min(18) as u32);
+                    types::Value::Decimal(Decimal::from(i64::arbitrary(self.g) % bound))
+                },
+                Schema::Date => types::Value::Date(i32::arbitrary(self.g)),
+                Schema::TimeMillis => types::Value::TimeMillis(i32::arbitrary(self.g).abs()),
+                Schema::TimeMicros => types::Value::TimeMicros(small(self.g).abs()),
+                Schema::TimestampMillis => types::Value::TimestampMillis(small(self.g)),
+                Schema::TimestampMicros => types::Value::TimestampMicros(small(self.g)),
+                Schema::LocalTimestampMillis => types::Value::LocalTimestampMillis(small(self.g)),
+                Schema::LocalTimestampMicros => types::Value::LocalTimestampMicros(small(self.g)),
+                Schema::Uuid => {
+                    let mut uuid = [0; 16];
+                    for byte in uuid.iter_mut() {
+                        *byte = u8::arbitrary(self.g);
+                    }
+                    types::Value::Uuid(uuid)
+                },
+                Schema::Duration { .. } => types::Value::Duration {
+                    months: u32::arbitrary(self.g),
+                    days: u32::arbitrary(self.g),
+                    millis: u32::arbitrary(self.g),
+                },
+                Schema::Annotated { .. } | Schema::Ref { .. } => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_serialization_round_trip_property() {
+        fn round_trip(generated: ArbitrarySchema) -> bool {
+            let schema = Schema::parse(&generated.json).unwrap();
+            let serialized = serde_json::to_value(&schema).unwrap();
+            let parsed = Schema::parse(&serialized).unwrap();
+
+            parsed == schema
+                && serde_json::to_value(&parsed).unwrap() == serialized
+                && parsed.canonical_form() == schema.canonical_form()
+                && generated.values.into_iter().all(|value| {
+                    let encoded = encode_to_vec(&value, &schema).unwrap();
+                    value.validate(&parsed) && decode(&parsed, &mut &encoded[..]).unwrap() == value
+                })
+        }
+
+        quickcheck(round_trip as fn(ArbitrarySchema) -> bool);
+    }
+
+    #[test]
+    fn test_canonical_form_of_namespaced_types() {
+        let schema = Schema::parse_str(
+            r#"{"type": "enum", "namespace": "cards", "name": "Suit", "symbols": ["hearts"]}"#,
+        ).unwrap();
+        assert_eq!(
+            schema.canonical_form(),
+            r#"{"name":"cards.Suit","type":"enum","symbols":["hearts"]}"#
+        );
+
+        let schema = Schema::parse_str(
+            r#"{"type": "fixed", "namespace": "hash", "name": "md5", "size": 16}"#,
+        ).unwrap();
+        assert_eq!(
+            schema.canonical_form(),
+            r#"{"name":"hash.md5","type":"fixed","size":16}"#
+        );
+    }
+
+    #[test]
+    fn test_fingerprints() {
+        // Reference values from the Apache Avro test suite.
+        let fingerprints = vec![
+            (
+                r#""null""#,
+                "8a8f25cce724dd63",
+                "9b41ef67651c18488a8b08bb67c75699",
+                "f072cbec3bf8841871d4284230c5e983dc211a56837aed862487148f947d1a1f",
+            ),
+            (
+                r#"{"type": "int"}"#,
+                "8f5c393f1ad57572",
+                "ef524ea1b91e73173d938ade36c1db32",
+                "3f2b87a9fe7cc9b13835598c3981cd45e3e355309e5090aa0933d7becb6fba45",
+            ),
+        ];
+
+        for (raw_schema, rabin, md5, sha256) in fingerprints {
+            let schema = Schema::parse_str(raw_schema).unwrap();
+            assert_eq!(
+                schema.fingerprint(FingerprintAlgorithm::Rabin).to_string(),
+                rabin
+            );
+            assert_eq!(schema.fingerprint(FingerprintAlgorithm::Md5).to_string(), md5);
+            assert_eq!(
+                schema.fingerprint(FingerprintAlgorithm::Sha256).to_string(),
+                sha256
+            );
+        }
+
+        // Fingerprints only depend on the canonical form, and can be used as keys.
+        let mut cache = HashSet::new();
+        let schema = Schema::parse_str(r#"{"type": "fixed", "name": "md5", "size": 16}"#).unwrap();
+        let same = Schema::parse_str(r#"{"size": 16, "type": "fixed", "name": "md5", "x": 1}"#)
+            .unwrap();
+        assert!(cache.insert(schema.fingerprint(FingerprintAlgorithm::Rabin)));
+        assert!(!cache.insert(same.fingerprint(FingerprintAlgorithm::Rabin)));
+        assert!(cache.insert(schema.fingerprint(FingerprintAlgorithm::Md5)));
+    }
+
+    #[test]
+    fn test_strict_validation() {
+        let error = |input: &str| Schema::parse_str(input).unwrap_err().to_string();
+
+        assert_eq!(
+            error(r#"{"type": "fixed", "name": "9lives", "size": 1}"#),
+            "Failed to parse schema: Invalid name of fixed: \"9lives\""
+        );
+        assert_eq!(
+            error(r#"{"type": "fixed", "name": "a", "namespace": "b..c", "size": 1}"#),
+            "Failed to parse schema: Invalid namespace of fixed a: \"b..c\""
+        );
+        assert_eq!(
+            error(r#"{"type": "fixed", "name": "a", "aliases": ["b-c"], "size": 1}"#),
+            "Failed to parse schema: Invalid alias of fixed a: \"b-c\""
+        );
+        assert_eq!(
+            error(r#"{"type": "fixed", "name": "a", "size": -1}"#),
+            "Failed to parse schema: Invalid size of fixed a: -1 is not a non-negative integer"
+        );
+        assert_eq!(
+            error(r#"{"type": "fixed", "name": "a", "size": 1.5}"#),
+            "Failed to parse schema: Invalid size of fixed a: 1.5 is not a non-negative integer"
+        );
+        assert_eq!(
+            error(r#"{"type": "enum", "name": "e", "symbols": ["a", "b", "a"]}"#),
+            "Failed to parse schema: Duplicate symbol a in enum e"
+        );
+        assert_eq!(
+            error(r#"{"type": "enum", "name": "e", "namespace": "n", "symbols": ["a b"]}"#),
+            "Failed to parse schema: Invalid symbol \"a b\" in enum n.e"
+        );
+        assert_eq!(
+            error(
+                r#"{"type": "record", "name": "r", "fields": [
+                    {"name": "a", "type": "int"},
+                    {"name": "a", "type": "long"}
+                ]}"#
+            ),
+            "Failed to parse schema: Duplicate field a in record r"
+        );
+        assert_eq!(
+            error(r#"{"type": "record", "name": "r", "fields": [{"name": "", "type": "int"}]}"#),
+            "Failed to parse schema: Invalid name of field in record r: \"\""
+        );
+        assert_eq!(
+            error(
+                r#"{"type": "record", "name": "r", "fields": [
+                    {"name": "a", "type": "int", "aliases": ["b.c"]}
+                ]}"#
+            ),
+            "Failed to parse schema: Invalid alias of field a in record r: \"b.c\""
+        );
+        assert_eq!(
+            error(r#"{"type": "record", "name": "r", "fields": [1]}"#),
+            "Failed to parse schema: Invalid field in record r: 1 is not a JSON object"
+        );
+    }
+
+    #[test]
+    fn test_invalid_defaults() {
+        let error = |field: &str| {
+            let input = format!(r#"{{"type": "record", "name": "r", "fields": [{}]}}"#, field);
+            Schema::parse_str(&input).map_err(|e| e.to_string())
+        };
+
+        let valid = vec![
+            r#"{"name": "a", "type": "int", "default": -2147483648}"#,
+            r#"{"name": "a", "type": "long", "default": 4294967296}"#,
+            r#"{"name": "a", "type": "double", "default": 1}"#,
+            r#"{"name": "a", "type": "bytes", "default": "\u00ff"}"#,
+            r#"{"name": "a", "type": {"type": "fixed", "name": "f", "size": 2}, "default": "ab"}"#,
+            r#"{"name": "a", "type": {"type": "array", "items": "int"}, "default": [1, 2]}"#,
+            r#"{"name": "a", "type": {"type": "map", "values": "int"}, "default": {"b": 1}}"#,
+            r#"{"name": "a", "type": ["null", "int"], "default": null}"#,
+            r#"{
+                "name": "a",
+                "type": {"type": "enum", "name": "e", "symbols": ["b"]},
+                "default": "b"
+            }"#,
+            r#"{"name": "a", "type": {"type": "int", "logicalType": "date"}, "default": 1}"#,
+            r#"{
+                "name": "a",
+                "type": {
+                    "type": "record",
+                    "name": "inner",
+                    "fields": [
+                        {"name": "b", "type": "int"},
+                        {"name": "c", "type": "int", "default": 0},
+                        {"name": "d", "type": ["null", "inner"], "default": null}
+                    ]
+                },
+                "default": {"b": 1, "d": null}
+            }"#,
+        ];
+        for field in valid {
+            assert!(error(field).is_ok(), "{}", field);
+        }
+
+        assert_eq!(
+            error(r#"{"name": "a", "type": "int", "default": 2147483648}"#).unwrap_err(),
+            "Failed to parse schema: Invalid default value of field a in record r: 2147483648 is \
+             not a valid int"
+        );
+        assert_eq!(
+            error(r#"{"name": "a", "type": ["null", "int"], "default": 1}"#).unwrap_err(),
+            "Failed to parse schema: Invalid default value of field a in record r: 1 is not a \
+             valid null (the first type of the union)"
+        );
+        assert_eq!(
+            error(r#"{"name": "a", "type": "bytes", "default": "\u0100"}"#).unwrap_err(),
+            "Failed to parse schema: Invalid default value of field a in record r: \"Ā\" is not \
+             a valid bytes"
+        );
+        let invalid = vec![
+            r#"{"name": "a", "type": "string", "default": null}"#,
+            r#"{"name": "a", "type": "long", "default": 1.5}"#,
+            r#"{"name": "a", "type": {"type": "fixed", "name": "f", "size": 2}, "default": "a"}"#,
+            r#"{"name": "a", "type": {"type": "array", "items": "int"}, "default": ["b"]}"#,
+            r#"{
+                "name": "a",
+                "type": {"type": "enum", "name": "e", "symbols": ["b"]},
+                "default": "c"
+            }"#,
+            r#"{
+                "name": "a",
+                "type": {
+                    "type": "record",
+                    "name": "inner",
+                    "fields": [{"name": "b", "type": "int"}]
+                },
+                "default": {}
+            }"#,
+        ];
+        for field in invalid {
+            assert!(error(field).is_err(), "{}", field);
+        }
+    }
+
+    #[test]
+    fn test_typed_defaults() {
+        let schema = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "r",
+                "fields": [
+                    {"name": "a", "type": "bytes", "default": "\u00ff\u0001"},
+                    {"name": "b", "type": ["int", "null"], "default": 1},
+                    {"name": "c", "type": {"type": "enum", "name": "e", "symbols": ["x", "y"]},
+                     "default": "y"},
+                    {"name": "d", "type": {"type": "array", "items": "e"}, "default": ["x"]},
+                    {"name": "e", "type": {"type": "string", "logicalType": "uuid"},
+                     "default": "67e55044-10b1-426f-9247-bb680e5fe0c8"},
+                    {"name": "f", "type": {"type": "bytes", "logicalType": "decimal",
+                     "precision": 4, "scale": 2}, "default": "\u00ff"},
+                    {"name": "g", "type": "string"}
+                ]
+            }
+            "#,
+        ).unwrap();
+
+        let defaults = match schema {
+            Schema::Record { ref fields, .. } => fields
+                .iter()
+                .map(|field| field.default_value.clone())
+                .collect::<Vec<_>>(),
+            _ => panic!("Expected a record, got {:?}", schema),
+        };
+        assert_eq!(
+            defaults,
+            vec![
+                Some(types::Value::Bytes(vec![0xff, 0x01])),
+                Some(types::Value::Union(0, Box::new(types::Value::Int(1)))),
+                Some(types::Value::Enum(1, "y".to_owned())),
+                Some(types::Value::Array(vec![types::Value::Enum(
+                    0,
+                    "x".to_owned()
+                )])),
+                Some(types::Value::Uuid(
+                    parse_uuid("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap()
+                )),
+                Some(types::Value::Decimal(Decimal::from(-1i64))),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lenient_parsing() {
+        let input = r#"
+            {
+                "type": "record",
+                "name": "legacy-record",
+                "fields": [
+                    {"name": "a", "type": ["null", "int"], "default": 1},
+                    "ignored",
+                    {"name": "b", "type": {"type": "enum", "name": "e", "symbols": ["x", "x"]}}
+                ]
+            }
+        "#;
+        assert!(Schema::parse_str(input).is_err());
+
+        let schema = Schema::parse_str_lenient(input).unwrap();
+        if let Schema::Record { ref fields, .. } = schema {
+            assert_eq!(fields.len(), 2);
+            assert_eq!(fields[1].name, "b");
+            assert_eq!(fields[1].position, 1);
+            // The default value of a union may match any of its types.
+            assert_eq!(
+                fields[0].default_value,
+                Some(types::Value::Union(1, Box::new(types::Value::Int(1))))
+            );
+        } else {
+            panic!("Expected a record, got {:?}", schema);
+        }
+
+        // Invalid sizes are never accepted.
+        let fixed = r#"{"type": "fixed", "name": "f", "size": -1}"#;
+        assert!(Schema::parse_str_lenient(fixed).is_err());
+    }
+
     // Tests to ensure Schema is Send + Sync. These tests don't need to _do_ anything, if they can
     // compile, they pass.
     #[test]
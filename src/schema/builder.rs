@@ -0,0 +1,754 @@
+//! Logic for building schemas in code rather than parsing them from JSON.
+//!
+//! ```
+//! use avro_rs::schema::builder::{FieldBuilder, SchemaBuilder};
+//! use avro_rs::Schema;
+//!
+//! let schema = SchemaBuilder::record("person")
+//!     .namespace("com.example")
+//!     .field(FieldBuilder::new("name", SchemaBuilder::string()))
+//!     .field(FieldBuilder::new("age", Schema::Int).default(0))
+//!     .build()
+//!     .unwrap();
+//!
+//! let parsed = Schema::parse_str(
+//!     r#"
+//!     {
+//!         "type": "record",
+//!         "name": "person",
+//!         "namespace": "com.example",
+//!         "fields": [
+//!             {"name": "name", "type": "string"},
+//!             {"name": "age", "type": "int", "default": 0}
+//!         ]
+//!     }
+//!     "#,
+//! ).unwrap();
+//!
+//! assert_eq!(schema, parsed);
+//! ```
+use failure::Error;
+use serde_json::{self, Map, Value};
+
+use schema::{Props, RecordFieldOrder, Schema};
+
+/// Describes errors happened while building Avro schemas.
+#[derive(Fail, Debug)]
+#[fail(display = "Failed to build schema: {}", _0)]
+pub struct BuildSchemaError(String);
+
+impl BuildSchemaError {
+    pub fn new<S>(msg: S) -> BuildSchemaError
+    where
+        S: Into<String>,
+    {
+        BuildSchemaError(msg.into())
+    }
+}
+
+/// Builder of any Avro schema.
+///
+/// Builders are validated when calling `build`, which returns the same `Schema` as parsing the
+/// equivalent JSON schema would, and fails for the same reasons.
+#[derive(Clone, Debug)]
+pub struct SchemaBuilder {
+    kind: Kind,
+    props: Props,
+}
+
+#[derive(Clone, Debug)]
+enum Kind {
+    Schema(Schema),
+    Reference(String),
+    Array(Box<SchemaBuilder>),
+    Map(Box<SchemaBuilder>),
+    Union(Vec<SchemaBuilder>),
+    Record(RecordBuilder),
+    Enum(EnumBuilder),
+    Fixed(FixedBuilder),
+}
+
+impl SchemaBuilder {
+    fn new(kind: Kind) -> SchemaBuilder {
+        SchemaBuilder {
+            kind,
+            props: Props::new(),
+        }
+    }
+
+    /// A `null` schema.
+    pub fn null() -> SchemaBuilder {
+        SchemaBuilder::from(Schema::Null)
+    }
+
+    /// A `boolean` schema.
+    pub fn boolean() -> SchemaBuilder {
+        SchemaBuilder::from(Schema::Boolean)
+    }
+
+    /// An `int` schema.
+    pub fn int() -> SchemaBuilder {
+        SchemaBuilder::from(Schema::Int)
+    }
+
+    /// A `long` schema.
+    pub fn long() -> SchemaBuilder {
+        SchemaBuilder::from(Schema::Long)
+    }
+
+    /// A `float` schema.
+    pub fn float() -> SchemaBuilder {
+        SchemaBuilder::from(Schema::Float)
+    }
+
+    /// A `double` schema.
+    pub fn double() -> SchemaBuilder {
+        SchemaBuilder::from(Schema::Double)
+    }
+
+    /// A `bytes` schema.
+    pub fn bytes() -> SchemaBuilder {
+        SchemaBuilder::from(Schema::Bytes)
+    }
+
+    /// A `string` schema.
+    pub fn string() -> SchemaBuilder {
+        SchemaBuilder::from(Schema::String)
+    }
+
+    /// An `array` schema of `items`.
+    pub fn array<S: Into<SchemaBuilder>>(items: S) -> SchemaBuilder {
+        SchemaBuilder::new(Kind::Array(Box::new(items.into())))
+    }
+
+    /// A `map` schema of `values`.
+    pub fn map<S: Into<SchemaBuilder>>(values: S) -> SchemaBuilder {
+        SchemaBuilder::new(Kind::Map(Box::new(values.into())))
+    }
+
+    /// A `union` schema of `variants`.
+    pub fn union(variants: Vec<SchemaBuilder>) -> SchemaBuilder {
+        SchemaBuilder::new(Kind::Union(variants))
+    }
+
+    /// A reference to the named type `name`, defined earlier in the schema being built.
+    ///
+    /// As in JSON schemas, an unqualified `name` is looked up in the enclosing namespace first.
+    pub fn reference(name: &str) -> SchemaBuilder {
+        SchemaBuilder::new(Kind::Reference(name.to_owned()))
+    }
+
+    /// A `record` schema named `name`.
+    pub fn record(name: &str) -> RecordBuilder {
+        RecordBuilder {
+            name: name.to_owned(),
+            namespace: None,
+            aliases: Vec::new(),
+            doc: None,
+            fields: Vec::new(),
+            props: Props::new(),
+        }
+    }
+
+    /// An `enum` schema named `name`.
+    pub fn enumeration(name: &str) -> EnumBuilder {
+        EnumBuilder {
+            name: name.to_owned(),
+            namespace: None,
+            aliases: Vec::new(),
+            doc: None,
+            symbols: Vec::new(),
+            props: Props::new(),
+        }
+    }
+
+    /// A `fixed` schema named `name`, of `size` bytes.
+    pub fn fixed(name: &str, size: usize) -> FixedBuilder {
+        FixedBuilder {
+            name: name.to_owned(),
+            namespace: None,
+            aliases: Vec::new(),
+            size,
+            props: Props::new(),
+        }
+    }
+
+    /// Add the custom attribute `key` to the schema. Logical types are attributes too:
+    ///
+    /// ```
+    /// use avro_rs::schema::builder::SchemaBuilder;
+    /// use avro_rs::Schema;
+    ///
+    /// let date = SchemaBuilder::int().prop("logicalType", "date").build().unwrap();
+    /// assert_eq!(date, Schema::Date);
+    /// ```
+    pub fn prop<V: Into<Value>>(mut self, key: &str, value: V) -> SchemaBuilder {
+        self.props.insert(key.to_owned(), value.into());
+        self
+    }
+
+    /// Validate the schema and build it.
+    pub fn build(&self) -> Result<Schema, Error> {
+        Schema::parse(&self.to_json()?)
+    }
+
+    /// Validate the schema and convert it to its JSON representation.
+    fn to_json(&self) -> Result<Value, Error> {
+        let json = match self.kind {
+            Kind::Schema(ref schema) => serde_json::to_value(schema)?,
+            Kind::Reference(ref name) => Value::String(name.clone()),
+            Kind::Array(ref items) => {
+                let mut map = Map::new();
+                map.insert("type".to_owned(), "array".into());
+                map.insert("items".to_owned(), items.to_json()?);
+                Value::Object(map)
+            },
+            Kind::Map(ref values) => {
+                let mut map = Map::new();
+                map.insert("type".to_owned(), "map".into());
+                map.insert("values".to_owned(), values.to_json()?);
+                Value::Object(map)
+            },
+            Kind::Union(ref variants) => Value::Array(
+                variants
+                    .iter()
+                    .map(|variant| variant.to_json())
+                    .collect::<Result<_, _>>()?,
+            ),
+            Kind::Record(ref record) => record.to_json()?,
+            Kind::Enum(ref enumeration) => enumeration.to_json()?,
+            Kind::Fixed(ref fixed) => fixed.to_json()?,
+        };
+
+        if self.props.is_empty() {
+            return Ok(json)
+        }
+
+        let mut map = match json {
+            Value::Object(map) => map,
+            Value::String(name) => {
+                let mut map = Map::new();
+                map.insert("type".to_owned(), Value::String(name));
+                map
+            },
+            _ => Err(BuildSchemaError::new("Unions cannot have attributes"))?,
+        };
+        insert_props(&mut map, &self.props)?;
+        Ok(Value::Object(map))
+    }
+}
+
+impl From<Schema> for SchemaBuilder {
+    fn from(schema: Schema) -> SchemaBuilder {
+        SchemaBuilder::new(Kind::Schema(schema))
+    }
+}
+
+impl From<RecordBuilder> for SchemaBuilder {
+    fn from(record: RecordBuilder) -> SchemaBuilder {
+        SchemaBuilder::new(Kind::Record(record))
+    }
+}
+
+impl From<EnumBuilder> for SchemaBuilder {
+    fn from(enumeration: EnumBuilder) -> SchemaBuilder {
+        SchemaBuilder::new(Kind::Enum(enumeration))
+    }
+}
+
+impl From<FixedBuilder> for SchemaBuilder {
+    fn from(fixed: FixedBuilder) -> SchemaBuilder {
+        SchemaBuilder::new(Kind::Fixed(fixed))
+    }
+}
+
+/// Builder of a `record` schema.
+#[derive(Clone, Debug)]
+pub struct RecordBuilder {
+    name: String,
+    namespace: Option<String>,
+    aliases: Vec<String>,
+    doc: Option<String>,
+    fields: Vec<FieldBuilder>,
+    props: Props,
+}
+
+impl RecordBuilder {
+    /// Set the namespace of the record. Types defined within the record inherit it.
+    pub fn namespace(mut self, namespace: &str) -> RecordBuilder {
+        self.namespace = Some(namespace.to_owned());
+        self
+    }
+
+    /// Add an alternate name of the record.
+    pub fn alias(mut self, alias: &str) -> RecordBuilder {
+        self.aliases.push(alias.to_owned());
+        self
+    }
+
+    /// Set the documentation of the record.
+    pub fn doc(mut self, doc: &str) -> RecordBuilder {
+        self.doc = Some(doc.to_owned());
+        self
+    }
+
+    /// Add a field to the record, after the fields added so far.
+    pub fn field(mut self, field: FieldBuilder) -> RecordBuilder {
+        self.fields.push(field);
+        self
+    }
+
+    /// Add the custom attribute `key` to the record.
+    pub fn prop<V: Into<Value>>(mut self, key: &str, value: V) -> RecordBuilder {
+        self.props.insert(key.to_owned(), value.into());
+        self
+    }
+
+    /// Validate the record and build it.
+    pub fn build(self) -> Result<Schema, Error> {
+        SchemaBuilder::from(self).build()
+    }
+
+    fn to_json(&self) -> Result<Value, Error> {
+        let mut map = named_type("record", &self.name, &self.namespace, &self.aliases);
+        if let Some(ref doc) = self.doc {
+            map.insert("doc".to_owned(), doc.as_str().into());
+        }
+
+        let fields = self
+            .fields
+            .iter()
+            .map(|field| field.to_json())
+            .collect::<Result<_, _>>()?;
+        map.insert("fields".to_owned(), Value::Array(fields));
+
+        insert_props(&mut map, &self.props)?;
+        Ok(Value::Object(map))
+    }
+}
+
+/// Builder of a field of a `record` schema.
+#[derive(Clone, Debug)]
+pub struct FieldBuilder {
+    name: String,
+    schema: SchemaBuilder,
+    aliases: Vec<String>,
+    doc: Option<String>,
+    default: Option<Value>,
+    order: Option<RecordFieldOrder>,
+    props: Props,
+}
+
+impl FieldBuilder {
+    /// A field named `name`, of type `schema`.
+    pub fn new<S: Into<SchemaBuilder>>(name: &str, schema: S) -> FieldBuilder {
+        FieldBuilder {
+            name: name.to_owned(),
+            schema: schema.into(),
+            aliases: Vec::new(),
+            doc: None,
+            default: None,
+            order: None,
+            props: Props::new(),
+        }
+    }
+
+    /// Add an alternate name of the field.
+    pub fn alias(mut self, alias: &str) -> FieldBuilder {
+        self.aliases.push(alias.to_owned());
+        self
+    }
+
+    /// Set the documentation of the field.
+    pub fn doc(mut self, doc: &str) -> FieldBuilder {
+        self.doc = Some(doc.to_owned());
+        self
+    }
+
+    /// Set the default value of the field, in its JSON representation.
+    pub fn default<V: Into<Value>>(mut self, default: V) -> FieldBuilder {
+        self.default = Some(default.into());
+        self
+    }
+
+    /// Set the sort order of the field.
+    pub fn order(mut self, order: RecordFieldOrder) -> FieldBuilder {
+        self.order = Some(order);
+        self
+    }
+
+    /// Add the custom attribute `key` to the field.
+    pub fn prop<V: Into<Value>>(mut self, key: &str, value: V) -> FieldBuilder {
+        self.props.insert(key.to_owned(), value.into());
+        self
+    }
+
+    fn to_json(&self) -> Result<Value, Error> {
+        let mut map = Map::new();
+        map.insert("name".to_owned(), self.name.as_str().into());
+        map.insert("type".to_owned(), self.schema.to_json()?);
+        if !self.aliases.is_empty() {
+            map.insert("aliases".to_owned(), self.aliases.clone().into());
+        }
+        if let Some(ref doc) = self.doc {
+            map.insert("doc".to_owned(), doc.as_str().into());
+        }
+        if let Some(ref default) = self.default {
+            map.insert("default".to_owned(), default.clone());
+        }
+        if let Some(ref order) = self.order {
+            let order = match *order {
+                RecordFieldOrder::Ascending => "ascending",
+                RecordFieldOrder::Descending => "descending",
+                RecordFieldOrder::Ignore => "ignore",
+            };
+            map.insert("order".to_owned(), order.into());
+        }
+
+        insert_props(&mut map, &self.props)?;
+        Ok(Value::Object(map))
+    }
+}
+
+/// Builder of an `enum` schema.
+#[derive(Clone, Debug)]
+pub struct EnumBuilder {
+    name: String,
+    namespace: Option<String>,
+    aliases: Vec<String>,
+    doc: Option<String>,
+    symbols: Vec<String>,
+    props: Props,
+}
+
+impl EnumBuilder {
+    /// Set the namespace of the enum.
+    pub fn namespace(mut self, namespace: &str) -> EnumBuilder {
+        self.namespace = Some(namespace.to_owned());
+        self
+    }
+
+    /// Add an alternate name of the enum.
+    pub fn alias(mut self, alias: &str) -> EnumBuilder {
+        self.aliases.push(alias.to_owned());
+        self
+    }
+
+    /// Set the documentation of the enum.
+    pub fn doc(mut self, doc: &str) -> EnumBuilder {
+        self.doc = Some(doc.to_owned());
+        self
+    }
+
+    /// Add a symbol to the enum, after the symbols added so far.
+    pub fn symbol(mut self, symbol: &str) -> EnumBuilder {
+        self.symbols.push(symbol.to_owned());
+        self
+    }
+
+    /// Add the custom attribute `key` to the enum.
+    pub fn prop<V: Into<Value>>(mut self, key: &str, value: V) -> EnumBuilder {
+        self.props.insert(key.to_owned(), value.into());
+        self
+    }
+
+    /// Validate the enum and build it.
+    pub fn build(self) -> Result<Schema, Error> {
+        SchemaBuilder::from(self).build()
+    }
+
+    fn to_json(&self) -> Result<Value, Error> {
+        let mut map = named_type("enum", &self.name, &self.namespace, &self.aliases);
+        if let Some(ref doc) = self.doc {
+            map.insert("doc".to_owned(), doc.as_str().into());
+        }
+        map.insert("symbols".to_owned(), self.symbols.clone().into());
+
+        insert_props(&mut map, &self.props)?;
+        Ok(Value::Object(map))
+    }
+}
+
+/// Builder of a `fixed` schema.
+#[derive(Clone, Debug)]
+pub struct FixedBuilder {
+    name: String,
+    namespace: Option<String>,
+    aliases: Vec<String>,
+    size: usize,
+    props: Props,
+}
+
+impl FixedBuilder {
+    /// Set the namespace of the fixed.
+    pub fn namespace(mut self, namespace: &str) -> FixedBuilder {
+        self.namespace = Some(namespace.to_owned());
+        self
+    }
+
+    /// Add an alternate name of the fixed.
+    pub fn alias(mut self, alias: &str) -> FixedBuilder {
+        self.aliases.push(alias.to_owned());
+        self
+    }
+
+    /// Add the custom attribute `key` to the fixed. Logical types are attributes too.
+    pub fn prop<V: Into<Value>>(mut self, key: &str, value: V) -> FixedBuilder {
+        self.props.insert(key.to_owned(), value.into());
+        self
+    }
+
+    /// Validate the fixed and build it.
+    pub fn build(self) -> Result<Schema, Error> {
+        SchemaBuilder::from(self).build()
+    }
+
+    fn to_json(&self) -> Result<Value, Error> {
+        let mut map = named_type("fixed", &self.name, &self.namespace, &self.aliases);
+        map.insert("size".to_owned(), self.size.into());
+
+        insert_props(&mut map, &self.props)?;
+        Ok(Value::Object(map))
+    }
+}
+
+/// The JSON attributes shared by all named types.
+fn named_type(
+    type_: &str,
+    name: &str,
+    namespace: &Option<String>,
+    aliases: &[String],
+) -> Map<String, Value> {
+    let mut map = Map::new();
+    map.insert("type".to_owned(), type_.into());
+    map.insert("name".to_owned(), name.into());
+    if let Some(ref namespace) = *namespace {
+        map.insert("namespace".to_owned(), namespace.as_str().into());
+    }
+    if !aliases.is_empty() {
+        map.insert("aliases".to_owned(), aliases.to_vec().into());
+    }
+    map
+}
+
+/// Add the custom attributes `props` to `map`, which must not define them already.
+fn insert_props(map: &mut Map<String, Value>, props: &Props) -> Result<(), Error> {
+    for (key, value) in props {
+        if map.contains_key(key) {
+            Err(BuildSchemaError::new(format!(
+                "Attribute {} is already defined",
+                key
+            )))?;
+        }
+        map.insert(key.clone(), value.clone());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types;
+
+    #[test]
+    fn test_build_as_parsed() {
+        let built = SchemaBuilder::record("person")
+            .namespace("com.example")
+            .alias("human")
+            .doc("A person")
+            .prop("version", 2)
+            .field(
+                FieldBuilder::new("name", SchemaBuilder::string())
+                    .doc("Full name")
+                    .alias("fullname")
+                    .order(RecordFieldOrder::Descending),
+            ).field(
+                FieldBuilder::new(
+                    "birthday",
+                    SchemaBuilder::int().prop("logicalType", "date"),
+                ).prop("deprecated", true),
+            ).field(
+                FieldBuilder::new(
+                    "suit",
+                    SchemaBuilder::enumeration("suit")
+                        .symbol("hearts")
+                        .symbol("spades"),
+                ).default("hearts"),
+            ).field(FieldBuilder::new(
+                "hash",
+                SchemaBuilder::fixed("md5", 16).namespace("org.hashes"),
+            )).field(FieldBuilder::new(
+                "hashes",
+                SchemaBuilder::map(SchemaBuilder::reference("org.hashes.md5")),
+            )).field(
+                FieldBuilder::new(
+                    "friends",
+                    SchemaBuilder::array(SchemaBuilder::reference("person")),
+                ).default(Vec::<Value>::new()),
+            ).field(
+                FieldBuilder::new(
+                    "partner",
+                    SchemaBuilder::union(vec![
+                        SchemaBuilder::null(),
+                        SchemaBuilder::reference("person"),
+                    ]),
+                ).default(Value::Null),
+            ).build()
+            .unwrap();
+
+        let parsed = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "person",
+                "namespace": "com.example",
+                "aliases": ["human"],
+                "doc": "A person",
+                "version": 2,
+                "fields": [
+                    {
+                        "name": "name",
+                        "type": "string",
+                        "doc": "Full name",
+                        "aliases": ["fullname"],
+                        "order": "descending"
+                    },
+                    {
+                        "name": "birthday",
+                        "type": {"type": "int", "logicalType": "date"},
+                        "deprecated": true
+                    },
+                    {
+                        "name": "suit",
+                        "type": {"type": "enum", "name": "suit", "symbols": ["hearts", "spades"]},
+                        "default": "hearts"
+                    },
+                    {
+                        "name": "hash",
+                        "type": {
+                            "type": "fixed",
+                            "name": "md5",
+                            "namespace": "org.hashes",
+                            "size": 16
+                        }
+                    },
+                    {"name": "hashes", "type": {"type": "map", "values": "org.hashes.md5"}},
+                    {
+                        "name": "friends",
+                        "type": {"type": "array", "items": "person"},
+                        "default": []
+                    },
+                    {"name": "partner", "type": ["null", "person"], "default": null}
+                ]
+            }
+            "#,
+        ).unwrap();
+
+        assert_eq!(built, parsed);
+        assert_eq!(built.canonical_form(), parsed.canonical_form());
+    }
+
+    #[test]
+    fn test_build_from_schemas() {
+        let point = SchemaBuilder::record("point")
+            .field(FieldBuilder::new("x", Schema::Double))
+            .field(FieldBuilder::new("y", Schema::Double))
+            .build()
+            .unwrap();
+
+        let built = SchemaBuilder::array(point.clone()).build().unwrap();
+        assert_eq!(built, Schema::Array(Box::new(point)));
+
+        let uuid = SchemaBuilder::string()
+            .prop("logicalType", "uuid")
+            .build()
+            .unwrap();
+        assert_eq!(uuid, Schema::Uuid);
+    }
+
+    #[test]
+    fn test_build_with_defaults() {
+        let built = SchemaBuilder::record("point")
+            .field(FieldBuilder::new("x", Schema::Double))
+            .field(FieldBuilder::new("y", Schema::Double).default(0.5))
+            .field(FieldBuilder::new("label", SchemaBuilder::string()).default("origin"))
+            .build()
+            .unwrap();
+
+        let value = types::Value::Record(vec![("x".to_string(), types::Value::Double(1.0))]);
+        assert_eq!(
+            value.resolve(&built).unwrap(),
+            types::Value::Record(vec![
+                ("x".to_string(), types::Value::Double(1.0)),
+                ("y".to_string(), types::Value::Double(0.5)),
+                ("label".to_string(), types::Value::String("origin".to_string())),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_build_errors() {
+        assert!(SchemaBuilder::record("9lives").build().is_err());
+        assert!(SchemaBuilder::record("a..b").build().is_err());
+        assert!(
+            SchemaBuilder::fixed("md5", 16)
+                .namespace("org-hashes")
+                .build()
+                .is_err()
+        );
+        assert!(
+            SchemaBuilder::record("r")
+                .field(FieldBuilder::new("a", Schema::Int))
+                .field(FieldBuilder::new("a", Schema::Long))
+                .build()
+                .is_err()
+        );
+        assert!(
+            SchemaBuilder::record("r")
+                .field(FieldBuilder::new("a-b", Schema::Int))
+                .build()
+                .is_err()
+        );
+        assert!(
+            SchemaBuilder::enumeration("suit")
+                .symbol("hearts")
+                .symbol("hearts")
+                .build()
+                .is_err()
+        );
+        assert!(
+            SchemaBuilder::enumeration("suit")
+                .symbol("one heart")
+                .build()
+                .is_err()
+        );
+        assert!(
+            SchemaBuilder::union(vec![SchemaBuilder::int(), SchemaBuilder::int()])
+                .build()
+                .is_err()
+        );
+        assert!(
+            SchemaBuilder::union(vec![SchemaBuilder::int()])
+                .prop("a", 1)
+                .build()
+                .is_err()
+        );
+        assert!(SchemaBuilder::reference("unknown").build().is_err());
+        assert!(
+            SchemaBuilder::record("r")
+                .prop("fields", 1)
+                .build()
+                .is_err()
+        );
+
+        // named types can only be defined once
+        let md5 = SchemaBuilder::fixed("md5", 16);
+        assert!(
+            SchemaBuilder::record("r")
+                .field(FieldBuilder::new("a", md5.clone()))
+                .field(FieldBuilder::new("b", md5))
+                .build()
+                .is_err()
+        );
+    }
+}
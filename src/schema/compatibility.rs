@@ -0,0 +1,836 @@
+//! Logic for checking whether data written with a schema can be read with another one.
+//!
+//! The rules are the ones of
+//! [schema resolution](https://avro.apache.org/docs/current/spec.html#Schema+Resolution) in the
+//! Avro specification: a reader schema is compatible with a writer schema if every datum written
+//! with the writer schema can be resolved against the reader schema.
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
+
+use schema::{describe, Name, Names, Props, RecordField, Schema, SchemaKind};
+
+/// Why part of a reader schema cannot read what was written with a writer schema.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IncompatibilityReason {
+    /// The writer type cannot be promoted to the reader type.
+    TypeMismatch { reader: String, writer: String },
+    /// The reader named type has neither the name nor an alias of the writer named type.
+    NameMismatch { reader: String, writer: String },
+    /// The reader field is not written and has no default value.
+    MissingDefault { field: String },
+    /// The reader enum does not have all the symbols of the writer enum.
+    MissingEnumSymbols { symbols: Vec<String> },
+    /// The reader and writer fixed types do not have the same size.
+    FixedSizeMismatch { reader: usize, writer: usize },
+    /// The reader decimal has a different scale, or a smaller precision, than the writer decimal.
+    DecimalMismatch {
+        reader: (usize, usize),
+        writer: (usize, usize),
+    },
+    /// None of the branches of the reader union can read the writer type.
+    MissingUnionBranch { writer: String },
+}
+
+impl fmt::Display for IncompatibilityReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IncompatibilityReason::TypeMismatch {
+                ref reader,
+                ref writer,
+            } => write!(f, "{} cannot be read as {}", writer, reader),
+            IncompatibilityReason::NameMismatch {
+                ref reader,
+                ref writer,
+            } => write!(f, "{} cannot be read as {}: names differ", writer, reader),
+            IncompatibilityReason::MissingDefault { ref field } => {
+                write!(f, "field {} is not written and has no default value", field)
+            },
+            IncompatibilityReason::MissingEnumSymbols { ref symbols } => {
+                write!(f, "missing enum symbols {}", symbols.join(", "))
+            },
+            IncompatibilityReason::FixedSizeMismatch { reader, writer } => {
+                write!(f, "fixed size {} cannot be read as fixed size {}", writer, reader)
+            },
+            IncompatibilityReason::DecimalMismatch { reader, writer } => write!(
+                f,
+                "decimal({}, {}) cannot be read as decimal({}, {})",
+                writer.0, writer.1, reader.0, reader.1
+            ),
+            IncompatibilityReason::MissingUnionBranch { ref writer } => {
+                write!(f, "no branch of the union can read {}", writer)
+            },
+        }
+    }
+}
+
+/// An incompatibility found within a reader schema.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Incompatibility {
+    /// Where the incompatibility lies within the reader schema: `.` for the schema itself,
+    /// `.address.zip` for the field `zip` of the field `address`, `[]` and `{}` for the items of
+    /// an array and the values of a map.
+    pub path: String,
+    /// Why the reader schema cannot read the writer schema there.
+    pub reason: IncompatibilityReason,
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.reason)
+    }
+}
+
+/// The result of checking a reader schema against a writer schema.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompatibilityReport {
+    incompatibilities: Vec<Incompatibility>,
+}
+
+impl CompatibilityReport {
+    /// Returns true if the reader schema can read everything written with the writer schema.
+    pub fn is_compatible(&self) -> bool {
+        self.incompatibilities.is_empty()
+    }
+
+    /// Returns every incompatibility found.
+    pub fn incompatibilities(&self) -> &[Incompatibility] {
+        &self.incompatibilities
+    }
+}
+
+impl fmt::Display for CompatibilityReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_compatible() {
+            return write!(f, "compatible")
+        }
+
+        for (i, incompatibility) in self.incompatibilities.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", incompatibility)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check whether data written with `writer` can be read with `reader`.
+pub fn check(reader: &Schema, writer: &Schema) -> CompatibilityReport {
+    let mut checker = Checker {
+        reader_names: reader.names(),
+        writer_names: writer.names(),
+        checked: HashSet::new(),
+        incompatibilities: Vec::new(),
+    };
+    checker.check(reader, writer, "");
+
+    CompatibilityReport {
+        incompatibilities: checker.incompatibilities,
+    }
+}
+
+/// How a new schema must be compatible with the existing schemas of its history.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CompatibilityMode {
+    /// The new schema can read data written with the existing schemas.
+    Backward,
+    /// The existing schemas can read data written with the new schema.
+    Forward,
+    /// Both `Backward` and `Forward`.
+    Full,
+}
+
+/// The incompatibilities between a new schema and one existing schema of its history.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VersionIncompatibility {
+    /// Position of the existing schema in the history.
+    pub version: usize,
+    /// `Backward` if the new schema cannot read data written with the existing schema, `Forward`
+    /// if the existing schema cannot read data written with the new schema.
+    pub direction: CompatibilityMode,
+    /// The incompatibilities found in that direction.
+    pub report: CompatibilityReport,
+}
+
+impl CompatibilityMode {
+    /// Check `schema` against the latest schema of `history`, which is ordered from the oldest to
+    /// the latest schema.
+    ///
+    /// Returns the incompatibilities found, if any.
+    pub fn check(self, schema: &Schema, history: &[Schema]) -> Vec<VersionIncompatibility> {
+        match history.len() {
+            0 => Vec::new(),
+            len => self.check_versions(schema, history, len - 1),
+        }
+    }
+
+    /// Check `schema` against every schema of `history`.
+    ///
+    /// Returns the incompatibilities found, if any.
+    pub fn check_transitive(
+        self,
+        schema: &Schema,
+        history: &[Schema],
+    ) -> Vec<VersionIncompatibility> {
+        self.check_versions(schema, history, 0)
+    }
+
+    fn check_versions(
+        self,
+        schema: &Schema,
+        history: &[Schema],
+        from: usize,
+    ) -> Vec<VersionIncompatibility> {
+        let mut incompatibilities = Vec::new();
+        for (version, existing) in history.iter().enumerate().skip(from) {
+            if self != CompatibilityMode::Forward {
+                let report = check(schema, existing);
+                if !report.is_compatible() {
+                    incompatibilities.push(VersionIncompatibility {
+                        version,
+                        direction: CompatibilityMode::Backward,
+                        report,
+                    });
+                }
+            }
+            if self != CompatibilityMode::Backward {
+                let report = check(existing, schema);
+                if !report.is_compatible() {
+                    incompatibilities.push(VersionIncompatibility {
+                        version,
+                        direction: CompatibilityMode::Forward,
+                        report,
+                    });
+                }
+            }
+        }
+        incompatibilities
+    }
+}
+
+struct Checker<'a> {
+    reader_names: Names<'a>,
+    writer_names: Names<'a>,
+    // Pairs of named types (reader, writer) already checked, so recursive types terminate.
+    checked: HashSet<(String, String)>,
+    incompatibilities: Vec<Incompatibility>,
+}
+
+impl<'a> Checker<'a> {
+    fn check(&mut self, reader: &'a Schema, writer: &'a Schema, path: &str) {
+        // Parsed schemas never contain references to undefined names.
+        let (reader, writer) = match (
+            reader.resolve_ref(&self.reader_names),
+            writer.resolve_ref(&self.writer_names),
+        ) {
+            (Some(reader), Some(writer)) => (reader, writer),
+            _ => return,
+        };
+
+        if let Schema::Union(ref writer_union) = *writer {
+            for branch in writer_union.variants() {
+                self.check(reader, branch, path);
+            }
+            return
+        }
+
+        if let Schema::Union(ref reader_union) = *reader {
+            if !reader_union
+                .variants()
+                .iter()
+                .any(|branch| self.can_read(branch, writer, path))
+            {
+                self.report(
+                    path,
+                    IncompatibilityReason::MissingUnionBranch {
+                        writer: describe(writer),
+                    },
+                );
+            }
+            return
+        }
+
+        match (reader, writer) {
+            (
+                Schema::Record {
+                    name: reader_name,
+                    fields: reader_fields,
+                    ..
+                },
+                Schema::Record {
+                    name: writer_name,
+                    fields: writer_fields,
+                    ..
+                },
+            ) => {
+                if self.check_names(reader_name, writer_name, path) {
+                    self.check_fields(reader_fields, writer_fields, path);
+                }
+            },
+            (
+                Schema::Enum {
+                    name: reader_name,
+                    symbols: reader_symbols,
+                    ..
+                },
+                Schema::Enum {
+                    name: writer_name,
+                    symbols: writer_symbols,
+                    ..
+                },
+            ) => {
+                if self.check_names(reader_name, writer_name, path) {
+                    let missing = writer_symbols
+                        .iter()
+                        .filter(|symbol| !reader_symbols.contains(symbol))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    if !missing.is_empty() {
+                        self.report(
+                            path,
+                            IncompatibilityReason::MissingEnumSymbols { symbols: missing },
+                        );
+                    }
+                }
+            },
+            (Schema::Array(reader_items), Schema::Array(writer_items)) => {
+                self.check(reader_items, writer_items, &format!("{}[]", path))
+            },
+            (Schema::Map(reader_values), Schema::Map(writer_values)) => {
+                self.check(reader_values, writer_values, &format!("{}{{}}", path))
+            },
+            _ => self.check_primitives(reader, writer, path),
+        }
+    }
+
+    /// Check types which do not contain other types: primitives, `fixed` and logical types.
+    fn check_primitives(&mut self, reader: &Schema, writer: &Schema, path: &str) {
+        if let Some(reason) = logical_type_mismatch(reader, writer) {
+            return self.report(path, reason)
+        }
+
+        match (&*underlying(reader), &*underlying(writer)) {
+            (
+                Schema::Fixed {
+                    name: reader_name,
+                    size: reader_size,
+                    ..
+                },
+                Schema::Fixed {
+                    name: writer_name,
+                    size: writer_size,
+                    ..
+                },
+            ) => {
+                if self.check_names(reader_name, writer_name, path) && reader_size != writer_size {
+                    self.report(
+                        path,
+                        IncompatibilityReason::FixedSizeMismatch {
+                            reader: *reader_size,
+                            writer: *writer_size,
+                        },
+                    );
+                }
+            },
+            (underlying_reader, underlying_writer) => {
+                let reader_kind = SchemaKind::from(underlying_reader);
+                let writer_kind = SchemaKind::from(underlying_writer);
+                if !is_promotable(reader_kind, writer_kind) {
+                    self.report(
+                        path,
+                        IncompatibilityReason::TypeMismatch {
+                            reader: describe(reader),
+                            writer: describe(writer),
+                        },
+                    );
+                }
+            },
+        }
+    }
+
+    fn check_fields(
+        &mut self,
+        reader_fields: &'a [RecordField],
+        writer_fields: &'a [RecordField],
+        path: &str,
+    ) {
+        for reader_field in reader_fields {
+            let writer_field = writer_fields
+                .iter()
+                .find(|writer_field| reader_field.matches(&writer_field.name));
+            let field_path = format!("{}.{}", path, reader_field.name);
+
+            match writer_field {
+                Some(writer_field) => {
+                    self.check(&reader_field.schema, &writer_field.schema, &field_path)
+                },
+                None if reader_field.default.is_none() => self.report(
+                    &field_path,
+                    IncompatibilityReason::MissingDefault {
+                        field: reader_field.name.clone(),
+                    },
+                ),
+                None => (),
+            }
+        }
+    }
+
+    /// Check that named types match, and that they have not been checked already. Returns whether
+    /// their definitions still have to be checked.
+    fn check_names(&mut self, reader: &Name, writer: &Name, path: &str) -> bool {
+        if !reader.matches(writer) {
+            self.report(
+                path,
+                IncompatibilityReason::NameMismatch {
+                    reader: reader.fullname(None),
+                    writer: writer.fullname(None),
+                },
+            );
+            return false
+        }
+
+        self.checked
+            .insert((reader.fullname(None), writer.fullname(None)))
+    }
+
+    /// Returns whether `reader` can read `writer`, without reporting anything.
+    fn can_read(&mut self, reader: &'a Schema, writer: &'a Schema, path: &str) -> bool {
+        let checked = self.checked.clone();
+        let found = self.incompatibilities.len();
+
+        self.check(reader, writer, path);
+        let compatible = self.incompatibilities.len() == found;
+
+        self.incompatibilities.truncate(found);
+        self.checked = checked;
+        compatible
+    }
+
+    fn report(&mut self, path: &str, reason: IncompatibilityReason) {
+        self.incompatibilities.push(Incompatibility {
+            path: if path.is_empty() {
+                ".".to_owned()
+            } else {
+                path.to_owned()
+            },
+            reason,
+        });
+    }
+}
+
+/// The promotions allowed by the specification, as (reader, writer) pairs.
+const PROMOTIONS: &[(SchemaKind, SchemaKind)] = &[
+    (SchemaKind::Long, SchemaKind::Int),
+    (SchemaKind::Float, SchemaKind::Int),
+    (SchemaKind::Float, SchemaKind::Long),
+    (SchemaKind::Double, SchemaKind::Int),
+    (SchemaKind::Double, SchemaKind::Long),
+    (SchemaKind::Double, SchemaKind::Float),
+    (SchemaKind::String, SchemaKind::Bytes),
+    (SchemaKind::Bytes, SchemaKind::String),
+];
+
+/// The logical types which can be read as another logical type, as (reader, writer) pairs.
+pub(crate) const LOGICAL_CONVERSIONS: &[(SchemaKind, SchemaKind)] = &[
+    (SchemaKind::TimeMicros, SchemaKind::TimeMillis),
+    (SchemaKind::TimestampMillis, SchemaKind::TimestampMicros),
+    (SchemaKind::TimestampMicros, SchemaKind::TimestampMillis),
+    (SchemaKind::LocalTimestampMillis, SchemaKind::LocalTimestampMicros),
+    (SchemaKind::LocalTimestampMicros, SchemaKind::LocalTimestampMillis),
+];
+
+/// Whether a value of the `writer` type can be read as the `reader` type.
+pub(crate) fn is_promotable(reader: SchemaKind, writer: SchemaKind) -> bool {
+    reader == writer || PROMOTIONS.contains(&(reader, writer))
+}
+
+/// Logical types are read through their underlying types, unless both the reader and the writer
+/// are annotated with logical types which cannot be converted into one another.
+fn logical_type_mismatch(reader: &Schema, writer: &Schema) -> Option<IncompatibilityReason> {
+    match (reader, writer) {
+        (
+            Schema::Decimal {
+                precision: reader_precision,
+                scale: reader_scale,
+                ..
+            },
+            Schema::Decimal {
+                precision: writer_precision,
+                scale: writer_scale,
+                ..
+            },
+        ) => {
+            if reader_scale != writer_scale || reader_precision < writer_precision {
+                Some(IncompatibilityReason::DecimalMismatch {
+                    reader: (*reader_precision, *reader_scale),
+                    writer: (*writer_precision, *writer_scale),
+                })
+            } else {
+                None
+            }
+        },
+        _ => {
+            let reader_kind = SchemaKind::from(reader);
+            let writer_kind = SchemaKind::from(writer);
+            let convertible = reader_kind == writer_kind
+                || !is_logical(reader)
+                || !is_logical(writer)
+                || LOGICAL_CONVERSIONS.contains(&(reader_kind, writer_kind));
+
+            if convertible {
+                None
+            } else {
+                Some(IncompatibilityReason::TypeMismatch {
+                    reader: describe(reader),
+                    writer: describe(writer),
+                })
+            }
+        },
+    }
+}
+
+/// Whether `schema` is annotated by a logical type.
+fn is_logical(schema: &Schema) -> bool {
+    SchemaKind::from(&*underlying(schema)) != SchemaKind::from(schema)
+}
+
+/// The type annotated by a logical type, or `schema` itself.
+fn underlying(schema: &Schema) -> Cow<'_, Schema> {
+    match *schema {
+        Schema::Decimal { ref inner, .. } => Cow::Borrowed(inner),
+        Schema::Date | Schema::TimeMillis => Cow::Owned(Schema::Int),
+        Schema::TimeMicros
+        | Schema::TimestampMillis
+        | Schema::TimestampMicros
+        | Schema::LocalTimestampMillis
+        | Schema::LocalTimestampMicros => Cow::Owned(Schema::Long),
+        Schema::Uuid => Cow::Owned(Schema::String),
+        Schema::Duration { ref name } => Cow::Owned(Schema::Fixed {
+            name: name.clone(),
+            size: 12,
+            props: Props::new(),
+        }),
+        ref schema => Cow::Borrowed(schema),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reasons(reader: &str, writer: &str) -> Vec<(String, IncompatibilityReason)> {
+        let reader = Schema::parse_str(reader).unwrap();
+        let writer = Schema::parse_str(writer).unwrap();
+        check(&reader, &writer)
+            .incompatibilities()
+            .iter()
+            .map(|incompatibility| (incompatibility.path.clone(), incompatibility.reason.clone()))
+            .collect()
+    }
+
+    fn type_mismatch(reader: &str, writer: &str) -> IncompatibilityReason {
+        IncompatibilityReason::TypeMismatch {
+            reader: reader.to_owned(),
+            writer: writer.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_primitive_promotions() {
+        let compatible = vec![
+            (r#""int""#, r#""int""#),
+            (r#""long""#, r#""int""#),
+            (r#""float""#, r#""long""#),
+            (r#""double""#, r#""float""#),
+            (r#""string""#, r#""bytes""#),
+            (r#""bytes""#, r#""string""#),
+            (r#"{"type": "int", "logicalType": "date"}"#, r#""int""#),
+            (r#""long""#, r#"{"type": "int", "logicalType": "date"}"#),
+            (
+                r#"{"type": "long", "logicalType": "timestamp-micros"}"#,
+                r#"{"type": "long", "logicalType": "timestamp-millis"}"#,
+            ),
+            (r#"{"type": "string", "logicalType": "uuid"}"#, r#""string""#),
+        ];
+        for (reader, writer) in compatible {
+            assert_eq!(reasons(reader, writer), vec![], "{} <- {}", reader, writer);
+        }
+
+        assert_eq!(
+            reasons(r#""int""#, r#""long""#),
+            vec![(".".to_owned(), type_mismatch("int", "long"))]
+        );
+        assert_eq!(
+            reasons(r#""string""#, r#""int""#),
+            vec![(".".to_owned(), type_mismatch("string", "int"))]
+        );
+        assert_eq!(
+            reasons(
+                r#"{"type": "long", "logicalType": "local-timestamp-millis"}"#,
+                r#"{"type": "long", "logicalType": "timestamp-millis"}"#,
+            ),
+            vec![(
+                ".".to_owned(),
+                type_mismatch("local-timestamp-millis", "timestamp-millis")
+            )]
+        );
+        assert_eq!(
+            reasons(
+                r#"{"type": "bytes", "logicalType": "decimal", "precision": 4, "scale": 2}"#,
+                r#"{"type": "bytes", "logicalType": "decimal", "precision": 4, "scale": 1}"#,
+            ),
+            vec![(
+                ".".to_owned(),
+                IncompatibilityReason::DecimalMismatch {
+                    reader: (4, 2),
+                    writer: (4, 1),
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_records() {
+        let writer = r#"
+            {
+                "type": "record",
+                "name": "person",
+                "fields": [
+                    {"name": "name", "type": "string"},
+                    {
+                        "name": "address",
+                        "type": {
+                            "type": "record",
+                            "name": "address",
+                            "fields": [
+                                {"name": "street", "type": "string"},
+                                {"name": "zip", "type": "long"}
+                            ]
+                        }
+                    }
+                ]
+            }
+        "#;
+        let reader = r#"
+            {
+                "type": "record",
+                "name": "person",
+                "fields": [
+                    {"name": "fullname", "aliases": ["name"], "type": "string"},
+                    {"name": "age", "type": "int", "default": 0},
+                    {"name": "email", "type": "string"},
+                    {
+                        "name": "address",
+                        "type": {
+                            "type": "record",
+                            "name": "address",
+                            "fields": [{"name": "zip", "type": "int"}]
+                        }
+                    }
+                ]
+            }
+        "#;
+
+        assert_eq!(
+            reasons(reader, writer),
+            vec![
+                (
+                    ".email".to_owned(),
+                    IncompatibilityReason::MissingDefault {
+                        field: "email".to_owned(),
+                    }
+                ),
+                (".address.zip".to_owned(), type_mismatch("int", "long")),
+            ]
+        );
+        // aliases of the writer fields are not used
+        assert_eq!(
+            reasons(writer, reader),
+            vec![
+                (
+                    ".name".to_owned(),
+                    IncompatibilityReason::MissingDefault {
+                        field: "name".to_owned(),
+                    }
+                ),
+                (
+                    ".address.street".to_owned(),
+                    IncompatibilityReason::MissingDefault {
+                        field: "street".to_owned(),
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_named_types() {
+        assert_eq!(
+            reasons(
+                r#"{"type": "enum", "name": "suit", "symbols": ["hearts"]}"#,
+                r#"{"type": "enum", "name": "suit", "symbols": ["hearts", "spades", "clubs"]}"#,
+            ),
+            vec![(
+                ".".to_owned(),
+                IncompatibilityReason::MissingEnumSymbols {
+                    symbols: vec!["spades".to_owned(), "clubs".to_owned()],
+                }
+            )]
+        );
+        assert_eq!(
+            reasons(
+                r#"{"type": "fixed", "name": "hash", "size": 16}"#,
+                r#"{"type": "fixed", "name": "hash", "size": 20}"#,
+            ),
+            vec![(
+                ".".to_owned(),
+                IncompatibilityReason::FixedSizeMismatch {
+                    reader: 16,
+                    writer: 20,
+                }
+            )]
+        );
+        assert_eq!(
+            reasons(
+                r#"{"type": "fixed", "name": "md5", "size": 16}"#,
+                r#"{"type": "fixed", "name": "hash", "size": 16}"#,
+            ),
+            vec![(
+                ".".to_owned(),
+                IncompatibilityReason::NameMismatch {
+                    reader: "md5".to_owned(),
+                    writer: "hash".to_owned(),
+                }
+            )]
+        );
+        assert_eq!(
+            reasons(
+                r#"{"type": "fixed", "name": "md5", "aliases": ["hash"], "size": 16}"#,
+                r#"{"type": "fixed", "name": "hash", "size": 16}"#,
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_unions() {
+        assert_eq!(reasons(r#"["null", "long"]"#, r#""int""#), vec![]);
+        assert_eq!(reasons(r#"["null", "long"]"#, r#"["int", "null"]"#), vec![]);
+        assert_eq!(reasons(r#""long""#, r#"["int"]"#), vec![]);
+        assert_eq!(
+            reasons(r#""long""#, r#"["null", "int"]"#),
+            vec![(".".to_owned(), type_mismatch("long", "null"))]
+        );
+        assert_eq!(
+            reasons(
+                r#"{"type": "array", "items": ["null", "string"]}"#,
+                r#"{"type": "array", "items": "double"}"#,
+            ),
+            vec![(
+                "[]".to_owned(),
+                IncompatibilityReason::MissingUnionBranch {
+                    writer: "double".to_owned(),
+                }
+            )]
+        );
+        assert_eq!(
+            reasons(
+                r#"{"type": "map", "values": "int"}"#,
+                r#"{"type": "map", "values": "long"}"#,
+            ),
+            vec![("{}".to_owned(), type_mismatch("int", "long"))]
+        );
+    }
+
+    #[test]
+    fn test_recursive_records() {
+        let writer = r#"
+            {
+                "type": "record",
+                "name": "list",
+                "fields": [
+                    {"name": "value", "type": "int"},
+                    {"name": "next", "type": ["null", "list"]}
+                ]
+            }
+        "#;
+        let reader = r#"
+            {
+                "type": "record",
+                "name": "list",
+                "fields": [
+                    {"name": "value", "type": "long"},
+                    {"name": "next", "type": ["null", "list"]}
+                ]
+            }
+        "#;
+
+        assert_eq!(reasons(reader, writer), vec![]);
+        assert_eq!(
+            reasons(writer, reader),
+            vec![(".value".to_owned(), type_mismatch("int", "long"))]
+        );
+    }
+
+    #[test]
+    fn test_modes() {
+        let v1 = Schema::parse_str(
+            r#"{"type": "record", "name": "r", "fields": [{"name": "a", "type": "int"}]}"#,
+        ).unwrap();
+        // adds a field with a default
+        let v2 = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "r",
+                "fields": [
+                    {"name": "a", "type": "int"},
+                    {"name": "b", "type": "int", "default": 0}
+                ]
+            }
+            "#,
+        ).unwrap();
+        // removes the field without a default
+        let v3 = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "r",
+                "fields": [{"name": "b", "type": "int", "default": 0}]
+            }
+            "#,
+        ).unwrap();
+
+        let history = vec![v1.clone(), v2.clone()];
+        assert!(CompatibilityMode::Full.check(&v2, std::slice::from_ref(&v1)).is_empty());
+        assert!(CompatibilityMode::Backward.check(&v3, &history).is_empty());
+        assert!(
+            CompatibilityMode::Backward
+                .check_transitive(&v3, &history)
+                .is_empty()
+        );
+
+        let incompatibilities = CompatibilityMode::Forward.check(&v3, &history);
+        assert_eq!(incompatibilities.len(), 1);
+        assert_eq!(incompatibilities[0].version, 1);
+        assert_eq!(incompatibilities[0].direction, CompatibilityMode::Forward);
+        assert_eq!(
+            incompatibilities[0].report.to_string(),
+            ".a: field a is not written and has no default value"
+        );
+
+        let incompatibilities = CompatibilityMode::Full.check_transitive(&v3, &history);
+        assert_eq!(
+            incompatibilities
+                .iter()
+                .map(|incompatibility| (incompatibility.version, incompatibility.direction))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, CompatibilityMode::Forward),
+                (1, CompatibilityMode::Forward),
+            ]
+        );
+        assert!(CompatibilityMode::Full.check(&v1, &[]).is_empty());
+    }
+}
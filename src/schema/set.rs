@@ -0,0 +1,368 @@
+//! Logic for parsing sets of schemas which refer to the named types of one another.
+use std::collections::HashMap;
+
+use failure::Error;
+use serde_json::{self, Value};
+
+use schema::{ExternalType, Name, ParseSchemaError, Parser, Schema};
+
+/// A set of JSON schemas, each one possibly referring to the named types defined by the others.
+///
+/// Sources can be added in any order. Parsing the set gives back one `Schema` per source, in the
+/// order they were added, where the named types defined in other sources are inlined at their
+/// first use. Every `Schema` is thus self-contained.
+///
+/// ```
+/// use avro_rs::schema::SchemaSet;
+///
+/// let mut set = SchemaSet::new();
+/// set.add(
+///     "person.avsc",
+///     r#"
+///     {
+///         "type": "record",
+///         "name": "person",
+///         "namespace": "com.example",
+///         "fields": [{"name": "address", "type": "address"}]
+///     }
+///     "#,
+/// ).unwrap();
+/// set.add(
+///     "address.avsc",
+///     r#"
+///     {
+///         "type": "record",
+///         "name": "address",
+///         "namespace": "com.example",
+///         "fields": [{"name": "zip", "type": "string"}]
+///     }
+///     "#,
+/// ).unwrap();
+///
+/// let schemas = set.parse().unwrap();
+/// assert_eq!(schemas.len(), 2);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SchemaSet {
+    sources: Vec<(String, Value)>,
+    lenient: bool,
+}
+
+impl SchemaSet {
+    /// Create an empty `SchemaSet`.
+    pub fn new() -> SchemaSet {
+        SchemaSet::default()
+    }
+
+    /// Add the JSON schema `input` to the set. `source` identifies it in error messages, and is
+    /// typically the path of the file it was read from.
+    pub fn add(&mut self, source: &str, input: &str) -> Result<&mut SchemaSet, Error> {
+        let value = serde_json::from_str(input)?;
+        Ok(self.add_value(source, value))
+    }
+
+    /// Add the JSON schema `value` to the set.
+    pub fn add_value(&mut self, source: &str, value: Value) -> &mut SchemaSet {
+        self.sources.push((source.to_owned(), value));
+        self
+    }
+
+    /// Whether to parse the schemas using the lenient rules of `Schema::parse_lenient`.
+    pub fn lenient(&mut self, lenient: bool) -> &mut SchemaSet {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Parse every schema of the set, in the order they were added.
+    ///
+    /// Fails if a named type is defined more than once, or if a schema cannot be parsed, which
+    /// includes references to names defined in none of the sources. All such errors are reported
+    /// together, along with their source.
+    pub fn parse(&self) -> Result<Vec<Schema>, Error> {
+        let mut definitions = HashMap::new();
+        let mut errors = Vec::new();
+        for (source, value) in &self.sources {
+            collect_definitions(value, None, source, &mut definitions, &mut errors);
+        }
+        if !errors.is_empty() {
+            return Err(ParseSchemaError::new(errors.join("; ")).into())
+        }
+
+        let external_types = definitions
+            .into_iter()
+            .map(|(fullname, (_, external))| (fullname, external))
+            .collect::<HashMap<_, _>>();
+
+        let mut schemas = Vec::with_capacity(self.sources.len());
+        for (source, value) in &self.sources {
+            let mut parser = Parser {
+                named_types: HashMap::new(),
+                external_types: external_types.clone(),
+                lenient: self.lenient,
+            };
+            match parser.parse_root(value) {
+                Ok(schema) => schemas.push(schema),
+                Err(error) => {
+                    let message = match error.downcast::<ParseSchemaError>() {
+                        Ok(ParseSchemaError(message)) => message,
+                        Err(error) => error.to_string(),
+                    };
+                    errors.push(format!("{} in {}", message, source));
+                },
+            }
+        }
+        if !errors.is_empty() {
+            return Err(ParseSchemaError::new(errors.join("; ")).into())
+        }
+
+        Ok(schemas)
+    }
+}
+
+impl Schema {
+    /// Create a `Schema` from each string of `inputs`, representing JSON Avro schemas which can
+    /// refer to the named types defined by one another, in any order.
+    ///
+    /// See `SchemaSet` for more details, and to name the inputs in error messages.
+    pub fn parse_list(inputs: &[&str]) -> Result<Vec<Schema>, Error> {
+        let mut set = SchemaSet::new();
+        for (i, input) in inputs.iter().enumerate() {
+            set.add(&format!("input #{}", i), input)?;
+        }
+        set.parse()
+    }
+}
+
+/// Collect the definitions of all the named types found in `value`, indexed by their `fullname`,
+/// along with the source defining them. Duplicate definitions are reported in `errors`.
+fn collect_definitions<'a>(
+    value: &Value,
+    enclosing_namespace: Option<&str>,
+    source: &'a str,
+    definitions: &mut HashMap<String, (&'a str, ExternalType)>,
+    errors: &mut Vec<String>,
+) {
+    let complex = match *value {
+        Value::Array(ref variants) => {
+            for variant in variants {
+                collect_definitions(variant, enclosing_namespace, source, definitions, errors);
+            }
+            return
+        },
+        Value::Object(ref complex) => complex,
+        _ => return,
+    };
+
+    let type_ = match complex.get("type") {
+        Some(Value::String(type_)) => type_.as_str(),
+        Some(nested) => {
+            return collect_definitions(nested, enclosing_namespace, source, definitions, errors)
+        },
+        None => return,
+    };
+
+    match type_ {
+        "record" | "enum" | "fixed" => {
+            // Schemas without a valid name are reported by the parser.
+            let name = match Name::parse(complex) {
+                Ok(name) => name.qualified(enclosing_namespace),
+                Err(_) => return,
+            };
+            let fullname = name.fullname(None);
+
+            if let Some(&(defined_in, _)) = definitions.get(&fullname) {
+                errors.push(format!(
+                    "Duplicate definition of type {} in {} and {}",
+                    fullname, defined_in, source
+                ));
+                return
+            }
+            let external = ExternalType {
+                definition: value.clone(),
+                enclosing_namespace: enclosing_namespace.map(|ns| ns.to_owned()),
+            };
+            definitions.insert(fullname, (source, external));
+
+            if let Some(Value::Array(fields)) = complex.get("fields") {
+                let namespace = name.namespace.as_deref();
+                for field in fields {
+                    if let Some(type_) = field.get("type") {
+                        collect_definitions(type_, namespace, source, definitions, errors);
+                    }
+                }
+            }
+        },
+        "array" | "map" => {
+            let key = if type_ == "array" { "items" } else { "values" };
+            if let Some(inner) = complex.get(key) {
+                collect_definitions(inner, enclosing_namespace, source, definitions, errors);
+            }
+        },
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_list_in_any_order() {
+        let person = r#"
+            {
+                "type": "record",
+                "name": "person",
+                "namespace": "com.example",
+                "fields": [
+                    {"name": "address", "type": "address"},
+                    {"name": "previous", "type": {"type": "array", "items": "address"}},
+                    {"name": "country", "type": "org.iso.country"}
+                ]
+            }
+        "#;
+        let address = r#"
+            {
+                "type": "record",
+                "name": "address",
+                "namespace": "com.example",
+                "fields": [
+                    {"name": "zip", "type": "string"},
+                    {"name": "country", "type": "org.iso.country"}
+                ]
+            }
+        "#;
+        let country = r#"
+            {"type": "enum", "name": "country", "namespace": "org.iso", "symbols": ["FR", "US"]}
+        "#;
+
+        let schemas = Schema::parse_list(&[person, address, country]).unwrap();
+        assert_eq!(schemas.len(), 3);
+        assert_eq!(schemas[1], Schema::parse_list(&[address, country]).unwrap()[0]);
+        assert_eq!(schemas[2], Schema::parse_str(country).unwrap());
+
+        let expected = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "person",
+                "namespace": "com.example",
+                "fields": [
+                    {
+                        "name": "address",
+                        "type": {
+                            "type": "record",
+                            "name": "address",
+                            "fields": [
+                                {"name": "zip", "type": "string"},
+                                {
+                                    "name": "country",
+                                    "type": {
+                                        "type": "enum",
+                                        "name": "country",
+                                        "namespace": "org.iso",
+                                        "symbols": ["FR", "US"]
+                                    }
+                                }
+                            ]
+                        }
+                    },
+                    {"name": "previous", "type": {"type": "array", "items": "address"}},
+                    {"name": "country", "type": "org.iso.country"}
+                ]
+            }
+            "#,
+        ).unwrap();
+        assert_eq!(schemas[0], expected);
+    }
+
+    #[test]
+    fn test_nested_definitions() {
+        // `item` is defined within `order`, and used on its own by `cart`
+        let order = r#"
+            {
+                "type": "record",
+                "name": "order",
+                "fields": [
+                    {
+                        "name": "items",
+                        "type": {
+                            "type": "array",
+                            "items": {
+                                "type": "record",
+                                "name": "item",
+                                "fields": [{"name": "sku", "type": "string"}]
+                            }
+                        }
+                    }
+                ]
+            }
+        "#;
+        let cart = r#"
+            {
+                "type": "record",
+                "name": "cart",
+                "fields": [
+                    {"name": "item", "type": "item"},
+                    {"name": "order", "type": "order"}
+                ]
+            }
+        "#;
+
+        let schemas = Schema::parse_list(&[cart, order]).unwrap();
+        let expected = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "cart",
+                "fields": [
+                    {
+                        "name": "item",
+                        "type": {
+                            "type": "record",
+                            "name": "item",
+                            "fields": [{"name": "sku", "type": "string"}]
+                        }
+                    },
+                    {
+                        "name": "order",
+                        "type": {
+                            "type": "record",
+                            "name": "order",
+                            "fields": [
+                                {"name": "items", "type": {"type": "array", "items": "item"}}
+                            ]
+                        }
+                    }
+                ]
+            }
+            "#,
+        ).unwrap();
+        assert_eq!(schemas[0], expected);
+        assert_eq!(schemas[1], Schema::parse_str(order).unwrap());
+    }
+
+    #[test]
+    fn test_errors_report_sources() {
+        let mut set = SchemaSet::new();
+        set.add("a.avsc", r#"{"type": "fixed", "name": "md5", "size": 16}"#)
+            .unwrap()
+            .add("b.avsc", r#"{"type": "fixed", "name": "md5", "size": 16}"#)
+            .unwrap();
+        assert_eq!(
+            set.parse().unwrap_err().to_string(),
+            "Failed to parse schema: Duplicate definition of type md5 in a.avsc and b.avsc"
+        );
+
+        let mut set = SchemaSet::new();
+        set.add("a.avsc", r#"{"type": "array", "items": "sha1"}"#)
+            .unwrap()
+            .add("b.avsc", r#"{"type": "map", "values": "md5"}"#)
+            .unwrap();
+        assert_eq!(
+            set.parse().unwrap_err().to_string(),
+            "Failed to parse schema: Unknown type: sha1 in a.avsc; Unknown type: md5 in b.avsc"
+        );
+
+        assert!(SchemaSet::new().add("a.avsc", "{").is_err());
+    }
+}
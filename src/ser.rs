@@ -6,40 +6,77 @@ use std::iter::once;
 
 use serde::ser::{self, Error as SerdeError, Serialize};
 
+use schema::{Names, Schema};
 use types::{ToAvro, Value};
 
-#[derive(Clone, Default)]
-pub struct Serializer {}
+#[derive(Clone)]
+pub struct Serializer<'s> {
+    /// The schema of the value, if known, whose unions hold the variants of enums in the named
+    /// types called after them.
+    schema: Option<&'s Schema>,
+    names: &'s Names<'s>,
+}
 
-pub struct SeqSerializer {
+pub struct SeqSerializer<'s> {
     items: Vec<Value>,
+    schema: Option<&'s Schema>,
+    names: &'s Names<'s>,
 }
 
-pub struct MapSerializer {
+pub struct MapSerializer<'s> {
     indices: HashMap<String, usize>,
     values: Vec<Value>,
+    schema: Option<&'s Schema>,
+    names: &'s Names<'s>,
+}
+
+pub struct StructSerializer<'s> {
+    fields: Vec<(String, Value)>,
+    schema: Option<&'s Schema>,
+    names: &'s Names<'s>,
 }
 
-pub struct StructSerializer {
+/// Serializes a tuple or struct variant of an enum as a record, within the union holding it if
+/// known. The fields of tuple variants are the fields of the record in order, named `field0`,
+/// `field1`, etc. when the record is not known.
+pub struct VariantSerializer<'s> {
+    index: Option<u32>,
     fields: Vec<(String, Value)>,
+    schema: Option<&'s Schema>,
+    names: &'s Names<'s>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Error {
     message: String,
+    path: String,
+}
+
+impl Error {
+    /// Move the error into `segment` of its enclosing value: `.field` for a field of a record,
+    /// `[index]` for an item of an array and `{key}` for a value of a map.
+    pub(crate) fn within(mut self, segment: &str) -> Error {
+        self.path.insert_str(0, segment);
+        self
+    }
 }
 
 impl ser::Error for Error {
     fn custom<T: fmt::Display>(msg: T) -> Self {
         Error {
             message: msg.to_string(),
+            path: String::new(),
         }
     }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str(error::Error::description(self))
+        if self.path.is_empty() {
+            formatter.write_str(&self.message)
+        } else {
+            write!(formatter, "{}: {}", self.path, self.message)
+        }
     }
 }
 
@@ -49,46 +86,160 @@ impl error::Error for Error {
     }
 }
 
-impl SeqSerializer {
-    pub fn new(len: Option<usize>) -> SeqSerializer {
+impl<'s> Serializer<'s> {
+    pub fn new(schema: Option<&'s Schema>, names: &'s Names<'s>) -> Serializer<'s> {
+        Serializer {
+            schema: schema.and_then(|schema| schema.resolve_ref(names)),
+            names,
+        }
+    }
+
+    /// Find the variant of the union, if the value is one, holding the enum variant `variant`:
+    /// the named type called after the enum variant, or else the variant at `position` if given.
+    fn find_enum_variant(&self, variant: &str, position: Option<u32>) -> Option<(u32, &'s Schema)> {
+        let variants = match self.schema {
+            Some(Schema::Union(union)) => union.variants(),
+            _ => return None,
+        };
+        variants
+            .iter()
+            .position(|schema| matches!(schema.name(), Some(name) if name.name == variant))
+            .or_else(|| {
+                position
+                    .map(|position| position as usize)
+                    .filter(|&position| position < variants.len())
+            })
+            .map(|index| (index as u32, &variants[index]))
+    }
+
+    /// Serialize the value of an option, following the only variant which is not `null` if the
+    /// schema is a union with one, or else the schema itself.
+    fn serialize_some_value<T: ?Sized + Serialize>(&self, value: &T) -> Result<Value, Error> {
+        let schema = match self.schema {
+            Some(Schema::Union(union)) => {
+                let mut variants = union.variants().iter().filter(|s| **s != Schema::Null);
+                match (variants.next(), variants.next()) {
+                    (Some(variant), None) => Some(variant),
+                    _ => self.schema,
+                }
+            },
+            schema => schema,
+        };
+        value.serialize(&mut Serializer::new(schema, self.names))
+    }
+}
+
+impl<'s> SeqSerializer<'s> {
+    pub fn new(
+        len: Option<usize>,
+        schema: Option<&'s Schema>,
+        names: &'s Names<'s>,
+    ) -> SeqSerializer<'s> {
         let items = match len {
             Some(len) => Vec::with_capacity(len),
             None => Vec::new(),
         };
+        let schema = match schema {
+            Some(Schema::Array(items)) => Some(&**items),
+            _ => None,
+        };
 
-        SeqSerializer { items }
+        SeqSerializer {
+            items,
+            schema,
+            names,
+        }
     }
 }
 
-impl MapSerializer {
-    pub fn new(len: Option<usize>) -> MapSerializer {
+impl<'s> MapSerializer<'s> {
+    pub fn new(
+        len: Option<usize>,
+        schema: Option<&'s Schema>,
+        names: &'s Names<'s>,
+    ) -> MapSerializer<'s> {
         let (indices, values) = match len {
             Some(len) => (HashMap::with_capacity(len), Vec::with_capacity(len)),
             None => (HashMap::new(), Vec::new()),
         };
+        let schema = match schema {
+            Some(Schema::Map(values)) => Some(&**values),
+            _ => None,
+        };
 
-        MapSerializer { indices, values }
+        MapSerializer {
+            indices,
+            values,
+            schema,
+            names,
+        }
     }
 }
 
-impl StructSerializer {
-    pub fn new(len: usize) -> StructSerializer {
+impl<'s> StructSerializer<'s> {
+    pub fn new(
+        len: usize,
+        schema: Option<&'s Schema>,
+        names: &'s Names<'s>,
+    ) -> StructSerializer<'s> {
         StructSerializer {
             fields: Vec::with_capacity(len),
+            schema,
+            names,
         }
     }
 }
 
-impl<'b> ser::Serializer for &'b mut Serializer {
+impl<'s> VariantSerializer<'s> {
+    pub fn new(
+        variant: Option<(u32, &'s Schema)>,
+        len: usize,
+        schema: Option<&'s Schema>,
+        names: &'s Names<'s>,
+    ) -> VariantSerializer<'s> {
+        let (index, schema) = match variant {
+            Some((index, variant)) => (Some(index), variant.resolve_ref(names)),
+            None => (None, schema),
+        };
+        VariantSerializer {
+            index,
+            fields: Vec::with_capacity(len),
+            schema,
+            names,
+        }
+    }
+
+    fn end_variant(self) -> Value {
+        let record = Value::Record(self.fields);
+        match self.index {
+            Some(index) => Value::Union(index, Box::new(record)),
+            None => record,
+        }
+    }
+}
+
+/// Serialize `value` as the field `name` of a record whose schema is `schema`, if known.
+fn serialize_field<T: ?Sized + Serialize>(
+    name: &str,
+    value: &T,
+    schema: Option<&Schema>,
+    names: &Names,
+) -> Result<(String, Value), Error> {
+    let schema = schema.and_then(|schema| schema.field_schema(name));
+    let value = value.serialize(&mut Serializer::new(schema, names))?;
+    Ok((name.to_owned(), value))
+}
+
+impl<'b, 's> ser::Serializer for &'b mut Serializer<'s> {
     type Ok = Value;
     type Error = Error;
-    type SerializeSeq = SeqSerializer;
-    type SerializeTuple = SeqSerializer;
-    type SerializeTupleStruct = SeqSerializer;
-    type SerializeTupleVariant = SeqSerializer;
-    type SerializeMap = MapSerializer;
-    type SerializeStruct = StructSerializer;
-    type SerializeStructVariant = StructSerializer;
+    type SerializeSeq = SeqSerializer<'s>;
+    type SerializeTuple = SeqSerializer<'s>;
+    type SerializeTupleStruct = SeqSerializer<'s>;
+    type SerializeTupleVariant = VariantSerializer<'s>;
+    type SerializeMap = MapSerializer<'s>;
+    type SerializeStruct = StructSerializer<'s>;
+    type SerializeStructVariant = VariantSerializer<'s>;
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
         Ok(Value::Boolean(v))
@@ -155,15 +306,25 @@ impl<'b> ser::Serializer for &'b mut Serializer {
     }
 
     fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
-        Ok(ToAvro::avro(None::<Self::Ok>))
+        match self.schema {
+            Some(schema) => Ok(Value::union_in(schema, Value::Null, self.names)
+                .unwrap_or_else(|_| ToAvro::avro(None::<Self::Ok>))),
+            None => Ok(ToAvro::avro(None::<Self::Ok>)),
+        }
     }
 
     fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error>
     where
         T: Serialize,
     {
-        let v = value.serialize(&mut Serializer::default())?;
-        Ok(ToAvro::avro(Some(v)))
+        let v = self.serialize_some_value(value)?;
+        match (self.schema, v) {
+            // Enums within the union already found the variant holding them.
+            (Some(Schema::Union(_)), v @ Value::Union(..)) => Ok(v),
+            (Some(schema), v) => Ok(Value::union_in(schema, v, self.names)
+                .unwrap_or_else(|v| ToAvro::avro(Some(v)))),
+            (None, v) => Ok(ToAvro::avro(Some(v))),
+        }
     }
 
     fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
@@ -180,6 +341,43 @@ impl<'b> ser::Serializer for &'b mut Serializer {
         index: u32,
         variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
+        let symbol = |schema: &Schema| match *schema {
+            Schema::Enum { ref symbols, .. } => symbols
+                .iter()
+                .position(|symbol| symbol == variant)
+                .map(|position| Value::Enum(position as i32, variant.to_owned())),
+            _ => None,
+        };
+        match self.schema {
+            Some(Schema::Union(union)) => {
+                // Within a union, a record without fields named after the variant is preferred to
+                // the symbol of an enum.
+                if let Some((index, schema)) = self.find_enum_variant(variant, None) {
+                    if let Some(Schema::Record { fields, .. }) = schema.resolve_ref(self.names) {
+                        if fields.is_empty() {
+                            return Ok(Value::Union(index, Box::new(Value::Record(vec![]))));
+                        }
+                    }
+                }
+                let found = union
+                    .variants()
+                    .iter()
+                    .enumerate()
+                    .find_map(|(index, schema)| {
+                        let value = schema.resolve_ref(self.names).and_then(&symbol)?;
+                        Some(Value::Union(index as u32, Box::new(value)))
+                    });
+                if let Some(value) = found {
+                    return Ok(value);
+                }
+            },
+            Some(schema) => {
+                if let Some(value) = symbol(schema) {
+                    return Ok(value);
+                }
+            },
+            None => {},
+        }
         Ok(Value::Enum(index as i32, variant.to_string()))
     }
 
@@ -197,18 +395,24 @@ impl<'b> ser::Serializer for &'b mut Serializer {
     fn serialize_newtype_variant<T: ?Sized>(
         self,
         _: &'static str,
-        _: u32,
-        _: &'static str,
+        index: u32,
+        variant: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
         T: Serialize,
     {
-        value.serialize(self)
+        match self.find_enum_variant(variant, Some(index)) {
+            Some((index, schema)) => {
+                let value = value.serialize(&mut Serializer::new(Some(schema), self.names))?;
+                Ok(Value::Union(index, Box::new(value)))
+            },
+            None => value.serialize(self),
+        }
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-        Ok(SeqSerializer::new(len))
+        Ok(SeqSerializer::new(len, self.schema, self.names))
     }
 
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
@@ -226,15 +430,21 @@ impl<'b> ser::Serializer for &'b mut Serializer {
     fn serialize_tuple_variant(
         self,
         _: &'static str,
-        _: u32,
-        _: &'static str,
-        _: usize,
+        index: u32,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-        unimplemented!() // TODO ?
+        let variant = self.find_enum_variant(variant, Some(index));
+        Ok(VariantSerializer::new(
+            variant,
+            len,
+            self.schema,
+            self.names,
+        ))
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-        Ok(MapSerializer::new(len))
+        Ok(MapSerializer::new(len, self.schema, self.names))
     }
 
     fn serialize_struct(
@@ -242,21 +452,27 @@ impl<'b> ser::Serializer for &'b mut Serializer {
         _: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
-        Ok(StructSerializer::new(len))
+        Ok(StructSerializer::new(len, self.schema, self.names))
     }
 
     fn serialize_struct_variant(
         self,
         _: &'static str,
-        _: u32,
-        _: &'static str,
-        _: usize,
+        index: u32,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
-        unimplemented!() // TODO ?
+        let variant = self.find_enum_variant(variant, Some(index));
+        Ok(VariantSerializer::new(
+            variant,
+            len,
+            self.schema,
+            self.names,
+        ))
     }
 }
 
-impl<'a> ser::SerializeSeq for SeqSerializer {
+impl<'s> ser::SerializeSeq for SeqSerializer<'s> {
     type Ok = Value;
     type Error = Error;
 
@@ -264,8 +480,8 @@ impl<'a> ser::SerializeSeq for SeqSerializer {
     where
         T: Serialize,
     {
-        self.items
-            .push(value.serialize(&mut Serializer::default())?);
+        let mut serializer = Serializer::new(self.schema, self.names);
+        self.items.push(value.serialize(&mut serializer)?);
         Ok(())
     }
 
@@ -274,7 +490,7 @@ impl<'a> ser::SerializeSeq for SeqSerializer {
     }
 }
 
-impl<'a> ser::SerializeTuple for SeqSerializer {
+impl<'s> ser::SerializeTuple for SeqSerializer<'s> {
     type Ok = Value;
     type Error = Error;
 
@@ -290,7 +506,7 @@ impl<'a> ser::SerializeTuple for SeqSerializer {
     }
 }
 
-impl ser::SerializeTupleStruct for SeqSerializer {
+impl<'s> ser::SerializeTupleStruct for SeqSerializer<'s> {
     type Ok = Value;
     type Error = Error;
 
@@ -306,23 +522,41 @@ impl ser::SerializeTupleStruct for SeqSerializer {
     }
 }
 
-impl ser::SerializeTupleVariant for SeqSerializer {
+impl<'s> ser::SerializeTupleVariant for VariantSerializer<'s> {
     type Ok = Value;
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _: &T) -> Result<(), Self::Error>
+    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: Serialize,
     {
-        unimplemented!()
+        let position = self.fields.len();
+        let field = match self.schema {
+            Some(Schema::Record { fields, .. }) => match fields.get(position) {
+                Some(field) => {
+                    let schema = Some(&field.schema);
+                    let value = value.serialize(&mut Serializer::new(schema, self.names))?;
+                    (field.name.clone(), value)
+                },
+                None => {
+                    return Err(Error::custom(format!(
+                        "more fields than the {} of the record",
+                        fields.len()
+                    )))
+                },
+            },
+            _ => serialize_field(&format!("field{}", position), value, None, self.names)?,
+        };
+        self.fields.push(field);
+        Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+        Ok(self.end_variant())
     }
 }
 
-impl ser::SerializeMap for MapSerializer {
+impl<'s> ser::SerializeMap for MapSerializer<'s> {
     type Ok = Value;
     type Error = Error;
 
@@ -330,7 +564,7 @@ impl ser::SerializeMap for MapSerializer {
     where
         T: Serialize,
     {
-        let key = key.serialize(&mut Serializer::default())?;
+        let key = key.serialize(&mut Serializer::new(None, self.names))?;
 
         if let Value::String(key) = key {
             self.indices.insert(key, self.values.len());
@@ -344,8 +578,8 @@ impl ser::SerializeMap for MapSerializer {
     where
         T: Serialize,
     {
-        self.values
-            .push(value.serialize(&mut Serializer::default())?);
+        let mut serializer = Serializer::new(self.schema, self.names);
+        self.values.push(value.serialize(&mut serializer)?);
         Ok(())
     }
 
@@ -361,7 +595,7 @@ impl ser::SerializeMap for MapSerializer {
     }
 }
 
-impl ser::SerializeStruct for StructSerializer {
+impl<'s> ser::SerializeStruct for StructSerializer<'s> {
     type Ok = Value;
     type Error = Error;
 
@@ -373,10 +607,8 @@ impl ser::SerializeStruct for StructSerializer {
     where
         T: Serialize,
     {
-        self.fields.push((
-            name.to_owned(),
-            value.serialize(&mut Serializer::default())?,
-        ));
+        let field = serialize_field(name, value, self.schema, self.names)?;
+        self.fields.push(field);
         Ok(())
     }
 
@@ -385,19 +617,25 @@ impl ser::SerializeStruct for StructSerializer {
     }
 }
 
-impl ser::SerializeStructVariant for StructSerializer {
+impl<'s> ser::SerializeStructVariant for VariantSerializer<'s> {
     type Ok = Value;
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _: &'static str, _: &T) -> Result<(), Self::Error>
+    fn serialize_field<T: ?Sized>(
+        &mut self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error>
     where
         T: Serialize,
     {
-        unimplemented!()
+        let field = serialize_field(name, value, self.schema, self.names)?;
+        self.fields.push(field);
+        Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+        Ok(self.end_variant())
     }
 }
 
@@ -405,8 +643,24 @@ impl ser::SerializeStructVariant for StructSerializer {
 ///
 /// This conversion can fail if the value is not valid as per the Avro specification.
 /// e.g: HashMap with non-string keys
+///
+/// Enums whose variants carry no data are converted to the symbol of an Avro enum. Enums with
+/// data are converted to the value of their variant, without the union holding it; see
+/// `to_value_with_schema` to find that variant.
 pub fn to_value<S: Serialize>(value: S) -> Result<Value, Error> {
-    let mut serializer = Serializer::default();
+    let names = Names::new();
+    let mut serializer = Serializer::new(None, &names);
+    value.serialize(&mut serializer)
+}
+
+/// Interpret a serializeable instance as a `Value` of the given `Schema`.
+///
+/// Unlike `to_value`, enums can also be converted to unions: the variant of an enum is held by
+/// the variant of the union called after it, typically a record, or else by the variant at the
+/// same position. The value is not validated against the schema.
+pub fn to_value_with_schema<S: Serialize>(schema: &Schema, value: S) -> Result<Value, Error> {
+    let names = schema.names();
+    let mut serializer = Serializer::new(Some(schema), &names);
     value.serialize(&mut serializer)
 }
 
@@ -433,4 +687,185 @@ mod tests {
 
         assert_eq!(to_value(test).unwrap(), expected);
     }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    enum Suit {
+        Hearts,
+        Spades,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    enum Shape {
+        Circle { radius: f64 },
+        Rectangle(f64, f64),
+        Named(String),
+        Empty,
+    }
+
+    #[test]
+    fn test_enums_to_value() {
+        use de::from_value;
+
+        let value = to_value(Suit::Spades).unwrap();
+        assert_eq!(value, Value::Enum(1, "Spades".to_owned()));
+        assert_eq!(from_value::<Suit>(&value).unwrap(), Suit::Spades);
+
+        // Without a schema, the union holding the other enums is unknown.
+        let value = to_value(Shape::Circle { radius: 1.0 }).unwrap();
+        assert_eq!(
+            value,
+            Value::Record(vec![("radius".to_owned(), Value::Double(1.0))])
+        );
+        let value = to_value(Shape::Named("square".to_owned())).unwrap();
+        assert_eq!(value, Value::String("square".to_owned()));
+        let value = Value::Union(0, Box::new(Value::Record(vec![])));
+        assert!(from_value::<Shape>(&value).is_err());
+    }
+
+    #[test]
+    fn test_enums_to_value_with_schema() {
+        use de::from_value_with_schema;
+        use reader::from_avro_datum;
+        use schema::Schema;
+        use writer::to_avro_datum;
+
+        // The variants of the union are found by name, whatever their position.
+        let schema = Schema::parse_str(
+            r#"
+            [
+                "null",
+                {"type": "record", "name": "Circle", "fields": [
+                    {"name": "radius", "type": "double"}
+                ]},
+                "string",
+                {"type": "record", "name": "Rectangle", "fields": [
+                    {"name": "width", "type": "double"},
+                    {"name": "height", "type": "double"}
+                ]},
+                {"type": "record", "name": "Empty", "fields": []}
+            ]
+        "#,
+        )
+        .unwrap();
+
+        let shapes = vec![
+            (
+                Shape::Circle { radius: 1.0 },
+                Value::Union(
+                    1,
+                    Box::new(Value::Record(vec![(
+                        "radius".to_owned(),
+                        Value::Double(1.0),
+                    )])),
+                ),
+            ),
+            (
+                Shape::Rectangle(2.0, 3.0),
+                Value::Union(
+                    3,
+                    Box::new(Value::Record(vec![
+                        ("width".to_owned(), Value::Double(2.0)),
+                        ("height".to_owned(), Value::Double(3.0)),
+                    ])),
+                ),
+            ),
+            // Variants which are not named types are found by position.
+            (
+                Shape::Named("square".to_owned()),
+                Value::Union(2, Box::new(Value::String("square".to_owned()))),
+            ),
+            (
+                Shape::Empty,
+                Value::Union(4, Box::new(Value::Record(vec![]))),
+            ),
+        ];
+        for (shape, expected) in shapes {
+            let value = to_value_with_schema(&schema, &shape).unwrap();
+            assert_eq!(value, expected);
+            assert!(value.validate(&schema));
+
+            let encoded = to_avro_datum(&schema, value).unwrap();
+            let decoded = from_avro_datum(&schema, &mut &encoded[..], None).unwrap();
+            assert_eq!(decoded, expected);
+            assert_eq!(
+                from_value_with_schema::<Shape>(&schema, &decoded).unwrap(),
+                shape
+            );
+        }
+
+        // An option of the enum is the same union.
+        let value = to_value_with_schema(&schema, Some(Shape::Empty)).unwrap();
+        assert_eq!(value, Value::Union(4, Box::new(Value::Record(vec![]))));
+        let value = to_value_with_schema(&schema, None::<Shape>).unwrap();
+        assert_eq!(value, Value::Union(0, Box::new(Value::Null)));
+        assert_eq!(
+            from_value_with_schema::<Option<Shape>>(&schema, &value).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_nested_enums_to_value_with_schema() {
+        use de::from_value_with_schema;
+        use schema::Schema;
+
+        #[derive(Debug, PartialEq, Deserialize, Serialize)]
+        struct Drawing {
+            suit: Suit,
+            shapes: Vec<Shape>,
+            highlighted: Option<Shape>,
+        }
+
+        let schema = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "Drawing",
+                "fields": [
+                    {"name": "suit", "type": {
+                        "type": "enum", "name": "Suit", "symbols": ["Spades", "Hearts"]
+                    }},
+                    {"name": "shapes", "type": {"type": "array", "items": [
+                        {"type": "record", "name": "Empty", "fields": []},
+                        {"type": "record", "name": "Circle", "fields": [
+                            {"name": "radius", "type": "double"}
+                        ]}
+                    ]}},
+                    {"name": "highlighted", "type": ["null", "Circle", "Empty"]}
+                ]
+            }
+        "#,
+        )
+        .unwrap();
+        let drawing = Drawing {
+            suit: Suit::Hearts,
+            shapes: vec![Shape::Circle { radius: 1.0 }, Shape::Empty],
+            highlighted: Some(Shape::Circle { radius: 2.0 }),
+        };
+
+        let value = to_value_with_schema(&schema, &drawing).unwrap();
+        let circle = |radius| Value::Record(vec![("radius".to_owned(), Value::Double(radius))]);
+        assert_eq!(
+            value,
+            Value::Record(vec![
+                ("suit".to_owned(), Value::Enum(1, "Hearts".to_owned())),
+                (
+                    "shapes".to_owned(),
+                    Value::Array(vec![
+                        Value::Union(1, Box::new(circle(1.0))),
+                        Value::Union(0, Box::new(Value::Record(vec![]))),
+                    ])
+                ),
+                (
+                    "highlighted".to_owned(),
+                    Value::Union(1, Box::new(circle(2.0)))
+                ),
+            ])
+        );
+        assert!(value.validate(&schema));
+        assert_eq!(
+            from_value_with_schema::<Drawing>(&schema, &value).unwrap(),
+            drawing
+        );
+    }
 }
@@ -0,0 +1,1185 @@
+//! Logic for serde-compatible serialization straight into Avro format, following a schema.
+use std::collections::HashMap;
+use std::iter::once;
+
+use serde::ser::{self, Error as SerdeError, Serialize};
+
+use decode::decode_internal;
+use encode::{encode_bytes, encode_int, encode_internal, encode_long, insert_block_header};
+use schema::{describe, Names, RecordField, Schema};
+use ser::Error;
+use types::{Value, MICROS_PER_DAY, MILLIS_PER_DAY};
+use util::parse_uuid;
+
+/// The schema of the keys of maps.
+static STRING_SCHEMA: Schema = Schema::String;
+
+/// Serializes anything implementing `Serialize` straight into Avro format, checking on the way
+/// that it matches the schema.
+///
+/// Optional values are written as unions with a `null` variant, and the fields of structs are
+/// written in the order of the fields of their record, with their default value when missing.
+pub(crate) struct SchemaSerializer<'a, 's: 'a> {
+    schema: &'s Schema,
+    names: &'a Names<'s>,
+    sized_blocks: bool,
+    buffer: &'a mut Vec<u8>,
+}
+
+/// Serializes the items of an array or the entries of a map as a single block.
+pub(crate) struct BlockSerializer<'a, 's: 'a> {
+    schema: &'s Schema,
+    names: &'a Names<'s>,
+    sized_blocks: bool,
+    buffer: &'a mut Vec<u8>,
+    start: usize,
+    key_start: usize,
+    len: usize,
+    // The number of items, when already written before them.
+    announced_len: Option<usize>,
+}
+
+/// Serializes the fields of a struct as a record.
+pub(crate) struct RecordSerializer<'a, 's: 'a> {
+    fields: &'s [RecordField],
+    lookup: &'s HashMap<String, usize>,
+    names: &'a Names<'s>,
+    sized_blocks: bool,
+    buffer: &'a mut Vec<u8>,
+    start: usize,
+    // The position of each field written, with the range of bytes it was written to.
+    written: Vec<(usize, usize, usize)>,
+}
+
+impl<'a, 's> SchemaSerializer<'a, 's> {
+    pub(crate) fn new(
+        schema: &'s Schema,
+        names: &'a Names<'s>,
+        sized_blocks: bool,
+        buffer: &'a mut Vec<u8>,
+    ) -> SchemaSerializer<'a, 's> {
+        SchemaSerializer {
+            schema,
+            names,
+            sized_blocks,
+            buffer,
+        }
+    }
+
+    /// Write a value with `write`, which writes it only if the schema it is given accepts it.
+    /// A union is written with the first of its variants accepting the value.
+    fn write_leaf<F>(self, kind: &str, write: F) -> Result<(), Error>
+    where
+        F: Fn(&Schema, &mut Vec<u8>) -> bool,
+    {
+        let schema = resolve(self.schema, self.names)?;
+        if let Schema::Union(ref union) = *schema {
+            let start = self.buffer.len();
+            for (i, variant) in union.variants().iter().enumerate() {
+                encode_long(i as i64, self.buffer);
+                if write(resolve(variant, self.names)?, self.buffer) {
+                    return Ok(())
+                }
+                self.buffer.truncate(start);
+            }
+        } else if write(schema, self.buffer) {
+            return Ok(())
+        }
+        Err(mismatch(kind, schema))
+    }
+
+    /// Find the schema a compound value is written with: the schema itself if `accepts` holds
+    /// for it, or else the first variant of a union for which it holds, whose index is written.
+    fn find_variant<F>(&mut self, accepts: F) -> Result<Option<&'s Schema>, Error>
+    where
+        F: Fn(&Schema) -> bool,
+    {
+        let schema = resolve(self.schema, self.names)?;
+        if let Schema::Union(ref union) = *schema {
+            for (i, variant) in union.variants().iter().enumerate() {
+                let variant = resolve(variant, self.names)?;
+                if accepts(variant) {
+                    encode_long(i as i64, self.buffer);
+                    return Ok(Some(variant))
+                }
+            }
+            return Ok(None)
+        }
+        Ok(if accepts(schema) { Some(schema) } else { None })
+    }
+
+    /// Find the variant of a union an enum variant is written with: the named type called after
+    /// the enum variant, or else the variant at `position` if given, whose index is written.
+    fn find_enum_variant(
+        &mut self,
+        variant: &str,
+        position: Option<u32>,
+    ) -> Result<Option<&'s Schema>, Error> {
+        let union = match *resolve(self.schema, self.names)? {
+            Schema::Union(ref union) => union,
+            _ => return Ok(None),
+        };
+        let variants = union.variants();
+        let index = variants
+            .iter()
+            .position(|schema| matches!(schema.name(), Some(name) if name.name == variant))
+            .or_else(|| {
+                position
+                    .map(|position| position as usize)
+                    .filter(|&position| position < variants.len())
+            });
+        match index {
+            Some(index) => {
+                encode_long(index as i64, self.buffer);
+                resolve(&variants[index], self.names).map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Start writing a tuple or struct variant of an enum as a record: the record of a union
+    /// found by `find_enum_variant`, or the schema itself if it is a record.
+    fn serialize_record_variant(
+        mut self,
+        index: u32,
+        variant: &str,
+    ) -> Result<RecordSerializer<'a, 's>, Error> {
+        let record = match self.find_enum_variant(variant, Some(index))? {
+            Some(record) => record,
+            None => resolve(self.schema, self.names)?,
+        };
+        match *record {
+            Schema::Record {
+                ref fields,
+                ref lookup,
+                ..
+            } => Ok(RecordSerializer::new(
+                fields,
+                lookup,
+                self.names,
+                self.sized_blocks,
+                self.buffer,
+            )),
+            _ => Err(mismatch(&format!("enum variant {}", variant), self.schema)),
+        }
+    }
+
+    fn serialize_integer(self, kind: &str, v: i64) -> Result<(), Error> {
+        let int = v as i32;
+        let fits_int = i64::from(int) == v;
+        self.write_leaf(kind, |schema, buffer| match *schema {
+            Schema::Int | Schema::Date if fits_int => {
+                encode_int(int, buffer);
+                true
+            },
+            Schema::TimeMillis if (0..MILLIS_PER_DAY).contains(&v) => {
+                encode_int(int, buffer);
+                true
+            },
+            Schema::TimeMicros if (0..MICROS_PER_DAY).contains(&v) => {
+                encode_long(v, buffer);
+                true
+            },
+            Schema::Long
+            | Schema::TimestampMillis
+            | Schema::TimestampMicros
+            | Schema::LocalTimestampMillis
+            | Schema::LocalTimestampMicros => {
+                encode_long(v, buffer);
+                true
+            },
+            _ => false,
+        })
+    }
+}
+
+impl<'a, 's> BlockSerializer<'a, 's> {
+    fn new(
+        schema: &'s Schema,
+        names: &'a Names<'s>,
+        sized_blocks: bool,
+        buffer: &'a mut Vec<u8>,
+        len: Option<usize>,
+    ) -> BlockSerializer<'a, 's> {
+        // The number of items is written before them when known, unless the size of the block
+        // has to be written as well.
+        let announced_len = match len {
+            Some(len) if !sized_blocks => {
+                if len > 0 {
+                    encode_long(len as i64, buffer);
+                }
+                Some(len)
+            },
+            _ => None,
+        };
+
+        BlockSerializer {
+            schema,
+            names,
+            sized_blocks,
+            start: buffer.len(),
+            key_start: buffer.len(),
+            buffer,
+            len: 0,
+            announced_len,
+        }
+    }
+
+    fn serialize_item<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(SchemaSerializer::new(
+            self.schema,
+            self.names,
+            self.sized_blocks,
+            self.buffer,
+        ))
+    }
+
+    fn end_block(self) -> Result<(), Error> {
+        match self.announced_len {
+            Some(announced_len) if announced_len != self.len => {
+                return Err(Error::custom(format!(
+                    "{} items written instead of the {} announced",
+                    self.len, announced_len
+                )))
+            },
+            Some(_) => (),
+            None if self.len > 0 && self.sized_blocks => {
+                insert_block_header(self.len, self.start, self.buffer)
+            },
+            None if self.len > 0 => {
+                let mut header = Vec::new();
+                encode_long(self.len as i64, &mut header);
+                self.buffer.splice(self.start..self.start, header);
+            },
+            None => (),
+        }
+        self.buffer.push(0u8);
+        Ok(())
+    }
+}
+
+impl<'a, 's> RecordSerializer<'a, 's> {
+    fn new(
+        fields: &'s [RecordField],
+        lookup: &'s HashMap<String, usize>,
+        names: &'a Names<'s>,
+        sized_blocks: bool,
+        buffer: &'a mut Vec<u8>,
+    ) -> RecordSerializer<'a, 's> {
+        RecordSerializer {
+            fields,
+            lookup,
+            names,
+            sized_blocks,
+            start: buffer.len(),
+            buffer,
+            written: Vec::with_capacity(fields.len()),
+        }
+    }
+
+    /// Write `value` as the field at `position`.
+    fn write_field<T>(&mut self, position: usize, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let field = &self.fields[position];
+        let start = self.buffer.len() - self.start;
+        value
+            .serialize(SchemaSerializer::new(
+                &field.schema,
+                self.names,
+                self.sized_blocks,
+                self.buffer,
+            )).map_err(|e| e.within(&format!(".{}", field.name)))?;
+        self.written
+            .push((position, start, self.buffer.len() - self.start));
+        Ok(())
+    }
+
+    fn end_record(self) -> Result<(), Error> {
+        let in_order = self.written.len() == self.fields.len()
+            && self
+                .written
+                .iter()
+                .enumerate()
+                .all(|(i, (position, _, _))| i == *position);
+        if in_order {
+            return Ok(())
+        }
+
+        // Fields were written out of order, or are missing: they are moved in order, and missing
+        // fields are written with their default value.
+        let written = self.buffer.split_off(self.start);
+        let mut ranges = vec![None; self.fields.len()];
+        for (position, start, end) in self.written.iter().cloned() {
+            if ranges[position].replace((start, end)).is_some() {
+                let name = &self.fields[position].name;
+                return Err(Error::custom(format!("duplicate field {}", name)))
+            }
+        }
+        for (field, range) in self.fields.iter().zip(ranges) {
+            match (range, &field.default_value) {
+                (Some((start, end)), _) => self.buffer.extend_from_slice(&written[start..end]),
+                (None, Some(default)) => {
+                    let (names, sized_blocks) = (self.names, self.sized_blocks);
+                    encode_internal(default, &field.schema, names, sized_blocks, self.buffer)
+                        .map_err(|e| {
+                            Error::custom(e.message()).within(&format!(".{}", field.name))
+                        })?
+                },
+                (None, None) => return Err(Error::custom(format!("missing field {}", field.name))),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, 's> ser::Serializer for SchemaSerializer<'a, 's> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = BlockSerializer<'a, 's>;
+    type SerializeTuple = BlockSerializer<'a, 's>;
+    type SerializeTupleStruct = BlockSerializer<'a, 's>;
+    type SerializeTupleVariant = RecordSerializer<'a, 's>;
+    type SerializeMap = BlockSerializer<'a, 's>;
+    type SerializeStruct = RecordSerializer<'a, 's>;
+    type SerializeStructVariant = RecordSerializer<'a, 's>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.write_leaf("bool", |schema, buffer| match *schema {
+            Schema::Boolean => {
+                buffer.push(if v { 1u8 } else { 0u8 });
+                true
+            },
+            _ => false,
+        })
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer("i8", i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer("i16", i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer("i32", i64::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer("i64", v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer("u8", i64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer("u16", i64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer("u32", i64::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        if v <= i64::MAX as u64 {
+            self.serialize_integer("u64", v as i64)
+        } else {
+            Err(Error::custom("u64 is too large"))
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.write_leaf("f32", |schema, buffer| match *schema {
+            Schema::Float => {
+                buffer.extend_from_slice(&v.to_le_bytes());
+                true
+            },
+            Schema::Double => {
+                buffer.extend_from_slice(&f64::from(v).to_le_bytes());
+                true
+            },
+            _ => false,
+        })
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        self.write_leaf("f64", |schema, buffer| match *schema {
+            Schema::Double => {
+                buffer.extend_from_slice(&v.to_le_bytes());
+                true
+            },
+            _ => false,
+        })
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.serialize_str(&once(v).collect::<String>())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        self.write_leaf("str", |schema, buffer| match *schema {
+            Schema::String => {
+                encode_bytes(v, buffer);
+                true
+            },
+            Schema::Uuid if parse_uuid(v).is_some() => {
+                encode_bytes(v, buffer);
+                true
+            },
+            Schema::Enum { ref symbols, .. } => match symbols.iter().position(|s| s == v) {
+                Some(index) => {
+                    encode_int(index as i32, buffer);
+                    true
+                },
+                None => false,
+            },
+            _ => false,
+        })
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        self.write_leaf("bytes", |schema, buffer| match *schema {
+            Schema::Bytes => {
+                encode_bytes(v, buffer);
+                true
+            },
+            Schema::Fixed { size, .. } if size == v.len() => {
+                buffer.extend_from_slice(v);
+                true
+            },
+            _ => false,
+        })
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let schema = resolve(self.schema, self.names)?;
+        let union = match *schema {
+            Schema::Union(ref union) => union,
+            _ => return Err(mismatch("option", schema)),
+        };
+
+        // The value is written with the first variant other than `null` accepting it.
+        let start = self.buffer.len();
+        let mut error = None;
+        for (i, variant) in union.variants().iter().enumerate() {
+            if let Schema::Null = *variant {
+                continue
+            }
+            encode_long(i as i64, self.buffer);
+            let serializer =
+                SchemaSerializer::new(variant, self.names, self.sized_blocks, self.buffer);
+            match value.serialize(serializer) {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    self.buffer.truncate(start);
+                    error.get_or_insert(e);
+                },
+            }
+        }
+        Err(error.unwrap_or_else(|| mismatch("option", schema)))
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.write_leaf("unit", |schema, _| matches!(*schema, Schema::Null))
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        mut self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        // Within a union, a record without fields named after the variant is preferred to the
+        // symbol of an enum.
+        let start = self.buffer.len();
+        match self.find_enum_variant(variant, None)? {
+            Some(Schema::Record { fields, .. }) if fields.is_empty() => return Ok(()),
+            _ => self.buffer.truncate(start),
+        }
+        self.write_leaf(variant, |schema, buffer| match *schema {
+            Schema::Enum { ref symbols, .. } => match symbols.iter().position(|s| s == variant) {
+                Some(index) => {
+                    encode_int(index as i32, buffer);
+                    true
+                },
+                None => false,
+            },
+            _ => false,
+        })
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        mut self,
+        _: &'static str,
+        index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        match self.find_enum_variant(variant, Some(index))? {
+            Some(schema) => value.serialize(SchemaSerializer::new(
+                schema,
+                self.names,
+                self.sized_blocks,
+                self.buffer,
+            )),
+            None => value.serialize(self),
+        }
+    }
+
+    fn serialize_seq(mut self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        match self.find_variant(|schema| matches!(*schema, Schema::Array(_)))? {
+            Some(Schema::Array(items)) => Ok(BlockSerializer::new(
+                items,
+                self.names,
+                self.sized_blocks,
+                self.buffer,
+                len,
+            )),
+            _ => Err(mismatch("sequence", self.schema)),
+        }
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        index: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        self.serialize_record_variant(index, variant)
+    }
+
+    fn serialize_map(mut self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        match self.find_variant(|schema| matches!(*schema, Schema::Map(_)))? {
+            Some(Schema::Map(values)) => Ok(BlockSerializer::new(
+                values,
+                self.names,
+                self.sized_blocks,
+                self.buffer,
+                len,
+            )),
+            _ => Err(mismatch("map", self.schema)),
+        }
+    }
+
+    fn serialize_struct(
+        mut self,
+        name: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        // Within a union, the record named after the struct is preferred to the first record.
+        let named = |schema: &Schema| match *schema {
+            Schema::Record { name: ref record, .. } => record.name == name,
+            _ => false,
+        };
+        let record = match self.find_variant(named)? {
+            Some(record) => Some(record),
+            None => self.find_variant(|schema| matches!(*schema, Schema::Record { .. }))?,
+        };
+
+        match record {
+            Some(Schema::Record { fields, lookup, .. }) => Ok(RecordSerializer::new(
+                fields,
+                lookup,
+                self.names,
+                self.sized_blocks,
+                self.buffer,
+            )),
+            _ => Err(mismatch(&format!("struct {}", name), self.schema)),
+        }
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        index: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        self.serialize_record_variant(index, variant)
+    }
+}
+
+impl<'a, 's> ser::SerializeSeq for BlockSerializer<'a, 's> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let index = self.len;
+        self.serialize_item(value)
+            .map_err(|e| e.within(&format!("[{}]", index)))?;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.end_block()
+    }
+}
+
+impl<'a, 's> ser::SerializeTuple for BlockSerializer<'a, 's> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'a, 's> ser::SerializeTupleStruct for BlockSerializer<'a, 's> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'a, 's> ser::SerializeMap for BlockSerializer<'a, 's> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.key_start = self.buffer.len();
+        key.serialize(SchemaSerializer::new(
+            &STRING_SCHEMA,
+            self.names,
+            self.sized_blocks,
+            self.buffer,
+        )).map_err(|_| Error::custom("map key is not a string"))
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        if let Err(e) = self.serialize_item(value) {
+            // The key is only needed to report where the error happened.
+            let mut key = &self.buffer[self.key_start..];
+            let key = decode_internal(&STRING_SCHEMA, self.names, &mut key);
+            return Err(match key {
+                Ok(Value::String(key)) => e.within(&format!("{{{}}}", key)),
+                _ => e,
+            })
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.end_block()
+    }
+}
+
+impl<'a, 's> ser::SerializeStruct for RecordSerializer<'a, 's> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(
+        &mut self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        // Fields usually come in the order of the record, sparing a lookup.
+        let next = match self.written.last() {
+            Some(&(position, _, _)) => position + 1,
+            None => 0,
+        };
+        let position = match self.fields.get(next) {
+            Some(field) if field.name == name => next,
+            _ => *self
+                .lookup
+                .get(name)
+                .ok_or_else(|| Error::custom(format!("unknown field {}", name)))?,
+        };
+        self.write_field(position, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.end_record()
+    }
+}
+
+impl<'a, 's> ser::SerializeTupleVariant for RecordSerializer<'a, 's> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        // The fields of tuple variants are written in order.
+        let position = self.written.len();
+        if position >= self.fields.len() {
+            return Err(Error::custom(format!(
+                "more fields than the {} of the record",
+                self.fields.len()
+            )))
+        }
+        self.write_field(position, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.end_record()
+    }
+}
+
+impl<'a, 's> ser::SerializeStructVariant for RecordSerializer<'a, 's> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(
+        &mut self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeStruct::serialize_field(self, name, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.end_record()
+    }
+}
+
+/// Follow `schema` if it is a reference to a named type, or annotated by a logical type.
+fn resolve<'s>(schema: &'s Schema, names: &Names<'s>) -> Result<&'s Schema, Error> {
+    schema.resolve_ref(names).ok_or_else(|| {
+        Error::custom(format!("unresolved schema reference {}", describe(schema)))
+    })
+}
+
+fn mismatch(kind: &str, schema: &Schema) -> Error {
+    Error::custom(format!("{} cannot be serialized as {}", kind, describe(schema)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encode::encode;
+    use ser::to_value_with_schema;
+
+    fn serialize<S: Serialize>(schema: &Schema, value: S) -> Result<Vec<u8>, String> {
+        let names = schema.names();
+        let mut buffer = Vec::new();
+        value
+            .serialize(SchemaSerializer::new(schema, &names, false, &mut buffer))
+            .map_err(|e| e.to_string())?;
+        Ok(buffer)
+    }
+
+    /// Encode `value` converted to a `Value` with `schema`, as `Writer::append_ser` used to.
+    fn encode_value<S: Serialize>(schema: &Schema, value: S) -> Vec<u8> {
+        let value = to_value_with_schema(schema, value).unwrap();
+        assert!(value.validate(schema));
+        let mut buffer = Vec::new();
+        encode(&value, schema, &mut buffer).unwrap();
+        buffer
+    }
+
+    #[derive(Serialize)]
+    enum Suit {
+        Hearts,
+        Spades,
+    }
+
+    #[derive(Serialize)]
+    struct Inner {
+        a: i64,
+        b: Option<String>,
+    }
+
+    #[derive(Serialize)]
+    struct Outer {
+        flag: bool,
+        count: i32,
+        ratio: f32,
+        score: f64,
+        name: String,
+        suit: Suit,
+        inner: Inner,
+        items: Vec<Inner>,
+        tags: HashMap<String, i64>,
+        nothing: (),
+    }
+
+    const OUTER_SCHEMA: &str = r#"
+        {
+            "type": "record",
+            "name": "Outer",
+            "fields": [
+                {"name": "flag", "type": "boolean"},
+                {"name": "count", "type": "int"},
+                {"name": "ratio", "type": "float"},
+                {"name": "score", "type": "double"},
+                {"name": "name", "type": "string"},
+                {"name": "suit", "type": {
+                    "type": "enum", "name": "Suit", "symbols": ["Hearts", "Spades"]
+                }},
+                {"name": "inner", "type": {
+                    "type": "record",
+                    "name": "Inner",
+                    "fields": [
+                        {"name": "a", "type": "long"},
+                        {"name": "b", "type": ["null", "string"]}
+                    ]
+                }},
+                {"name": "items", "type": {"type": "array", "items": "Inner"}},
+                {"name": "tags", "type": {"type": "map", "values": "long"}},
+                {"name": "nothing", "type": "null"}
+            ]
+        }
+    "#;
+
+    fn outer() -> Outer {
+        let mut tags = HashMap::new();
+        tags.insert("tag".to_owned(), 7);
+        Outer {
+            flag: true,
+            count: -3,
+            ratio: 0.5,
+            score: 1.25,
+            name: "foo".to_owned(),
+            suit: Suit::Spades,
+            inner: Inner {
+                a: 42,
+                b: Some("bar".to_owned()),
+            },
+            items: vec![
+                Inner { a: 1, b: None },
+                Inner {
+                    a: 2,
+                    b: Some("baz".to_owned()),
+                },
+            ],
+            tags,
+            nothing: (),
+        }
+    }
+
+    #[test]
+    fn test_serialize_as_encoded_value() {
+        let schema = Schema::parse_str(OUTER_SCHEMA).unwrap();
+        assert_eq!(
+            serialize(&schema, outer()).unwrap(),
+            encode_value(&schema, outer())
+        );
+
+        let empty = Outer {
+            suit: Suit::Hearts,
+            items: vec![],
+            tags: HashMap::new(),
+            ..outer()
+        };
+        let expected = encode_value(&schema, &empty);
+        assert_eq!(serialize(&schema, &empty).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_serialize_options_and_unions() {
+        let schema = Schema::parse_str(r#"["string", "null"]"#).unwrap();
+        assert_eq!(serialize(&schema, None::<String>).unwrap(), vec![2]);
+        assert_eq!(serialize(&schema, Some("a")).unwrap(), vec![0, 2, b'a']);
+        // A value which is not optional is written with the first variant accepting it.
+        assert_eq!(serialize(&schema, "a").unwrap(), vec![0, 2, b'a']);
+
+        let schema = Schema::parse_str(r#"["null", "int", "long"]"#).unwrap();
+        assert_eq!(serialize(&schema, Some(1i64)).unwrap(), vec![2, 2]);
+        assert_eq!(serialize(&schema, Some(1i64 << 40)).unwrap()[0], 4);
+
+        let schema = Schema::parse_str(
+            r#"[
+                "null",
+                {"type": "record", "name": "Other", "fields": [{"name": "a", "type": "long"}]},
+                {"type": "record", "name": "Inner", "fields": [
+                    {"name": "a", "type": "long"},
+                    {"name": "b", "type": ["null", "string"]}
+                ]}
+            ]"#,
+        ).unwrap();
+        let inner = Inner { a: 1, b: None };
+        assert_eq!(serialize(&schema, Some(&inner)).unwrap(), vec![4, 2, 0]);
+
+        assert_eq!(
+            serialize(&Schema::Long, Some(1i64)),
+            Err("option cannot be serialized as long".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_serialize_struct_field_order() {
+        #[derive(Serialize)]
+        struct Reordered {
+            b: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            c: Option<i64>,
+            a: i64,
+        }
+
+        let schema = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "Reordered",
+                "fields": [
+                    {"name": "a", "type": "long"},
+                    {"name": "b", "type": "string"},
+                    {"name": "c", "type": ["null", "long"], "default": null}
+                ]
+            }
+        "#,
+        ).unwrap();
+
+        let value = Reordered {
+            b: "foo".to_owned(),
+            c: None,
+            a: 27,
+        };
+        assert_eq!(
+            serialize(&schema, &value).unwrap(),
+            vec![54, 6, b'f', b'o', b'o', 0]
+        );
+        let value = Reordered {
+            c: Some(1),
+            ..value
+        };
+        assert_eq!(
+            serialize(&schema, &value).unwrap(),
+            vec![54, 6, b'f', b'o', b'o', 2, 2]
+        );
+    }
+
+    #[test]
+    fn test_serialize_errors() {
+        #[derive(Serialize)]
+        struct Wrong {
+            a: String,
+        }
+        #[derive(Serialize)]
+        struct Items {
+            items: Vec<Wrong>,
+        }
+        let schema = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "Items",
+                "fields": [
+                    {"name": "items", "type": {"type": "array", "items": {
+                        "type": "record",
+                        "name": "Wrong",
+                        "fields": [
+                            {"name": "a", "type": "long"},
+                            {"name": "b", "type": "long"}
+                        ]
+                    }}}
+                ]
+            }
+        "#,
+        ).unwrap();
+        let value = Items {
+            items: vec![Wrong { a: "x".to_owned() }],
+        };
+        assert_eq!(
+            serialize(&schema, &value),
+            Err(".items[0].a: str cannot be serialized as long".to_owned())
+        );
+
+        #[derive(Serialize)]
+        struct Missing {
+            a: i64,
+        }
+        let wrong = Schema::parse_str(
+            r#"{"type": "record", "name": "Wrong", "fields": [
+                {"name": "a", "type": "long"},
+                {"name": "b", "type": "long"}
+            ]}"#,
+        ).unwrap();
+        assert_eq!(
+            serialize(&wrong, Missing { a: 1 }),
+            Err("missing field b".to_owned())
+        );
+
+        #[derive(Serialize)]
+        struct Unknown {
+            a: i64,
+            b: i64,
+            c: i64,
+        }
+        assert_eq!(
+            serialize(&wrong, Unknown { a: 1, b: 2, c: 3 }),
+            Err("unknown field c".to_owned())
+        );
+
+        assert_eq!(
+            serialize(&Schema::Int, 1i64 << 40),
+            Err("i64 cannot be serialized as int".to_owned())
+        );
+        assert_eq!(
+            serialize(&Schema::Uuid, "not a uuid"),
+            Err("str cannot be serialized as uuid".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_serialize_enums() {
+        #[derive(Serialize)]
+        enum Shape {
+            Circle { radius: f64 },
+            Rectangle(f64, f64),
+            Named(String),
+            Empty,
+        }
+
+        // Records are found by name, other variants by position.
+        let schema = Schema::parse_str(
+            r#"
+            [
+                {"type": "record", "name": "Rectangle", "fields": [
+                    {"name": "width", "type": "double"},
+                    {"name": "height", "type": "double"}
+                ]},
+                {"type": "record", "name": "Circle", "fields": [
+                    {"name": "radius", "type": "double"}
+                ]},
+                "string",
+                {"type": "record", "name": "Empty", "fields": []}
+            ]
+        "#,
+        ).unwrap();
+        let expected = |value: Value| {
+            let mut buffer = Vec::new();
+            encode(&value, &schema, &mut buffer).unwrap();
+            buffer
+        };
+
+        assert_eq!(
+            serialize(&schema, Shape::Circle { radius: 1.0 }).unwrap(),
+            expected(Value::Union(
+                1,
+                Box::new(Value::Record(vec![("radius".to_owned(), Value::Double(1.0))]))
+            ))
+        );
+        assert_eq!(
+            serialize(&schema, Shape::Rectangle(2.0, 3.0)).unwrap(),
+            expected(Value::Union(
+                0,
+                Box::new(Value::Record(vec![
+                    ("width".to_owned(), Value::Double(2.0)),
+                    ("height".to_owned(), Value::Double(3.0)),
+                ]))
+            ))
+        );
+        assert_eq!(
+            serialize(&schema, Shape::Named("a".to_owned())).unwrap(),
+            vec![4, 2, b'a']
+        );
+        assert_eq!(serialize(&schema, Shape::Empty).unwrap(), vec![6]);
+        // Values converted with the schema hold the same variants and fields.
+        let shapes = vec![
+            Shape::Circle { radius: 1.0 },
+            Shape::Rectangle(2.0, 3.0),
+            Shape::Named("a".to_owned()),
+            Shape::Empty,
+        ];
+        for shape in &shapes {
+            assert_eq!(serialize(&schema, shape).unwrap(), encode_value(&schema, shape));
+        }
+        let symbols = r#"{"type": "enum", "name": "Shape", "symbols": ["Empty"]}"#;
+        let symbols = Schema::parse_str(symbols).unwrap();
+        assert_eq!(serialize(&symbols, Shape::Empty).unwrap(), vec![0]);
+
+        assert_eq!(
+            serialize(&Schema::Long, Shape::Rectangle(2.0, 3.0)),
+            Err("enum variant Rectangle cannot be serialized as long".to_owned())
+        );
+        let circle = Schema::parse_str(
+            r#"{"type": "record", "name": "Circle", "fields": [
+                {"name": "radius", "type": "double"}
+            ]}"#,
+        ).unwrap();
+        assert_eq!(
+            serialize(&circle, Shape::Rectangle(2.0, 3.0)),
+            Err("more fields than the 1 of the record".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_serialize_sized_blocks() {
+        let schema = Schema::parse_str(r#"{"type": "array", "items": "long"}"#).unwrap();
+        let names = schema.names();
+        let mut buffer = Vec::new();
+        vec![1i64, 2]
+            .serialize(SchemaSerializer::new(&schema, &names, true, &mut buffer))
+            .unwrap();
+        assert_eq!(buffer, vec![3, 4, 2, 4, 0]);
+    }
+}
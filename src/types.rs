@@ -1,14 +1,20 @@
 //! Logic handling the intermediate representation of Avro values.
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
 use std::hash::BuildHasher;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use failure::Error;
 use serde_json::Value as JsonValue;
 
-use schema::{RecordField, Schema, SchemaKind, UnionSchema};
+use decimal::Decimal;
+use schema::compatibility::{is_promotable, LOGICAL_CONVERSIONS};
+use schema::{describe, Name, Names, RecordField, Schema, SchemaKind, UnionSchema};
+use util::{format_uuid, parse_uuid};
 
 /// Describes errors happened while performing schema resolution on Avro data.
-#[derive(Fail, Debug)]
+#[derive(Fail, Debug, Clone)]
 #[fail(display = "Decoding error: {}", _0)]
 pub struct SchemaResolutionError(String);
 
@@ -21,6 +27,34 @@ impl SchemaResolutionError {
     }
 }
 
+/// A part of a value which does not match the schema it is validated against.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationMismatch {
+    /// Where the mismatch lies within the value: `.` for the value itself, `.address.zip` for the
+    /// field `zip` of the field `address`, `[2]` and `{key}` for an item of an array and a value of
+    /// a map.
+    pub path: String,
+    /// The schema expected there, such as `long` or `record User`.
+    pub expected: String,
+    /// The kind of value found there, such as `string` or `record`.
+    pub actual: String,
+    /// Why the value does not match when its kind does, such as the size of a `fixed`.
+    pub detail: Option<String>,
+}
+
+impl fmt::Display for ValidationMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: expected {}, found {}", self.path, self.expected, self.actual)?;
+        if let Some(ref detail) = self.detail {
+            write!(f, " ({})", detail)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) const MILLIS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+pub(crate) const MICROS_PER_DAY: i64 = MILLIS_PER_DAY * 1000;
+
 /// Represents any valid Avro value
 /// More information about Avro values can be found in the
 /// [Avro Specification](https://avro.apache.org/docs/current/spec.html#schemas)
@@ -53,7 +87,46 @@ pub enum Value {
     /// reading values.
     Enum(i32, String),
     /// An `union` Avro value.
-    Union(Box<Value>),
+    ///
+    /// An Union is represented by the position of its schema within the union, and by the value
+    /// itself. See `Value::union` to find the position automatically.
+    Union(u32, Box<Value>),
+    /// A `decimal` Avro value.
+    ///
+    /// The value holds the unscaled value of the decimal, its scale being defined by the schema.
+    Decimal(Decimal),
+    /// A `date` Avro value.
+    ///
+    /// The number of days since the unix epoch.
+    Date(i32),
+    /// A `time-millis` Avro value.
+    ///
+    /// The number of milliseconds after midnight.
+    TimeMillis(i32),
+    /// A `time-micros` Avro value.
+    ///
+    /// The number of microseconds after midnight.
+    TimeMicros(i64),
+    /// A `timestamp-millis` Avro value.
+    ///
+    /// The number of milliseconds since the unix epoch, in UTC.
+    TimestampMillis(i64),
+    /// A `timestamp-micros` Avro value.
+    ///
+    /// The number of microseconds since the unix epoch, in UTC.
+    TimestampMicros(i64),
+    /// A `local-timestamp-millis` Avro value.
+    ///
+    /// The number of milliseconds since the unix epoch, in an unspecified local timezone.
+    LocalTimestampMillis(i64),
+    /// A `local-timestamp-micros` Avro value.
+    ///
+    /// The number of microseconds since the unix epoch, in an unspecified local timezone.
+    LocalTimestampMicros(i64),
+    /// A `uuid` Avro value.
+    Uuid([u8; 16]),
+    /// A `duration` Avro value.
+    Duration { months: u32, days: u32, millis: u32 },
     /// An `array` Avro value.
     Array(Vec<Value>),
     /// A `map` Avro value.
@@ -72,6 +145,17 @@ pub enum Value {
 pub trait ToAvro {
     /// Transforms this value into an Avro-compatible [Value](enum.Value.html).
     fn avro(self) -> Value;
+
+    /// Transforms this value into an Avro-compatible [Value](enum.Value.html) following `schema`,
+    /// which gives the variant of the union holding an option.
+    ///
+    /// Defaults to `avro`, other values not depending on the schema.
+    fn avro_with_schema(self, _schema: &Schema) -> Value
+    where
+        Self: Sized,
+    {
+        self.avro()
+    }
 }
 
 macro_rules! to_avro(
@@ -115,16 +199,24 @@ impl<'a> ToAvro for &'a [u8] {
     }
 }
 
+/// Options are represented as unions whose first variant is `null`, like `["null", "string"]`.
+/// Given the schema of the union, they are represented as the first variant they match instead.
 impl<T> ToAvro for Option<T>
 where
     T: ToAvro,
 {
     fn avro(self) -> Value {
-        let v = match self {
-            Some(v) => T::avro(v),
-            None => Value::Null,
-        };
-        Value::Union(Box::new(v))
+        match self {
+            Some(v) => Value::Union(1, Box::new(T::avro(v))),
+            None => Value::Union(0, Box::new(Value::Null)),
+        }
+    }
+
+    fn avro_with_schema(self, schema: &Schema) -> Value {
+        let position = if self.is_some() { 1 } else { 0 };
+        let value = self.map_or(Value::Null, T::avro);
+        Value::union_in(schema, value, &schema.names())
+            .unwrap_or_else(|value| Value::Union(position, Box::new(value)))
     }
 }
 
@@ -154,6 +246,12 @@ where
     }
 }
 
+impl ToAvro for Decimal {
+    fn avro(self) -> Value {
+        Value::Decimal(self)
+    }
+}
+
 impl ToAvro for Value {
     fn avro(self) -> Value {
         self
@@ -177,6 +275,7 @@ pub struct Record<'a> {
     /// Ordered according to the fields in the schema given to create this
     /// `Record` object. Any unset field defaults to `Value::Null`.
     pub fields: Vec<(String, Value)>,
+    schema_fields: &'a [RecordField],
     schema_lookup: &'a HashMap<String, usize>,
 }
 
@@ -198,6 +297,7 @@ impl<'a> Record<'a> {
 
                 Some(Record {
                     fields,
+                    schema_fields,
                     schema_lookup,
                 })
             },
@@ -209,13 +309,14 @@ impl<'a> Record<'a> {
     /// `Record` for a given `field` name.
     ///
     /// **NOTE** Only ensure that the field name is present in the `Schema` given when creating
-    /// this `Record`. Does not perform any schema validation.
+    /// this `Record`, whose field schema is used to convert options. Does not perform any schema
+    /// validation.
     pub fn put<V>(&mut self, field: &str, value: V)
     where
         V: ToAvro,
     {
         if let Some(&position) = self.schema_lookup.get(field) {
-            self.fields[position].1 = value.avro()
+            self.fields[position].1 = value.avro_with_schema(&self.schema_fields[position].schema)
         }
     }
 }
@@ -249,12 +350,81 @@ impl ToAvro for JsonValue {
 }
 
 impl Value {
+    /// Create a `Value::TimestampMillis` from a `SystemTime`.
+    ///
+    /// Sub-millisecond precision is truncated towards the past.
+    pub fn timestamp_millis(time: SystemTime) -> Value {
+        Value::TimestampMillis(system_time_to_nanos(time).div_euclid(1_000_000) as i64)
+    }
+
+    /// Create a `Value::TimestampMicros` from a `SystemTime`.
+    ///
+    /// Sub-microsecond precision is truncated towards the past.
+    pub fn timestamp_micros(time: SystemTime) -> Value {
+        Value::TimestampMicros(system_time_to_nanos(time).div_euclid(1_000) as i64)
+    }
+
+    /// Convert a `Value::TimestampMillis` or a `Value::TimestampMicros` into a `SystemTime`.
+    ///
+    /// Returns `None` for any other value, including local timestamps which do not represent an
+    /// instant, or if the timestamp cannot be represented by a `SystemTime`.
+    pub fn as_system_time(&self) -> Option<SystemTime> {
+        let micros = match *self {
+            Value::TimestampMillis(t) => i128::from(t) * 1000,
+            Value::TimestampMicros(t) => i128::from(t),
+            _ => return None,
+        };
+
+        let offset = Duration::from_micros(u64::try_from(micros.unsigned_abs()).ok()?);
+        if micros >= 0 {
+            UNIX_EPOCH.checked_add(offset)
+        } else {
+            UNIX_EPOCH.checked_sub(offset)
+        }
+    }
+
+    /// Create a `Value::Union` holding `value` as the first variant of the union `schema` it
+    /// matches.
+    ///
+    /// Returns `None` if `schema` is not a union, or if `value` matches none of its variants.
+    pub fn union(schema: &Schema, value: Value) -> Option<Value> {
+        Value::union_in(schema, value, &schema.names()).ok()
+    }
+
+    /// Like `Value::union`, with the named types `names` which variants can refer to, giving back
+    /// `value` when it cannot be held by `schema`.
+    pub(crate) fn union_in(schema: &Schema, value: Value, names: &Names) -> Result<Value, Value> {
+        match *schema {
+            Schema::Union(ref inner) => match inner.find_schema_in(&value, names) {
+                Some((i, _)) => Ok(Value::Union(i as u32, Box::new(value))),
+                None => Err(value),
+            },
+            _ => Err(value),
+        }
+    }
+
     /// Validate the value against the given [Schema](../schema/enum.Schema.html).
     ///
     /// See the [Avro specification](https://avro.apache.org/docs/current/spec.html)
     /// for the full set of rules of schema validation.
+    ///
+    /// The named types of the `Schema` are collected on every call. A
+    /// [`Writer`](../struct.Writer.html) collects them once to validate all the values it writes.
     pub fn validate(&self, schema: &Schema) -> bool {
+        self.validate_internal(schema, &schema.names())
+    }
+
+    /// Validate the value against the given `Schema`, following references to named types
+    /// through `names`.
+    ///
+    /// `names` are the named types of the root `Schema`, as given by `Schema::names`, which
+    /// callers validating several values with the same `Schema` should collect once.
+    pub(crate) fn validate_internal(&self, schema: &Schema, names: &Names) -> bool {
         match (self, schema) {
+            (_, &Schema::Ref { .. }) | (_, &Schema::Annotated { .. }) => schema
+                .resolve_ref(names)
+                .map(|schema| self.validate_internal(schema, names))
+                .unwrap_or(false),
             (&Value::Null, &Schema::Null) => true,
             (&Value::Boolean(_), &Schema::Boolean) => true,
             (&Value::Int(_), &Schema::Int) => true,
@@ -264,25 +434,43 @@ impl Value {
             (&Value::Bytes(_), &Schema::Bytes) => true,
             (&Value::String(_), &Schema::String) => true,
             (&Value::Fixed(n, _), &Schema::Fixed { size, .. }) => n == size,
+            (&Value::Decimal(ref d), &Schema::Decimal { precision, ref inner, .. }) => {
+                d.num_digits() <= precision && match inner.resolve_ref(names) {
+                    Some(&Schema::Fixed { size, .. }) => d.as_bytes().len() <= size,
+                    _ => true,
+                }
+            },
+            (&Value::Date(_), &Schema::Date) => true,
+            (&Value::TimeMillis(t), &Schema::TimeMillis) => (0..MILLIS_PER_DAY).contains(&t.into()),
+            (&Value::TimeMicros(t), &Schema::TimeMicros) => (0..MICROS_PER_DAY).contains(&t),
+            (&Value::TimestampMillis(_), &Schema::TimestampMillis) => true,
+            (&Value::TimestampMicros(_), &Schema::TimestampMicros) => true,
+            (&Value::LocalTimestampMillis(_), &Schema::LocalTimestampMillis) => true,
+            (&Value::LocalTimestampMicros(_), &Schema::LocalTimestampMicros) => true,
+            (&Value::Uuid(_), &Schema::Uuid) => true,
+            (&Value::Duration { .. }, &Schema::Duration { .. }) => true,
+            (&Value::String(ref s), &Schema::Uuid) => parse_uuid(s).is_some(),
             (&Value::String(ref s), &Schema::Enum { ref symbols, .. }) => symbols.contains(s),
             (&Value::Enum(i, ref s), &Schema::Enum { ref symbols, .. }) => symbols
                 .get(i as usize)
                 .map(|ref symbol| symbol == &s)
                 .unwrap_or(false),
             // (&Value::Union(None), &Schema::Union(_)) => true,
-            (&Value::Union(ref value), &Schema::Union(ref inner)) => {
-                inner.find_schema(value).is_some()
-            },
+            (&Value::Union(i, ref value), &Schema::Union(ref inner)) => inner
+                .variants()
+                .get(i as usize)
+                .map(|schema| value.validate_internal(schema, names))
+                .unwrap_or(false),
             (&Value::Array(ref items), &Schema::Array(ref inner)) => {
-                items.iter().all(|item| item.validate(inner))
-            },
-            (&Value::Map(ref items), &Schema::Map(ref inner)) => {
-                items.iter().all(|(_, value)| value.validate(inner))
+                items.iter().all(|item| item.validate_internal(inner, names))
             },
+            (&Value::Map(ref items), &Schema::Map(ref inner)) => items
+                .iter()
+                .all(|(_, value)| value.validate_internal(inner, names)),
             (&Value::Record(ref record_fields), &Schema::Record { ref fields, .. }) => {
                 fields.len() == record_fields.len() && fields.iter().zip(record_fields.iter()).all(
                     |(field, &(ref name, ref value))| {
-                        field.name == *name && value.validate(&field.schema)
+                        field.name == *name && value.validate_internal(&field.schema, names)
                     },
                 )
             },
@@ -290,20 +478,153 @@ impl Value {
         }
     }
 
+    /// Validate the value against the given [Schema](../schema/enum.Schema.html), reporting every
+    /// part of the value which does not match it.
+    ///
+    /// The value is valid if and only if [`validate`](#method.validate) returns `true`.
+    pub fn validate_detailed(&self, schema: &Schema) -> Result<(), Vec<ValidationMismatch>> {
+        self.validate_detailed_internal(schema, &schema.names())
+    }
+
+    /// Validate the value against the given `Schema` like `validate_detailed`, following
+    /// references to named types through `names`.
+    pub(crate) fn validate_detailed_internal(
+        &self,
+        schema: &Schema,
+        names: &Names,
+    ) -> Result<(), Vec<ValidationMismatch>> {
+        let mut mismatches = Vec::new();
+        self.find_mismatches(schema, names, "", &mut mismatches);
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(mismatches)
+        }
+    }
+
+    /// Add to `mismatches` every part of the value, found at `path`, which does not match
+    /// `schema`.
+    fn find_mismatches(
+        &self,
+        schema: &Schema,
+        names: &Names,
+        path: &str,
+        mismatches: &mut Vec<ValidationMismatch>,
+    ) {
+        let detail = match (self, schema) {
+            (_, Schema::Ref { .. }) | (_, Schema::Annotated { .. }) => {
+                match schema.resolve_ref(names) {
+                    Some(schema) => return self.find_mismatches(schema, names, path, mismatches),
+                    None => Some("unresolved schema reference".to_owned()),
+                }
+            },
+            (Value::Union(i, value), Schema::Union(inner)) => {
+                match inner.variants().get(*i as usize) {
+                    Some(schema) => return value.find_mismatches(schema, names, path, mismatches),
+                    None => Some(format!("no variant at position {}", i)),
+                }
+            },
+            (Value::Array(items), Schema::Array(inner)) => {
+                for (i, item) in items.iter().enumerate() {
+                    item.find_mismatches(inner, names, &format!("{}[{}]", path, i), mismatches);
+                }
+                return
+            },
+            (Value::Map(items), Schema::Map(inner)) => {
+                let mut keys = items.keys().collect::<Vec<_>>();
+                keys.sort();
+                for key in keys {
+                    let key_path = format!("{}{{{}}}", path, key);
+                    items[key].find_mismatches(inner, names, &key_path, mismatches);
+                }
+                return
+            },
+            (Value::Record(record_fields), Schema::Record { fields, .. }) => {
+                let mut details = Vec::new();
+                for (i, field) in fields.iter().enumerate() {
+                    match record_fields.get(i) {
+                        Some((name, value)) if *name == field.name => {
+                            let field_path = format!("{}.{}", path, field.name);
+                            value.find_mismatches(&field.schema, names, &field_path, mismatches)
+                        },
+                        Some((name, _)) => {
+                            details.push(format!("field {} instead of {}", name, field.name))
+                        },
+                        None => details.push(format!("missing field {}", field.name)),
+                    }
+                }
+                for (name, _) in record_fields.iter().skip(fields.len()) {
+                    details.push(format!("unexpected field {}", name));
+                }
+                if details.is_empty() {
+                    return
+                }
+                Some(details.join(", "))
+            },
+            _ if self.validate_internal(schema, names) => return,
+            (Value::Fixed(n, _), Schema::Fixed { size, .. }) => {
+                Some(format!("size {} instead of {}", n, size))
+            },
+            (Value::Decimal(_), Schema::Decimal { .. }) => {
+                Some("too many digits for the precision or the size".to_owned())
+            },
+            (Value::TimeMillis(_), Schema::TimeMillis)
+            | (Value::TimeMicros(_), Schema::TimeMicros) => {
+                Some("not within a day".to_owned())
+            },
+            (Value::String(_), Schema::Uuid) => Some("not a UUID".to_owned()),
+            (Value::String(s), Schema::Enum { .. }) => Some(format!("unknown symbol {}", s)),
+            (Value::Enum(i, s), Schema::Enum { symbols, .. }) => {
+                match symbols.iter().position(|symbol| symbol == s) {
+                    Some(position) => {
+                        Some(format!("symbol {} at position {} instead of {}", s, i, position))
+                    },
+                    None => Some(format!("unknown symbol {}", s)),
+                }
+            },
+            _ => None,
+        };
+
+        mismatches.push(ValidationMismatch {
+            path: if path.is_empty() {
+                ".".to_owned()
+            } else {
+                path.to_owned()
+            },
+            expected: describe(schema),
+            actual: describe_value(self).to_owned(),
+            detail,
+        });
+    }
+
     /// Attempt to perform schema resolution on the value, with the given
     /// [Schema](../schema/enum.Schema.html).
     ///
     /// See [Schema Resolution](https://avro.apache.org/docs/current/spec.html#Schema+Resolution)
     /// in the Avro specification for the full set of rules of schema
     /// resolution.
-    pub fn resolve(mut self, schema: &Schema) -> Result<Self, Error> {
+    pub fn resolve(self, schema: &Schema) -> Result<Self, Error> {
+        self.resolve_internal(schema, &schema.names())
+    }
+
+    /// Perform schema resolution on the value, following references to named types of the reader
+    /// `Schema` through `names`.
+    pub(crate) fn resolve_internal(
+        mut self,
+        schema: &Schema,
+        names: &Names,
+    ) -> Result<Self, Error> {
+        let schema = schema.resolve_ref(names).ok_or_else(|| {
+            SchemaResolutionError::new(format!("Unresolved schema reference: {:?}", schema))
+        })?;
+
         // Check if this schema is a union, and if the reader schema is not.
         if SchemaKind::from(&self) == SchemaKind::Union
             && SchemaKind::from(schema) != SchemaKind::Union
         {
             // Pull out the Union, and attempt to resolve against it.
             let v = match self {
-                Value::Union(b) => *b,
+                Value::Union(_, b) => *b,
                 _ => unreachable!(),
             };
             self = v;
@@ -318,11 +639,160 @@ impl Value {
             Schema::Bytes => self.resolve_bytes(),
             Schema::String => self.resolve_string(),
             Schema::Fixed { size, .. } => self.resolve_fixed(size),
-            Schema::Union(ref inner) => self.resolve_union(inner),
+            Schema::Decimal {
+                precision,
+                ref inner,
+                ..
+            } => self.resolve_decimal(precision, inner),
+            Schema::Date => self.resolve_date(),
+            Schema::TimeMillis => self.resolve_time_millis(),
+            Schema::TimeMicros => self.resolve_time_micros(),
+            Schema::TimestampMillis => self.resolve_timestamp_millis(),
+            Schema::TimestampMicros => self.resolve_timestamp_micros(),
+            Schema::LocalTimestampMillis => self.resolve_local_timestamp_millis(),
+            Schema::LocalTimestampMicros => self.resolve_local_timestamp_micros(),
+            Schema::Uuid => self.resolve_uuid(),
+            Schema::Duration { .. } => self.resolve_duration(),
+            Schema::Union(ref inner) => self.resolve_union(inner, names),
             Schema::Enum { ref symbols, .. } => self.resolve_enum(symbols),
-            Schema::Array(ref inner) => self.resolve_array(inner),
-            Schema::Map(ref inner) => self.resolve_map(inner),
-            Schema::Record { ref fields, .. } => self.resolve_record(fields),
+            Schema::Array(ref inner) => self.resolve_array(inner, names),
+            Schema::Map(ref inner) => self.resolve_map(inner, names),
+            Schema::Record { ref fields, .. } => self.resolve_record(fields, names),
+            Schema::Ref { .. } | Schema::Annotated { .. } => Err(SchemaResolutionError::new(
+                format!("Unresolved schema reference: {:?}", schema),
+            ).into()),
+        }
+    }
+
+    /// Attempt to perform schema resolution on a value decoded with the `writer` schema, with the
+    /// `reader` schema.
+    ///
+    /// Unlike `resolve`, which only knows about the shape of the value, this also requires named
+    /// types (`record`, `enum` and `fixed`) of the writer to match those of the reader by name or
+    /// aliases, and matches record fields by name or aliases through the writer schema, as
+    /// described by the Avro specification.
+    pub fn resolve_with_writer(self, writer: &Schema, reader: &Schema) -> Result<Self, Error> {
+        self.resolve_from(writer, &writer.names(), reader, &reader.names())
+    }
+
+    /// Perform schema resolution on a value decoded with the `writer` schema, following references
+    /// to named types through `writer_names` and `reader_names`.
+    pub(crate) fn resolve_from(
+        self,
+        writer: &Schema,
+        writer_names: &Names,
+        reader: &Schema,
+        reader_names: &Names,
+    ) -> Result<Self, Error> {
+        let writer = writer.resolve_ref(writer_names).ok_or_else(|| {
+            SchemaResolutionError::new(format!("Unresolved schema reference: {:?}", writer))
+        })?;
+        let reader = reader.resolve_ref(reader_names).ok_or_else(|| {
+            SchemaResolutionError::new(format!("Unresolved schema reference: {:?}", reader))
+        })?;
+
+        match (writer, reader) {
+            (Schema::Union(writer_union), _) => {
+                let (written, value) = match self {
+                    Value::Union(i, value) => (writer_union.variants().get(i as usize), *value),
+                    value => (
+                        writer_union
+                            .find_schema_in(&value, writer_names)
+                            .map(|(_, schema)| schema),
+                        value,
+                    ),
+                };
+                let written = written.ok_or_else(|| {
+                    SchemaResolutionError::new("Could not find written type in union")
+                })?;
+                value.resolve_from(written, writer_names, reader, reader_names)
+            },
+            (_, Schema::Union(reader_union)) => match writer.name() {
+                Some(writer_name) => {
+                    let (i, branch) = find_named_variant(writer, reader_union, reader_names)
+                        .ok_or_else(|| {
+                            SchemaResolutionError::new(format!(
+                                "Could not find type matching {} in union",
+                                writer_name.fullname(None)
+                            ))
+                        })?;
+                    self.resolve_from(writer, writer_names, branch, reader_names)
+                        .map(|value| Value::Union(i as u32, Box::new(value)))
+                },
+                None => self.resolve_internal(reader, reader_names),
+            },
+            (
+                Schema::Record {
+                    name: writer_name,
+                    fields: writer_fields,
+                    ..
+                },
+                Schema::Record {
+                    name: reader_name,
+                    fields: reader_fields,
+                    ..
+                },
+            ) => {
+                check_names(reader_name, writer_name)?;
+                self.resolve_record_from(writer_fields, writer_names, reader_fields, reader_names)
+            },
+            (
+                Schema::Enum {
+                    name: writer_name, ..
+                },
+                Schema::Enum {
+                    name: reader_name, ..
+                },
+            )
+            | (
+                Schema::Fixed {
+                    name: writer_name, ..
+                },
+                Schema::Fixed {
+                    name: reader_name, ..
+                },
+            ) => {
+                check_names(reader_name, writer_name)?;
+                self.resolve_internal(reader, reader_names)
+            },
+            (Schema::Array(writer_items), Schema::Array(reader_items)) => match self {
+                Value::Array(items) => Ok(Value::Array(
+                    items
+                        .into_iter()
+                        .map(|item| {
+                            item.resolve_from(
+                                writer_items,
+                                writer_names,
+                                reader_items,
+                                reader_names,
+                            )
+                        }).collect::<Result<_, _>>()?,
+                )),
+                other => Err(SchemaResolutionError::new(format!(
+                    "Array expected, got {:?}",
+                    other
+                )).into()),
+            },
+            (Schema::Map(writer_values), Schema::Map(reader_values)) => match self {
+                Value::Map(items) => Ok(Value::Map(
+                    items
+                        .into_iter()
+                        .map(|(key, value)| {
+                            value
+                                .resolve_from(
+                                    writer_values,
+                                    writer_names,
+                                    reader_values,
+                                    reader_names,
+                                ).map(|value| (key, value))
+                        }).collect::<Result<_, _>>()?,
+                )),
+                other => Err(SchemaResolutionError::new(format!(
+                    "Map expected, got {:?}",
+                    other
+                )).into()),
+            },
+            _ => self.resolve_internal(reader, reader_names),
         }
     }
 
@@ -346,7 +816,7 @@ impl Value {
 
     fn resolve_int(self) -> Result<Self, Error> {
         match self {
-            Value::Int(n) => Ok(Value::Int(n)),
+            Value::Int(n) | Value::Date(n) | Value::TimeMillis(n) => Ok(Value::Int(n)),
             Value::Long(n) => Ok(Value::Int(n as i32)),
             other => {
                 Err(SchemaResolutionError::new(format!("Int expected, got {:?}", other)).into())
@@ -356,8 +826,13 @@ impl Value {
 
     fn resolve_long(self) -> Result<Self, Error> {
         match self {
-            Value::Int(n) => Ok(Value::Long(i64::from(n))),
-            Value::Long(n) => Ok(Value::Long(n)),
+            Value::Int(n) | Value::Date(n) | Value::TimeMillis(n) => Ok(Value::Long(i64::from(n))),
+            Value::Long(n)
+            | Value::TimeMicros(n)
+            | Value::TimestampMillis(n)
+            | Value::TimestampMicros(n)
+            | Value::LocalTimestampMillis(n)
+            | Value::LocalTimestampMicros(n) => Ok(Value::Long(n)),
             other => {
                 Err(SchemaResolutionError::new(format!("Long expected, got {:?}", other)).into())
             },
@@ -366,8 +841,13 @@ impl Value {
 
     fn resolve_float(self) -> Result<Self, Error> {
         match self {
-            Value::Int(n) => Ok(Value::Float(n as f32)),
-            Value::Long(n) => Ok(Value::Float(n as f32)),
+            Value::Int(n) | Value::Date(n) | Value::TimeMillis(n) => Ok(Value::Float(n as f32)),
+            Value::Long(n)
+            | Value::TimeMicros(n)
+            | Value::TimestampMillis(n)
+            | Value::TimestampMicros(n)
+            | Value::LocalTimestampMillis(n)
+            | Value::LocalTimestampMicros(n) => Ok(Value::Float(n as f32)),
             Value::Float(x) => Ok(Value::Float(x)),
             Value::Double(x) => Ok(Value::Float(x as f32)),
             other => {
@@ -378,8 +858,15 @@ impl Value {
 
     fn resolve_double(self) -> Result<Self, Error> {
         match self {
-            Value::Int(n) => Ok(Value::Double(f64::from(n))),
-            Value::Long(n) => Ok(Value::Double(n as f64)),
+            Value::Int(n) | Value::Date(n) | Value::TimeMillis(n) => {
+                Ok(Value::Double(f64::from(n)))
+            },
+            Value::Long(n)
+            | Value::TimeMicros(n)
+            | Value::TimestampMillis(n)
+            | Value::TimestampMicros(n)
+            | Value::LocalTimestampMillis(n)
+            | Value::LocalTimestampMicros(n) => Ok(Value::Double(n as f64)),
             Value::Float(x) => Ok(Value::Double(f64::from(x))),
             Value::Double(x) => Ok(Value::Double(x)),
             other => {
@@ -392,6 +879,7 @@ impl Value {
         match self {
             Value::Bytes(bytes) => Ok(Value::Bytes(bytes)),
             Value::String(s) => Ok(Value::Bytes(s.into_bytes())),
+            Value::Uuid(uuid) => Ok(Value::Bytes(format_uuid(&uuid).into_bytes())),
             other => {
                 Err(SchemaResolutionError::new(format!("Bytes expected, got {:?}", other)).into())
             },
@@ -402,6 +890,7 @@ impl Value {
         match self {
             Value::String(s) => Ok(Value::String(s)),
             Value::Bytes(bytes) => Ok(Value::String(String::from_utf8(bytes)?)),
+            Value::Uuid(uuid) => Ok(Value::String(format_uuid(&uuid))),
             other => {
                 Err(SchemaResolutionError::new(format!("String expected, got {:?}", other)).into())
             },
@@ -418,12 +907,181 @@ impl Value {
                     size, n
                 )).into())
             },
+            Value::Duration {
+                months,
+                days,
+                millis,
+            } if size == 12 => {
+                let bytes = duration_to_bytes(months, days, millis);
+                Ok(Value::Fixed(12, bytes.to_vec()))
+            },
             other => {
                 Err(SchemaResolutionError::new(format!("String expected, got {:?}", other)).into())
             },
         }
     }
 
+    fn resolve_decimal(self, precision: usize, inner: &Schema) -> Result<Self, Error> {
+        let decimal = match (self, inner) {
+            (Value::Decimal(decimal), _) => decimal,
+            (Value::Bytes(bytes), _) => Decimal::from(bytes),
+            (Value::Fixed(n, bytes), &Schema::Fixed { size, .. }) if n == size => {
+                Decimal::from(bytes)
+            },
+            (Value::Fixed(_, bytes), &Schema::Bytes) => Decimal::from(bytes),
+            (other, _) => {
+                return Err(SchemaResolutionError::new(format!(
+                    "Decimal expected, got {:?}",
+                    other
+                )).into())
+            },
+        };
+
+        if decimal.num_digits() > precision {
+            return Err(SchemaResolutionError::new(format!(
+                "Decimal precision {} is too small for {:?}",
+                precision, decimal
+            )).into())
+        }
+        if let Schema::Fixed { size, .. } = *inner {
+            if decimal.as_bytes().len() > size {
+                return Err(SchemaResolutionError::new(format!(
+                    "Decimal {:?} does not fit in a fixed of size {}",
+                    decimal, size
+                )).into())
+            }
+        }
+
+        Ok(Value::Decimal(decimal))
+    }
+
+    fn resolve_date(self) -> Result<Self, Error> {
+        match self {
+            Value::Date(d) | Value::Int(d) => Ok(Value::Date(d)),
+            other => {
+                Err(SchemaResolutionError::new(format!("Date expected, got {:?}", other)).into())
+            },
+        }
+    }
+
+    fn resolve_time_millis(self) -> Result<Self, Error> {
+        match self {
+            Value::TimeMillis(t) | Value::Int(t) => {
+                if (0..MILLIS_PER_DAY).contains(&i64::from(t)) {
+                    Ok(Value::TimeMillis(t))
+                } else {
+                    Err(SchemaResolutionError::new(format!(
+                        "TimeMillis out of range of a day, got {}",
+                        t
+                    )).into())
+                }
+            },
+            other => Err(SchemaResolutionError::new(format!(
+                "TimeMillis expected, got {:?}",
+                other
+            )).into()),
+        }
+    }
+
+    fn resolve_time_micros(self) -> Result<Self, Error> {
+        let t = match self {
+            Value::TimeMicros(t) | Value::Long(t) => t,
+            Value::Int(t) => i64::from(t),
+            // time-millis are promoted to time-micros, as int is promoted to long
+            Value::TimeMillis(t) => i64::from(t) * 1000,
+            other => {
+                return Err(SchemaResolutionError::new(format!(
+                    "TimeMicros expected, got {:?}",
+                    other
+                )).into())
+            },
+        };
+
+        if (0..MICROS_PER_DAY).contains(&t) {
+            Ok(Value::TimeMicros(t))
+        } else {
+            Err(SchemaResolutionError::new(format!(
+                "TimeMicros out of range of a day, got {}",
+                t
+            )).into())
+        }
+    }
+
+    fn resolve_timestamp_millis(self) -> Result<Self, Error> {
+        match self {
+            Value::TimestampMillis(t) | Value::Long(t) => Ok(Value::TimestampMillis(t)),
+            Value::Int(t) => Ok(Value::TimestampMillis(i64::from(t))),
+            Value::TimestampMicros(t) => Ok(Value::TimestampMillis(t.div_euclid(1000))),
+            other => Err(SchemaResolutionError::new(format!(
+                "TimestampMillis expected, got {:?}",
+                other
+            )).into()),
+        }
+    }
+
+    fn resolve_timestamp_micros(self) -> Result<Self, Error> {
+        match self {
+            Value::TimestampMicros(t) | Value::Long(t) => Ok(Value::TimestampMicros(t)),
+            Value::Int(t) => Ok(Value::TimestampMicros(i64::from(t))),
+            Value::TimestampMillis(t) => millis_to_micros(t).map(Value::TimestampMicros),
+            other => Err(SchemaResolutionError::new(format!(
+                "TimestampMicros expected, got {:?}",
+                other
+            )).into()),
+        }
+    }
+
+    fn resolve_local_timestamp_millis(self) -> Result<Self, Error> {
+        match self {
+            Value::LocalTimestampMillis(t) | Value::Long(t) => Ok(Value::LocalTimestampMillis(t)),
+            Value::Int(t) => Ok(Value::LocalTimestampMillis(i64::from(t))),
+            Value::LocalTimestampMicros(t) => {
+                Ok(Value::LocalTimestampMillis(t.div_euclid(1000)))
+            },
+            other => Err(SchemaResolutionError::new(format!(
+                "LocalTimestampMillis expected, got {:?}",
+                other
+            )).into()),
+        }
+    }
+
+    fn resolve_local_timestamp_micros(self) -> Result<Self, Error> {
+        match self {
+            Value::LocalTimestampMicros(t) | Value::Long(t) => Ok(Value::LocalTimestampMicros(t)),
+            Value::Int(t) => Ok(Value::LocalTimestampMicros(i64::from(t))),
+            Value::LocalTimestampMillis(t) => {
+                millis_to_micros(t).map(Value::LocalTimestampMicros)
+            },
+            other => Err(SchemaResolutionError::new(format!(
+                "LocalTimestampMicros expected, got {:?}",
+                other
+            )).into()),
+        }
+    }
+
+    fn resolve_uuid(self) -> Result<Self, Error> {
+        match self {
+            Value::Uuid(uuid) => Ok(Value::Uuid(uuid)),
+            Value::String(s) => match parse_uuid(&s) {
+                Some(uuid) => Ok(Value::Uuid(uuid)),
+                None => Err(SchemaResolutionError::new(format!("Invalid UUID: {}", s)).into()),
+            },
+            other => {
+                Err(SchemaResolutionError::new(format!("Uuid expected, got {:?}", other)).into())
+            },
+        }
+    }
+
+    fn resolve_duration(self) -> Result<Self, Error> {
+        match self {
+            duration @ Value::Duration { .. } => Ok(duration),
+            Value::Fixed(12, bytes) => Ok(duration_from_bytes(&bytes)),
+            other => Err(
+                SchemaResolutionError::new(format!("Duration expected, got {:?}", other)).into(),
+            ),
+        }
+    }
+
     fn resolve_enum(self, symbols: &[String]) -> Result<Self, Error> {
         let validate_symbol = |symbol: String, symbols: &[String]| {
             if let Some(index) = symbols.iter().position(|ref item| item == &&symbol) {
@@ -437,15 +1095,8 @@ impl Value {
         };
 
         match self {
-            Value::Enum(i, s) => if i > 0 && i < symbols.len() as i32 {
-                validate_symbol(s, symbols)
-            } else {
-                Err(SchemaResolutionError::new(format!(
-                    "Enum value {} is out of bound {}",
-                    i,
-                    symbols.len() as i32
-                )).into())
-            },
+            // The index is the position of the symbol in the writer schema, and is irrelevant here.
+            Value::Enum(_, s) => validate_symbol(s, symbols),
             Value::String(s) => validate_symbol(s, symbols),
             other => Err(SchemaResolutionError::new(format!(
                 "Enum({:?}) expected, got {:?}",
@@ -454,26 +1105,41 @@ impl Value {
         }
     }
 
-    fn resolve_union(self, schema: &UnionSchema) -> Result<Self, Error> {
+    fn resolve_union(self, schema: &UnionSchema, names: &Names) -> Result<Self, Error> {
         let v = match self {
             // Both are unions case.
-            Value::Union(v) => *v,
+            Value::Union(_, v) => *v,
             // Reader is a union, but writer is not.
             v => v,
         };
         // Find the first match in the reader schema.
-        let (_, inner) = schema
-            .find_schema(&v)
-            .ok_or_else(|| SchemaResolutionError::new("Could not find matching type in union"))?;
-        v.resolve(inner)
+        if let Some((i, inner)) = schema.find_schema_in(&v, names) {
+            return Ok(Value::Union(i as u32, Box::new(v.resolve_internal(inner, names)?)))
+        }
+
+        // Otherwise, the first variant the value can be promoted to, or the first named type of
+        // the same kind the value can be resolved against.
+        let kind = SchemaKind::from(&v);
+        let candidates = schema
+            .variants()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, variant)| variant.resolve_ref(names).map(|variant| (i, variant)))
+            .filter(|(_, variant)| is_union_promotable(SchemaKind::from(*variant), kind));
+        for (i, inner) in candidates {
+            if let Ok(resolved) = v.clone().resolve_internal(inner, names) {
+                return Ok(Value::Union(i as u32, Box::new(resolved)))
+            }
+        }
+        Err(SchemaResolutionError::new("Could not find matching type in union").into())
     }
 
-    fn resolve_array(self, schema: &Schema) -> Result<Self, Error> {
+    fn resolve_array(self, schema: &Schema, names: &Names) -> Result<Self, Error> {
         match self {
             Value::Array(items) => Ok(Value::Array(
                 items
                     .into_iter()
-                    .map(|item| item.resolve(schema))
+                    .map(|item| item.resolve_internal(schema, names))
                     .collect::<Result<Vec<_>, _>>()?,
             )),
             other => Err(SchemaResolutionError::new(format!(
@@ -483,12 +1149,16 @@ impl Value {
         }
     }
 
-    fn resolve_map(self, schema: &Schema) -> Result<Self, Error> {
+    fn resolve_map(self, schema: &Schema, names: &Names) -> Result<Self, Error> {
         match self {
             Value::Map(items) => Ok(Value::Map(
                 items
                     .into_iter()
-                    .map(|(key, value)| value.resolve(schema).map(|value| (key, value)))
+                    .map(|(key, value)| {
+                        value
+                            .resolve_internal(schema, names)
+                            .map(|value| (key, value))
+                    })
                     .collect::<Result<HashMap<_, _>, _>>()?,
             )),
             other => Err(SchemaResolutionError::new(format!(
@@ -498,7 +1168,7 @@ impl Value {
         }
     }
 
-    fn resolve_record(self, fields: &[RecordField]) -> Result<Self, Error> {
+    fn resolve_record(self, fields: &[RecordField], names: &Names) -> Result<Self, Error> {
         let mut items = match self {
             Value::Map(items) => Ok(items),
             Value::Record(fields) => Ok(fields.into_iter().collect::<HashMap<_, _>>()),
@@ -511,15 +1181,18 @@ impl Value {
         let new_fields = fields
             .iter()
             .map(|field| {
-                let value = match items.remove(&field.name) {
+                let value = items.remove(&field.name).or_else(|| {
+                    field
+                        .aliases
+                        .iter()
+                        .flatten()
+                        .filter_map(|alias| items.remove(alias))
+                        .next()
+                });
+                let value = match value {
                     Some(value) => value,
-                    None => match field.default {
-                        Some(ref value) => match field.schema {
-                            Schema::Enum { ref symbols, .. } => {
-                                value.clone().avro().resolve_enum(symbols)?
-                            },
-                            _ => value.clone().avro(),
-                        },
+                    None => match field.default_value {
+                        Some(ref value) => value.clone(),
                         _ => {
                             return Err(SchemaResolutionError::new(format!(
                                 "missing field {} in record",
@@ -529,18 +1202,197 @@ impl Value {
                     },
                 };
                 value
-                    .resolve(&field.schema)
+                    .resolve_internal(&field.schema, names)
                     .map(|value| (field.name.clone(), value))
             }).collect::<Result<Vec<_>, _>>()?;
 
         Ok(Value::Record(new_fields))
     }
+
+    fn resolve_record_from(
+        self,
+        writer_fields: &[RecordField],
+        writer_names: &Names,
+        reader_fields: &[RecordField],
+        reader_names: &Names,
+    ) -> Result<Self, Error> {
+        let mut items = match self {
+            Value::Map(items) => items,
+            Value::Record(fields) => fields.into_iter().collect::<HashMap<_, _>>(),
+            other => Err(SchemaResolutionError::new(format!(
+                "Record expected, got {:?}",
+                other
+            )))?,
+        };
+
+        let new_fields = reader_fields
+            .iter()
+            .map(|field| {
+                let written = writer_fields
+                    .iter()
+                    .find(|writer_field| field.matches(&writer_field.name));
+                let value = match written {
+                    Some(writer_field) => items
+                        .remove(&writer_field.name)
+                        .ok_or_else(|| {
+                            SchemaResolutionError::new(format!(
+                                "missing field {} in record",
+                                writer_field.name
+                            ))
+                        })?.resolve_from(
+                            &writer_field.schema,
+                            writer_names,
+                            &field.schema,
+                            reader_names,
+                        )?,
+                    None => match field.default_value {
+                        Some(ref value) => value
+                            .clone()
+                            .resolve_internal(&field.schema, reader_names)?,
+                        None => Err(SchemaResolutionError::new(format!(
+                            "missing field {} in record",
+                            field.name
+                        )))?,
+                    },
+                };
+                Ok((field.name.clone(), value))
+            }).collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(Value::Record(new_fields))
+    }
+}
+
+/// Whether a value of the `writer` type can be read as a variant of the `reader` type of a union,
+/// through a promotion, possibly of the type annotated by a logical type.
+pub(crate) fn is_union_promotable(reader: SchemaKind, writer: SchemaKind) -> bool {
+    let underlying = match writer {
+        SchemaKind::Date | SchemaKind::TimeMillis => SchemaKind::Int,
+        SchemaKind::TimeMicros
+        | SchemaKind::TimestampMillis
+        | SchemaKind::TimestampMicros
+        | SchemaKind::LocalTimestampMillis
+        | SchemaKind::LocalTimestampMicros => SchemaKind::Long,
+        SchemaKind::Uuid => SchemaKind::String,
+        kind => kind,
+    };
+    is_promotable(reader, writer)
+        || is_promotable(reader, underlying)
+        || LOGICAL_CONVERSIONS.contains(&(reader, writer))
+}
+
+/// Find the variant of the `reader` union a value of the named type `writer` is read as: the
+/// variant of the same fullname, or else the first one it matches by aliases.
+pub(crate) fn find_named_variant<'a>(
+    writer: &Schema,
+    reader: &'a UnionSchema,
+    reader_names: &Names<'a>,
+) -> Option<(usize, &'a Schema)> {
+    let writer_name = writer.name()?;
+    let named = |variant: &Schema| {
+        variant
+            .resolve_ref(reader_names)
+            .filter(|variant| SchemaKind::from(*variant) == SchemaKind::from(writer))
+            .and_then(|variant| variant.name())
+            .filter(|name| name.matches(writer_name))
+            .is_some()
+    };
+    reader
+        .find_named(&writer_name.fullname(None))
+        .filter(|(_, variant)| named(variant))
+        .or_else(|| {
+            reader
+                .variants()
+                .iter()
+                .enumerate()
+                .find(|(_, variant)| named(variant))
+        })
+}
+
+/// Check that a named type called `writer` can be read as a named type called `reader`.
+pub(crate) fn check_names(reader: &Name, writer: &Name) -> Result<(), SchemaResolutionError> {
+    if reader.matches(writer) {
+        Ok(())
+    } else {
+        Err(SchemaResolutionError::new(format!(
+            "{} cannot be read as {}: names differ",
+            writer.fullname(None),
+            reader.fullname(None)
+        )))
+    }
+}
+
+/// Name of the kind of `value`, as the type of its schema.
+pub(crate) fn describe_value(value: &Value) -> &'static str {
+    match *value {
+        Value::Null => "null",
+        Value::Boolean(_) => "boolean",
+        Value::Int(_) => "int",
+        Value::Long(_) => "long",
+        Value::Float(_) => "float",
+        Value::Double(_) => "double",
+        Value::Bytes(_) => "bytes",
+        Value::String(_) => "string",
+        Value::Fixed(..) => "fixed",
+        Value::Enum(..) => "enum",
+        Value::Union(..) => "union",
+        Value::Array(_) => "array",
+        Value::Map(_) => "map",
+        Value::Record(_) => "record",
+        Value::Decimal(_) => "decimal",
+        Value::Date(_) => "date",
+        Value::TimeMillis(_) => "time-millis",
+        Value::TimeMicros(_) => "time-micros",
+        Value::TimestampMillis(_) => "timestamp-millis",
+        Value::TimestampMicros(_) => "timestamp-micros",
+        Value::LocalTimestampMillis(_) => "local-timestamp-millis",
+        Value::LocalTimestampMicros(_) => "local-timestamp-micros",
+        Value::Uuid(_) => "uuid",
+        Value::Duration { .. } => "duration",
+    }
+}
+
+/// Signed number of nanoseconds elapsed between the unix epoch and `time`.
+fn system_time_to_nanos(time: SystemTime) -> i128 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(elapsed) => elapsed.as_nanos() as i128,
+        Err(e) => -(e.duration().as_nanos() as i128),
+    }
+}
+
+/// Little-endian representation of a `duration`, as written on the wire.
+pub(crate) fn duration_to_bytes(months: u32, days: u32, millis: u32) -> [u8; 12] {
+    let mut bytes = [0u8; 12];
+    bytes[0..4].copy_from_slice(&months.to_le_bytes());
+    bytes[4..8].copy_from_slice(&days.to_le_bytes());
+    bytes[8..12].copy_from_slice(&millis.to_le_bytes());
+    bytes
+}
+
+/// Build a `Value::Duration` out of its 12 bytes long, little-endian, representation.
+pub(crate) fn duration_from_bytes(bytes: &[u8]) -> Value {
+    let read_u32 = |i: usize| {
+        let mut word = [0u8; 4];
+        word.copy_from_slice(&bytes[i..i + 4]);
+        u32::from_le_bytes(word)
+    };
+
+    Value::Duration {
+        months: read_u32(0),
+        days: read_u32(4),
+        millis: read_u32(8),
+    }
+}
+
+fn millis_to_micros(millis: i64) -> Result<i64, Error> {
+    millis.checked_mul(1000).ok_or_else(|| {
+        SchemaResolutionError::new(format!("Timestamp {} overflows microseconds", millis)).into()
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use schema::{Name, RecordField, RecordFieldOrder, UnionSchema};
+    use schema::{Name, Props, RecordField, RecordFieldOrder, UnionSchema};
 
     #[test]
     fn validate() {
@@ -548,22 +1400,32 @@ mod tests {
             (Value::Int(42), Schema::Int, true),
             (Value::Int(42), Schema::Boolean, false),
             (
-                Value::Union(Box::new(Value::Null)),
+                Value::Union(0, Box::new(Value::Null)),
                 Schema::Union(UnionSchema::new(vec![Schema::Null, Schema::Int]).unwrap()),
                 true,
             ),
             (
-                Value::Union(Box::new(Value::Int(42))),
+                Value::Union(1, Box::new(Value::Int(42))),
                 Schema::Union(UnionSchema::new(vec![Schema::Null, Schema::Int]).unwrap()),
                 true,
             ),
             (
-                Value::Union(Box::new(Value::Null)),
+                Value::Union(0, Box::new(Value::Int(42))),
+                Schema::Union(UnionSchema::new(vec![Schema::Null, Schema::Int]).unwrap()),
+                false,
+            ),
+            (
+                Value::Union(2, Box::new(Value::Int(42))),
+                Schema::Union(UnionSchema::new(vec![Schema::Null, Schema::Int]).unwrap()),
+                false,
+            ),
+            (
+                Value::Union(0, Box::new(Value::Null)),
                 Schema::Union(UnionSchema::new(vec![Schema::Double, Schema::Int]).unwrap()),
                 false,
             ),
             (
-                Value::Union(Box::new(Value::Int(42))),
+                Value::Union(3, Box::new(Value::Int(42))),
                 Schema::Union(
                     UnionSchema::new(vec![
                         Schema::Null,
@@ -589,14 +1451,48 @@ mod tests {
 
         for (value, schema, valid) in value_schema_valid.into_iter() {
             assert_eq!(valid, value.validate(&schema));
+            assert_eq!(valid, value.validate_detailed(&schema).is_ok());
         }
     }
 
+    #[test]
+    fn union_picks_variant() {
+        let schema = Schema::parse_str(
+            r#"
+            [
+                "null",
+                "long",
+                {"type": "record", "name": "a", "fields": [{"name": "x", "type": "long"}]},
+                {"type": "record", "name": "b", "fields": [{"name": "y", "type": "long"}]},
+                {"type": "array", "items": "b"}
+            ]
+            "#,
+        ).unwrap();
+
+        assert_eq!(
+            Value::union(&schema, Value::Long(1)),
+            Some(Value::Union(1, Box::new(Value::Long(1))))
+        );
+        let record = Value::Record(vec![("y".to_string(), Value::Long(1))]);
+        assert_eq!(
+            Value::union(&schema, record.clone()),
+            Some(Value::Union(3, Box::new(record.clone())))
+        );
+        assert_eq!(Value::union(&schema, Value::Int(1)), None);
+        assert_eq!(Value::union(&Schema::Long, Value::Long(1)), None);
+
+        // The variant given by the index is the one validated.
+        assert!(Value::Union(3, Box::new(record.clone())).validate(&schema));
+        assert!(!Value::Union(2, Box::new(record)).validate(&schema));
+        assert!(!Value::Union(5, Box::new(Value::Null)).validate(&schema));
+    }
+
     #[test]
     fn validate_fixed() {
         let schema = Schema::Fixed {
             size: 4,
             name: Name::new("some_fixed"),
+            props: Props::new(),
         };
 
         assert!(Value::Fixed(4, vec![0, 0, 0, 0]).validate(&schema));
@@ -614,6 +1510,7 @@ mod tests {
                 "diamonds".to_string(),
                 "clubs".to_string(),
             ],
+            props: Props::new(),
         };
 
         assert!(Value::Enum(0, "spades".to_string()).validate(&schema));
@@ -631,6 +1528,7 @@ mod tests {
                 "clubs".to_string(),
                 "spades".to_string(),
             ],
+            props: Props::new(),
         };
 
         assert!(!Value::Enum(0, "spades".to_string()).validate(&other_schema));
@@ -652,21 +1550,28 @@ mod tests {
                 RecordField {
                     name: "a".to_string(),
                     doc: None,
+                    aliases: None,
                     default: None,
+                    default_value: None,
                     schema: Schema::Long,
                     order: RecordFieldOrder::Ascending,
                     position: 0,
+                    props: Props::new(),
                 },
                 RecordField {
                     name: "b".to_string(),
                     doc: None,
+                    aliases: None,
                     default: None,
+                    default_value: None,
                     schema: Schema::String,
                     order: RecordFieldOrder::Ascending,
                     position: 1,
+                    props: Props::new(),
                 },
             ],
             lookup: HashMap::new(),
+            props: Props::new(),
         };
 
         assert!(
@@ -705,4 +1610,663 @@ mod tests {
             ]).validate(&schema)
         );
     }
+
+    #[test]
+    fn validate_detailed() {
+        let schema = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "User",
+                "fields": [
+                    {"name": "id", "type": "long"},
+                    {"name": "address", "type": {
+                        "type": "record",
+                        "name": "Address",
+                        "fields": [
+                            {"name": "zip", "type": "int"},
+                            {"name": "city", "type": "string"}
+                        ]
+                    }},
+                    {"name": "tags", "type": {"type": "array", "items": "string"}},
+                    {"name": "scores", "type": {"type": "map", "values": ["null", "double"]}},
+                    {"name": "suit", "type": {
+                        "type": "enum", "name": "Suit", "symbols": ["hearts", "spades"]
+                    }},
+                    {"name": "hash", "type": {"type": "fixed", "name": "Hash", "size": 2}}
+                ]
+            }
+        "#,
+        ).unwrap();
+        let mut scores = HashMap::new();
+        scores.insert("a".to_owned(), Value::Union(1, Box::new(Value::Double(1.0))));
+        scores.insert("b".to_owned(), Value::Union(1, Box::new(Value::Null)));
+        scores.insert("c".to_owned(), Value::Union(2, Box::new(Value::Null)));
+        let value = Value::Record(vec![
+            ("id".to_owned(), Value::String("42".to_owned())),
+            (
+                "address".to_owned(),
+                Value::Record(vec![("zip".to_owned(), Value::Int(75000))]),
+            ),
+            (
+                "tags".to_owned(),
+                Value::Array(vec![Value::String("a".to_owned()), Value::Int(1)]),
+            ),
+            ("scores".to_owned(), Value::Map(scores)),
+            ("suit".to_owned(), Value::Enum(0, "spades".to_owned())),
+            ("hash".to_owned(), Value::Fixed(3, vec![0, 0, 0])),
+        ]);
+
+        assert!(!value.validate(&schema));
+        let mismatches = value
+            .validate_detailed(&schema)
+            .unwrap_err()
+            .into_iter()
+            .map(|mismatch| mismatch.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            mismatches,
+            vec![
+                ".id: expected long, found string",
+                ".address: expected record Address, found record (missing field city)",
+                ".tags[1]: expected string, found int",
+                ".scores{b}: expected double, found null",
+                ".scores{c}: expected union, found union (no variant at position 2)",
+                ".suit: expected enum Suit, found enum (symbol spades at position 0 instead of 1)",
+                ".hash: expected fixed Hash, found fixed (size 3 instead of 2)",
+            ]
+        );
+
+        assert_eq!(
+            Value::Int(1).validate_detailed(&Schema::Long),
+            Err(vec![ValidationMismatch {
+                path: ".".to_owned(),
+                expected: "long".to_owned(),
+                actual: "int".to_owned(),
+                detail: None,
+            }])
+        );
+        assert_eq!(Value::Long(1).validate_detailed(&Schema::Long), Ok(()));
+    }
+
+    #[test]
+    fn validate_and_resolve_through_reference() {
+        let schema = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "Tree",
+                "fields": [
+                    {"name": "label", "type": "string"},
+                    {"name": "children", "type": {"type": "array", "items": "Tree"}}
+                ]
+            }
+        "#,
+        ).unwrap();
+
+        let leaf = |label: &str| {
+            Value::Record(vec![
+                ("label".to_string(), Value::String(label.to_string())),
+                ("children".to_string(), Value::Array(vec![])),
+            ])
+        };
+        let tree = Value::Record(vec![
+            ("label".to_string(), Value::String("root".to_string())),
+            ("children".to_string(), Value::Array(vec![leaf("a"), leaf("b")])),
+        ]);
+
+        assert!(tree.validate(&schema));
+        assert!(
+            !Value::Record(vec![
+                ("label".to_string(), Value::String("root".to_string())),
+                ("children".to_string(), Value::Array(vec![Value::Int(1)])),
+            ]).validate(&schema)
+        );
+        assert_eq!(tree.clone().resolve(&schema).unwrap(), tree);
+    }
+
+    #[test]
+    fn validate_decimal() {
+        let bytes_schema = Schema::Decimal {
+            precision: 4,
+            scale: 2,
+            inner: Box::new(Schema::Bytes),
+        };
+        let fixed_schema = Schema::Decimal {
+            precision: 4,
+            scale: 2,
+            inner: Box::new(Schema::Fixed {
+                name: Name::new("decimal"),
+                size: 1,
+                props: Props::new(),
+            }),
+        };
+
+        assert!(Value::Decimal(Decimal::from(9999)).validate(&bytes_schema));
+        assert!(Value::Decimal(Decimal::from(-9999)).validate(&bytes_schema));
+        assert!(!Value::Decimal(Decimal::from(10000)).validate(&bytes_schema));
+        assert!(!Value::Bytes(vec![1]).validate(&bytes_schema));
+
+        assert!(Value::Decimal(Decimal::from(-128)).validate(&fixed_schema));
+        assert!(!Value::Decimal(Decimal::from(128)).validate(&fixed_schema));
+    }
+
+    #[test]
+    fn resolve_decimal() {
+        let schema = Schema::Decimal {
+            precision: 3,
+            scale: 0,
+            inner: Box::new(Schema::Bytes),
+        };
+
+        assert_eq!(
+            Value::Bytes(vec![0x01, 0x00]).resolve(&schema).unwrap(),
+            Value::Decimal(Decimal::from(256))
+        );
+        assert!(Value::Decimal(Decimal::from(1000)).resolve(&schema).is_err());
+        assert!(Value::String("1".to_string()).resolve(&schema).is_err());
+    }
+
+    #[test]
+    fn validate_date_and_time() {
+        assert!(Value::Date(-1).validate(&Schema::Date));
+        assert!(!Value::Int(1).validate(&Schema::Date));
+
+        assert!(Value::TimeMillis(0).validate(&Schema::TimeMillis));
+        assert!(Value::TimeMillis(86_399_999).validate(&Schema::TimeMillis));
+        assert!(!Value::TimeMillis(86_400_000).validate(&Schema::TimeMillis));
+        assert!(!Value::TimeMillis(-1).validate(&Schema::TimeMillis));
+
+        assert!(Value::TimeMicros(86_399_999_999).validate(&Schema::TimeMicros));
+        assert!(!Value::TimeMicros(86_400_000_000).validate(&Schema::TimeMicros));
+        assert!(!Value::TimeMillis(0).validate(&Schema::TimeMicros));
+    }
+
+    #[test]
+    fn resolve_date_and_time() {
+        assert_eq!(Value::Int(42).resolve(&Schema::Date).unwrap(), Value::Date(42));
+        assert_eq!(Value::Date(42).resolve(&Schema::Long).unwrap(), Value::Long(42));
+        assert!(Value::Long(42).resolve(&Schema::Date).is_err());
+
+        assert_eq!(
+            Value::TimeMillis(1).resolve(&Schema::TimeMicros).unwrap(),
+            Value::TimeMicros(1000)
+        );
+        assert!(Value::Int(-1).resolve(&Schema::TimeMillis).is_err());
+        assert!(
+            Value::Long(86_400_000_000)
+                .resolve(&Schema::TimeMicros)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn resolve_timestamps() {
+        assert_eq!(
+            Value::TimestampMillis(1_500).resolve(&Schema::TimestampMicros).unwrap(),
+            Value::TimestampMicros(1_500_000)
+        );
+        assert_eq!(
+            Value::TimestampMicros(-1_500)
+                .resolve(&Schema::TimestampMillis)
+                .unwrap(),
+            Value::TimestampMillis(-2)
+        );
+        assert_eq!(
+            Value::LocalTimestampMillis(2)
+                .resolve(&Schema::LocalTimestampMicros)
+                .unwrap(),
+            Value::LocalTimestampMicros(2_000)
+        );
+        assert_eq!(
+            Value::Long(42).resolve(&Schema::LocalTimestampMillis).unwrap(),
+            Value::LocalTimestampMillis(42)
+        );
+        assert_eq!(
+            Value::TimestampMicros(42).resolve(&Schema::Long).unwrap(),
+            Value::Long(42)
+        );
+        assert!(
+            Value::TimestampMillis(i64::max_value())
+                .resolve(&Schema::TimestampMicros)
+                .is_err()
+        );
+        // local and UTC timestamps are not interchangeable
+        assert!(
+            Value::TimestampMillis(42)
+                .resolve(&Schema::LocalTimestampMillis)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn timestamps_and_system_time() {
+        let time = UNIX_EPOCH + Duration::new(1_540_000_000, 123_456_789);
+        assert_eq!(
+            Value::timestamp_millis(time),
+            Value::TimestampMillis(1_540_000_000_123)
+        );
+        assert_eq!(
+            Value::timestamp_micros(time),
+            Value::TimestampMicros(1_540_000_000_123_456)
+        );
+        assert_eq!(
+            Value::TimestampMicros(1_540_000_000_123_456).as_system_time(),
+            Some(UNIX_EPOCH + Duration::new(1_540_000_000, 123_456_000))
+        );
+
+        let before_epoch = UNIX_EPOCH - Duration::new(1, 500_000);
+        assert_eq!(
+            Value::timestamp_millis(before_epoch),
+            Value::TimestampMillis(-1_001)
+        );
+        assert_eq!(
+            Value::TimestampMillis(-1_001).as_system_time(),
+            Some(UNIX_EPOCH - Duration::from_millis(1_001))
+        );
+
+        assert_eq!(Value::TimestampMillis(i64::MAX).as_system_time(), None);
+        assert_eq!(Value::LocalTimestampMillis(0).as_system_time(), None);
+        assert_eq!(Value::Long(0).as_system_time(), None);
+    }
+
+    #[test]
+    fn validate_and_resolve_uuid() {
+        let uuid = [
+            0x55, 0x0e, 0x84, 0x00, 0xe2, 0x9b, 0x41, 0xd4, 0xa7, 0x16, 0x44, 0x66, 0x55, 0x44,
+            0x00, 0x00,
+        ];
+        let canonical = "550e8400-e29b-41d4-a716-446655440000".to_string();
+
+        assert!(Value::Uuid(uuid).validate(&Schema::Uuid));
+        assert!(Value::String(canonical.clone()).validate(&Schema::Uuid));
+        let unhyphenated = "550e8400e29b41d4a716446655440000".to_string();
+        assert!(!Value::String(unhyphenated).validate(&Schema::Uuid));
+        assert!(!Value::Uuid(uuid).validate(&Schema::String));
+
+        assert_eq!(
+            Value::String(canonical.clone()).resolve(&Schema::Uuid).unwrap(),
+            Value::Uuid(uuid)
+        );
+        assert_eq!(
+            Value::Uuid(uuid).resolve(&Schema::String).unwrap(),
+            Value::String(canonical)
+        );
+        assert!(Value::String("foo".to_string()).resolve(&Schema::Uuid).is_err());
+    }
+
+    #[test]
+    fn validate_and_resolve_duration() {
+        let schema = Schema::Duration {
+            name: Name::new("interval"),
+        };
+        let duration = Value::Duration {
+            months: 1,
+            days: 2,
+            millis: 3,
+        };
+        let bytes = vec![1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0];
+
+        assert!(duration.validate(&schema));
+        assert!(!Value::Fixed(12, bytes.clone()).validate(&schema));
+        assert!(!duration.validate(&Schema::Bytes));
+
+        assert_eq!(
+            Value::Fixed(12, bytes.clone()).resolve(&schema).unwrap(),
+            duration
+        );
+        assert_eq!(
+            duration
+                .clone()
+                .resolve(&Schema::Fixed {
+                    name: Name::new("interval"),
+                    size: 12,
+                    props: Props::new(),
+                }).unwrap(),
+            Value::Fixed(12, bytes)
+        );
+        assert!(Value::Fixed(8, vec![0; 8]).resolve(&schema).is_err());
+    }
+
+    #[test]
+    fn resolve_record_with_defaults() {
+        let schema = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "r",
+                "fields": [
+                    {"name": "a", "type": "int"},
+                    {"name": "b", "type": "bytes", "default": "\u00ff\u0000"},
+                    {
+                        "name": "c",
+                        "type": {"type": "fixed", "name": "f", "size": 2},
+                        "default": "ab"
+                    },
+                    {"name": "d", "type": "float", "default": 1},
+                    {"name": "e", "type": ["null", "long"], "default": null},
+                    {"name": "f", "type": {"type": "map", "values": "int"}, "default": {"x": 1}},
+                    {
+                        "name": "g",
+                        "type": {
+                            "type": "record",
+                            "name": "inner",
+                            "fields": [
+                                {"name": "x", "type": "long"},
+                                {"name": "y", "type": "string", "default": "why"}
+                            ]
+                        },
+                        "default": {"x": 2}
+                    }
+                ]
+            }
+            "#,
+        ).unwrap();
+
+        let value = Value::Record(vec![("a".to_string(), Value::Int(1))]);
+        let mut map = HashMap::new();
+        map.insert("x".to_string(), Value::Int(1));
+        assert_eq!(
+            value.resolve(&schema).unwrap(),
+            Value::Record(vec![
+                ("a".to_string(), Value::Int(1)),
+                ("b".to_string(), Value::Bytes(vec![0xff, 0])),
+                ("c".to_string(), Value::Fixed(2, b"ab".to_vec())),
+                ("d".to_string(), Value::Float(1.0)),
+                ("e".to_string(), Value::Union(0, Box::new(Value::Null))),
+                ("f".to_string(), Value::Map(map)),
+                (
+                    "g".to_string(),
+                    Value::Record(vec![
+                        ("x".to_string(), Value::Long(2)),
+                        ("y".to_string(), Value::String("why".to_string())),
+                    ])
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn resolve_enum_by_symbol() {
+        let schema = Schema::parse_str(
+            r#"{"type": "enum", "name": "suit", "symbols": ["diamonds", "spades"]}"#,
+        ).unwrap();
+
+        // The first symbol is resolved like any other.
+        assert_eq!(
+            Value::Enum(0, "diamonds".to_string()).resolve(&schema).unwrap(),
+            Value::Enum(0, "diamonds".to_string())
+        );
+        // The index refers to the writer schema, only the symbol matters to the reader.
+        assert_eq!(
+            Value::Enum(3, "spades".to_string()).resolve(&schema).unwrap(),
+            Value::Enum(1, "spades".to_string())
+        );
+        assert!(Value::Enum(1, "clubs".to_string()).resolve(&schema).is_err());
+    }
+
+    #[test]
+    fn resolve_union() {
+        let union = |json: &str| Schema::parse_str(json).unwrap();
+        let vectors = vec![
+            // Reader union, writer not: same type first, or else the first promotion.
+            (Value::Int(1), union(r#"["null", "long", "int"]"#), Some((2, Value::Int(1)))),
+            (Value::Int(1), union(r#"["null", "long"]"#), Some((1, Value::Long(1)))),
+            (Value::Int(1), union(r#"["double", "float"]"#), Some((0, Value::Double(1.0)))),
+            (Value::Long(1), union(r#"["int", "float"]"#), Some((1, Value::Float(1.0)))),
+            (Value::Float(1.0), union(r#"["long", "double"]"#), Some((1, Value::Double(1.0)))),
+            (
+                Value::String("a".to_string()),
+                union(r#"["null", "bytes"]"#),
+                Some((1, Value::Bytes(b"a".to_vec()))),
+            ),
+            (
+                Value::Bytes(b"a".to_vec()),
+                union(r#"["null", "string"]"#),
+                Some((1, Value::String("a".to_string()))),
+            ),
+            (Value::Date(1), union(r#"["null", "long"]"#), Some((1, Value::Long(1)))),
+            (
+                Value::TimestampMillis(1),
+                union(r#"["null", {"type": "long", "logicalType": "timestamp-micros"}]"#),
+                Some((1, Value::TimestampMicros(1000))),
+            ),
+            (Value::Long(1), union(r#"["null", "int"]"#), None),
+            (Value::Boolean(true), union(r#"["null", "int"]"#), None),
+            // Both unions: the variant written is resolved like above.
+            (
+                Value::Union(0, Box::new(Value::Int(1))),
+                union(r#"["string", "long"]"#),
+                Some((1, Value::Long(1))),
+            ),
+            (
+                Value::Union(1, Box::new(Value::Null)),
+                union(r#"["string", "null"]"#),
+                Some((1, Value::Null)),
+            ),
+            // Named types are matched by structure.
+            (
+                Value::Record(vec![("y".to_string(), Value::Int(1))]),
+                union(
+                    r#"
+                    [
+                        {"type": "record", "name": "a", "fields": [{"name": "x", "type": "int"}]},
+                        {"type": "record", "name": "b", "fields": [{"name": "y", "type": "long"}]}
+                    ]
+                    "#,
+                ),
+                Some((1, Value::Record(vec![("y".to_string(), Value::Long(1))]))),
+            ),
+            (
+                Value::Enum(1, "B".to_string()),
+                union(
+                    r#"
+                    [
+                        "null",
+                        {"type": "enum", "name": "a", "symbols": ["A"]},
+                        {"type": "enum", "name": "b", "symbols": ["B", "A"]}
+                    ]
+                    "#,
+                ),
+                Some((2, Value::Enum(0, "B".to_string()))),
+            ),
+        ];
+
+        for (value, schema, expected) in vectors {
+            let resolved = value.clone().resolve(&schema).ok();
+            let expected = expected.map(|(i, value)| Value::Union(i, Box::new(value)));
+            assert_eq!(resolved, expected, "{:?} as {:?}", value, schema);
+        }
+
+        // Writer union, reader not: the variant written is resolved against the reader.
+        let value = Value::Union(1, Box::new(Value::Int(1)));
+        assert_eq!(value.resolve(&Schema::Double).unwrap(), Value::Double(1.0));
+        let value = Value::Union(1, Box::new(Value::Null));
+        assert!(value.resolve(&Schema::Double).is_err());
+    }
+
+    #[test]
+    fn resolve_union_named_types() {
+        let writer = Schema::parse_str(
+            r#"
+            [
+                "null",
+                {"type": "record", "name": "b", "fields": [{"name": "x", "type": "int"}]},
+                "int"
+            ]
+            "#,
+        ).unwrap();
+        let reader = Schema::parse_str(
+            r#"
+            [
+                {"type": "record", "name": "a", "fields": [{"name": "x", "type": "int"}]},
+                {"type": "record", "name": "b", "fields": [{"name": "x", "type": "long"}]},
+                "double",
+                "null"
+            ]
+            "#,
+        ).unwrap();
+
+        // Records of the same structure are told apart by name.
+        let value = Value::Union(
+            1,
+            Box::new(Value::Record(vec![("x".to_string(), Value::Int(1))])),
+        );
+        assert_eq!(
+            value.resolve_with_writer(&writer, &reader).unwrap(),
+            Value::Union(
+                1,
+                Box::new(Value::Record(vec![("x".to_string(), Value::Long(1))]))
+            )
+        );
+        let value = Value::Union(2, Box::new(Value::Int(1)));
+        assert_eq!(
+            value.resolve_with_writer(&writer, &reader).unwrap(),
+            Value::Union(2, Box::new(Value::Double(1.0)))
+        );
+        let value = Value::Union(0, Box::new(Value::Null));
+        assert_eq!(
+            value.resolve_with_writer(&writer, &reader).unwrap(),
+            Value::Union(3, Box::new(Value::Null))
+        );
+
+        // A named type missing from the reader union cannot be read.
+        let value = Value::Record(vec![("x".to_string(), Value::Int(1))]);
+        let writer = Schema::parse_str(
+            r#"{"type": "record", "name": "c", "fields": [{"name": "x", "type": "int"}]}"#,
+        ).unwrap();
+        assert!(value.resolve_with_writer(&writer, &reader).is_err());
+    }
+
+    #[test]
+    fn resolve_renamed_fields() {
+        let writer = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "r",
+                "fields": [
+                    {"name": "a", "type": "int"},
+                    {"name": "old", "type": "string"}
+                ]
+            }
+            "#,
+        ).unwrap();
+        let reader = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "r",
+                "fields": [
+                    {"name": "new", "type": "string", "aliases": ["old"]},
+                    {"name": "a", "type": "long"}
+                ]
+            }
+            "#,
+        ).unwrap();
+
+        let value = Value::Record(vec![
+            ("a".to_string(), Value::Int(1)),
+            ("old".to_string(), Value::String("foo".to_string())),
+        ]);
+        let expected = Value::Record(vec![
+            ("new".to_string(), Value::String("foo".to_string())),
+            ("a".to_string(), Value::Long(1)),
+        ]);
+        assert_eq!(
+            value.clone().resolve_with_writer(&writer, &reader).unwrap(),
+            expected
+        );
+        assert_eq!(value.resolve(&reader).unwrap(), expected);
+    }
+
+    #[test]
+    fn resolve_renamed_record_in_union() {
+        let writer = Schema::parse_str(
+            r#"
+            [
+                "null",
+                {
+                    "type": "record",
+                    "name": "old",
+                    "namespace": "ns",
+                    "fields": [
+                        {"name": "a", "type": "int"},
+                        {"name": "b", "type": {"type": "enum", "name": "e", "symbols": ["X", "Y"]}}
+                    ]
+                }
+            ]
+            "#,
+        ).unwrap();
+        let reader = Schema::parse_str(
+            r#"
+            [
+                "null",
+                {"type": "record", "name": "other", "fields": [{"name": "a", "type": "int"}]},
+                {
+                    "type": "record",
+                    "name": "new",
+                    "namespace": "ns",
+                    "aliases": ["old"],
+                    "fields": [
+                        {"name": "c", "type": "int", "aliases": ["a"]},
+                        {
+                            "name": "b",
+                            "type": {
+                                "type": "enum",
+                                "name": "e2",
+                                "aliases": ["ns.e"],
+                                "symbols": ["Y", "X"]
+                            }
+                        },
+                        {"name": "d", "type": "boolean", "default": true}
+                    ]
+                }
+            ]
+            "#,
+        ).unwrap();
+
+        let value = Value::Union(1, Box::new(Value::Record(vec![
+            ("a".to_string(), Value::Int(1)),
+            ("b".to_string(), Value::Enum(1, "Y".to_string())),
+        ])));
+        assert_eq!(
+            value.resolve_with_writer(&writer, &reader).unwrap(),
+            Value::Union(
+                2,
+                Box::new(Value::Record(vec![
+                    ("c".to_string(), Value::Int(1)),
+                    ("b".to_string(), Value::Enum(0, "Y".to_string())),
+                    ("d".to_string(), Value::Boolean(true)),
+                ]))
+            )
+        );
+
+        let value = Value::Union(0, Box::new(Value::Null));
+        assert_eq!(
+            value.resolve_with_writer(&writer, &reader).unwrap(),
+            Value::Union(0, Box::new(Value::Null))
+        );
+    }
+
+    #[test]
+    fn resolve_named_types_without_alias() {
+        let writer = Schema::parse_str(
+            r#"{"type": "record", "name": "a", "fields": [{"name": "x", "type": "int"}]}"#,
+        ).unwrap();
+        let reader = Schema::parse_str(
+            r#"{"type": "record", "name": "b", "fields": [{"name": "x", "type": "int"}]}"#,
+        ).unwrap();
+        let value = Value::Record(vec![("x".to_string(), Value::Int(1))]);
+        assert!(value.resolve_with_writer(&writer, &reader).is_err());
+
+        let writer = Schema::parse_str(r#"{"type": "fixed", "name": "a", "size": 1}"#).unwrap();
+        let reader = Schema::parse_str(r#"["null", {"type": "fixed", "name": "b", "size": 1}]"#)
+            .unwrap();
+        assert!(
+            Value::Fixed(1, vec![0])
+                .resolve_with_writer(&writer, &reader)
+                .is_err()
+        );
+    }
 }
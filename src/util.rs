@@ -39,6 +39,47 @@ impl DecodeError {
     }
 }
 
+/// Describes errors happened while encoding Avro data.
+#[derive(Fail, Debug, Clone, PartialEq)]
+#[fail(display = "Encoding error at {}: {}", path, message)]
+pub struct EncodeError {
+    path: String,
+    message: String,
+}
+
+impl EncodeError {
+    pub fn new<S>(msg: S) -> EncodeError
+    where
+        S: Into<String>,
+    {
+        EncodeError {
+            path: ".".to_owned(),
+            message: msg.into(),
+        }
+    }
+
+    /// Move the error into `segment` of its enclosing value: `.field` for a field of a record,
+    /// `[index]` for an item of an array and `{key}` for a value of a map.
+    pub(crate) fn within(mut self, segment: &str) -> EncodeError {
+        if self.path == "." {
+            self.path.clear();
+        }
+        self.path.insert_str(0, segment);
+        self
+    }
+
+    /// Where the value which cannot be encoded lies within the encoded value, in the format of
+    /// [`ValidationMismatch::path`](types/struct.ValidationMismatch.html#structfield.path).
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Why the value cannot be encoded.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
 pub trait MapHelper {
     fn string(&self, key: &str) -> Option<String>;
 
@@ -49,6 +90,8 @@ pub trait MapHelper {
     fn doc(&self) -> Option<String> {
         self.string("doc")
     }
+
+    fn aliases(&self) -> Option<Vec<String>>;
 }
 
 impl MapHelper for Map<String, Value> {
@@ -57,6 +100,18 @@ impl MapHelper for Map<String, Value> {
             .and_then(|v| v.as_str())
             .map(|v| v.to_string())
     }
+
+    fn aliases(&self) -> Option<Vec<String>> {
+        self.get("aliases")
+            .and_then(|aliases| aliases.as_array())
+            .and_then(|aliases| {
+                aliases
+                    .iter()
+                    .map(|alias| alias.as_str())
+                    .map(|alias| alias.map(|a| a.to_string()))
+                    .collect::<Option<_>>()
+            })
+    }
 }
 
 pub fn read_long<R: Read>(reader: &mut R) -> Result<i64, Error> {
@@ -152,6 +207,55 @@ pub fn safe_len(len: usize) -> Result<usize, Error> {
     }
 }
 
+/// Parse a UUID from its canonical, 36 characters long, textual representation
+/// (`xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`).
+pub fn parse_uuid(s: &str) -> Option<[u8; 16]> {
+    if s.len() != 36 {
+        return None
+    }
+
+    let mut digits = Vec::with_capacity(32);
+    for (i, c) in s.bytes().enumerate() {
+        if UUID_HYPHENS.contains(&i) {
+            if c != b'-' {
+                return None
+            }
+        } else {
+            digits.push(hex_digit(c)?);
+        }
+    }
+
+    let mut bytes = [0u8; 16];
+    for (byte, pair) in bytes.iter_mut().zip(digits.chunks(2)) {
+        *byte = pair[0] << 4 | pair[1];
+    }
+
+    Some(bytes)
+}
+
+/// Format a UUID using its canonical, lowercase, textual representation.
+pub fn format_uuid(bytes: &[u8; 16]) -> String {
+    let mut s = String::with_capacity(36);
+    for (i, byte) in bytes.iter().enumerate() {
+        if i == 4 || i == 6 || i == 8 || i == 10 {
+            s.push('-');
+        }
+        s.push_str(&format!("{:02x}", byte));
+    }
+    s
+}
+
+const UUID_HYPHENS: [usize; 4] = [8, 13, 18, 23];
+
+fn hex_digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -222,4 +326,25 @@ mod tests {
         assert_eq!(42usize, safe_len(42usize).unwrap());
         assert!(safe_len(1024 * 1024 * 1024).is_err());
     }
+
+    #[test]
+    fn test_uuid() {
+        let bytes = [
+            0x55, 0x0e, 0x84, 0x00, 0xe2, 0x9b, 0x41, 0xd4, 0xa7, 0x16, 0x44, 0x66, 0x55, 0x44,
+            0x00, 0x00,
+        ];
+        assert_eq!(format_uuid(&bytes), "550e8400-e29b-41d4-a716-446655440000");
+        assert_eq!(
+            parse_uuid("550e8400-e29b-41d4-a716-446655440000"),
+            Some(bytes)
+        );
+        assert_eq!(
+            parse_uuid("550E8400-E29B-41D4-A716-446655440000"),
+            Some(bytes)
+        );
+        assert_eq!(parse_uuid("550e8400e29b41d4a716446655440000"), None);
+        assert_eq!(parse_uuid("550e8400-e29b-41d4-a716-44665544000g"), None);
+        assert_eq!(parse_uuid("550e8400-e29b-41d4-a716+446655440000"), None);
+        assert_eq!(parse_uuid("{550e8400-e29b-41d4-a716-446655440000}"), None);
+    }
 }
@@ -7,10 +7,10 @@ use rand::random;
 use serde::Serialize;
 use serde_json;
 
-use encode::{encode, encode_ref, encode_to_vec};
-use schema::Schema;
-use ser::Serializer;
-use types::{ToAvro, Value};
+use encode::{encode, encode_internal, encode_to_vec};
+use schema::{Names, Schema};
+use ser_schema::SchemaSerializer;
+use types::{ToAvro, ValidationMismatch, Value};
 use Codec;
 
 const SYNC_SIZE: usize = 16;
@@ -20,28 +20,53 @@ const AVRO_OBJECT_HEADER: &[u8] = &[b'O', b'b', b'j', 1u8];
 
 /// Describes errors happened while validating Avro data.
 #[derive(Fail, Debug)]
-#[fail(display = "Decoding error: {}", _0)]
-pub struct ValidationError(String);
+#[fail(display = "Decoding error: {}", message)]
+pub struct ValidationError {
+    message: String,
+    mismatches: Vec<ValidationMismatch>,
+}
 
 impl ValidationError {
     pub fn new<S>(msg: S) -> ValidationError
     where
         S: Into<String>,
     {
-        ValidationError(msg.into())
+        ValidationError {
+            message: msg.into(),
+            mismatches: Vec::new(),
+        }
+    }
+
+    /// Create a `ValidationError` listing the parts of a value which do not match its schema.
+    pub fn from_mismatches(mismatches: Vec<ValidationMismatch>) -> ValidationError {
+        let message = mismatches
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ");
+        ValidationError {
+            message: format!("value does not match schema: {}", message),
+            mismatches,
+        }
+    }
+
+    /// Returns the parts of the value which do not match the schema, if they are known.
+    pub fn mismatches(&self) -> &[ValidationMismatch] {
+        &self.mismatches
     }
 }
 
 /// Main interface for writing Avro formatted values.
 pub struct Writer<'a, W> {
     schema: &'a Schema,
-    serializer: Serializer,
+    names: Names<'a>,
     writer: W,
     buffer: Vec<u8>,
     num_values: usize,
     codec: Codec,
     marker: Vec<u8>,
     has_header: bool,
+    sized_blocks: bool,
 }
 
 impl<'a, W: Write> Writer<'a, W> {
@@ -62,13 +87,14 @@ impl<'a, W: Write> Writer<'a, W> {
 
         Writer {
             schema,
-            serializer: Serializer::default(),
+            names: schema.names(),
             writer,
             buffer: Vec::with_capacity(SYNC_INTERVAL),
             num_values: 0,
             codec,
             marker,
             has_header: false,
+            sized_blocks: false,
         }
     }
 
@@ -77,6 +103,12 @@ impl<'a, W: Write> Writer<'a, W> {
         self.schema
     }
 
+    /// Set whether arrays and maps are written with the size of their items in bytes, allowing
+    /// readers to skip them without decoding their items. They are not by default.
+    pub fn set_sized_blocks(&mut self, sized_blocks: bool) {
+        self.sized_blocks = sized_blocks;
+    }
+
     /// Append a compatible value (implementing the `ToAvro` trait) to a `Writer`, also performing
     /// schema validation.
     ///
@@ -95,8 +127,14 @@ impl<'a, W: Write> Writer<'a, W> {
             0
         };
 
-        let avro = value.avro();
-        write_value_ref(self.schema, &avro, &mut self.buffer)?;
+        let avro = value.avro_with_schema(self.schema);
+        write_value_ref(
+            self.schema,
+            &self.names,
+            &avro,
+            self.sized_blocks,
+            &mut self.buffer,
+        )?;
 
         self.num_values += 1;
 
@@ -124,7 +162,13 @@ impl<'a, W: Write> Writer<'a, W> {
             0
         };
 
-        write_value_ref(self.schema, value, &mut self.buffer)?;
+        write_value_ref(
+            self.schema,
+            &self.names,
+            value,
+            self.sized_blocks,
+            &mut self.buffer,
+        )?;
 
         self.num_values += 1;
 
@@ -145,8 +189,30 @@ impl<'a, W: Write> Writer<'a, W> {
     /// internal buffering for performance reasons. If you want to be sure the value has been
     /// written, then call [`flush`](struct.Writer.html#method.flush).
     pub fn append_ser<S: Serialize>(&mut self, value: S) -> Result<usize, Error> {
-        let avro_value = value.serialize(&mut self.serializer)?;
-        self.append(avro_value)
+        let n = if !self.has_header {
+            let header = self.header()?;
+            let n = self.append_bytes(header.as_ref())?;
+            self.has_header = true;
+            n
+        } else {
+            0
+        };
+
+        let len = self.buffer.len();
+        let serializer =
+            SchemaSerializer::new(self.schema, &self.names, self.sized_blocks, &mut self.buffer);
+        if let Err(e) = value.serialize(serializer) {
+            self.buffer.truncate(len);
+            return Err(e.into())
+        }
+
+        self.num_values += 1;
+
+        if self.buffer.len() >= SYNC_INTERVAL {
+            return self.flush().map(|b| b + n)
+        }
+
+        Ok(n)
     }
 
     /// Extend a `Writer` with an `Iterator` of compatible values (implementing the `ToAvro`
@@ -277,7 +343,7 @@ impl<'a, W: Write> Writer<'a, W> {
 
     /// Append a raw Avro Value to the payload avoiding to encode it again.
     fn append_raw(&mut self, value: &Value, schema: &Schema) -> Result<usize, Error> {
-        self.append_bytes(encode_to_vec(&value, schema).as_ref())
+        self.append_bytes(encode_to_vec(&value, schema)?.as_ref())
     }
 
     /// Append pure bytes to the payload.
@@ -299,7 +365,7 @@ impl<'a, W: Write> Writer<'a, W> {
             &metadata.avro(),
             &Schema::Map(Box::new(Schema::Bytes)),
             &mut header,
-        );
+        )?;
         header.extend_from_slice(&self.marker);
 
         Ok(header)
@@ -316,20 +382,25 @@ fn write_avro_datum<T: ToAvro>(
     value: T,
     buffer: &mut Vec<u8>,
 ) -> Result<(), Error> {
-    let avro = value.avro();
-    if !avro.validate(schema) {
-        return Err(ValidationError::new("value does not match schema").into())
-    }
-    encode(&avro, schema, buffer);
-    Ok(())
+    let avro = value.avro_with_schema(schema);
+    write_value_ref(schema, &schema.names(), &avro, false, buffer)
 }
 
-fn write_value_ref(schema: &Schema, value: &Value, buffer: &mut Vec<u8>) -> Result<(), Error> {
-    if !value.validate(schema) {
-        return Err(ValidationError::new("value does not match schema").into())
+fn write_value_ref(
+    schema: &Schema,
+    names: &Names,
+    value: &Value,
+    sized_blocks: bool,
+    buffer: &mut Vec<u8>,
+) -> Result<(), Error> {
+    if let Err(mismatches) = value.validate_detailed_internal(schema, names) {
+        return Err(ValidationError::from_mismatches(mismatches).into())
     }
-    encode_ref(value, schema, buffer);
-    Ok(())
+    let len = buffer.len();
+    encode_internal(value, schema, names, sized_blocks, buffer).map_err(|e| {
+        buffer.truncate(len);
+        e.into()
+    })
 }
 
 /// Encode a compatible value (implementing the `ToAvro` trait) into Avro format, also
@@ -337,7 +408,8 @@ fn write_value_ref(schema: &Schema, value: &Value, buffer: &mut Vec<u8>) -> Resu
 ///
 /// **NOTE** This function has a quite small niche of usage and does NOT generate headers and sync
 /// markers; use [`Writer`](struct.Writer.html) to be fully Avro-compatible if you don't know what
-/// you are doing, instead.
+/// you are doing, instead. A `Writer` also collects the named types of the `Schema` once, while
+/// this function collects them on every call.
 pub fn to_avro_datum<T: ToAvro>(schema: &Schema, value: T) -> Result<Vec<u8>, Error> {
     let mut buffer = Vec::new();
     write_avro_datum(schema, value, &mut buffer)?;
@@ -347,6 +419,8 @@ pub fn to_avro_datum<T: ToAvro>(schema: &Schema, value: T) -> Result<Vec<u8>, Er
 #[cfg(test)]
 mod tests {
     use super::*;
+    use reader::Reader;
+    use ser::to_value_with_schema;
     use types::Record;
     use util::zig_i64;
 
@@ -379,16 +453,131 @@ mod tests {
         assert_eq!(to_avro_datum(&schema, record).unwrap(), expected);
     }
 
+    #[test]
+    fn test_validation_error() {
+        let schema = Schema::parse_str(SCHEMA).unwrap();
+        let mut writer = Writer::new(&schema, Vec::new());
+        let mut record = Record::new(&schema).unwrap();
+        record.put("a", 27i64);
+        record.put("b", 42i64);
+
+        let error = writer.append(record).unwrap_err();
+        let error = error.downcast_ref::<ValidationError>().unwrap();
+        assert_eq!(
+            error.mismatches(),
+            &[ValidationMismatch {
+                path: ".b".to_owned(),
+                expected: "string".to_owned(),
+                actual: "long".to_owned(),
+                detail: None,
+            }]
+        );
+        assert_eq!(
+            error.to_string(),
+            "Decoding error: value does not match schema: .b: expected string, found long"
+        );
+    }
+
     #[test]
     fn test_union() {
         let schema = Schema::parse_str(UNION_SCHEMA).unwrap();
-        let union = Value::Union(Box::new(Value::Long(3)));
+        let union = Value::Union(1, Box::new(Value::Long(3)));
+
+        let mut expected = Vec::new();
+        zig_i64(1, &mut expected);
+        zig_i64(3, &mut expected);
+
+        assert_eq!(to_avro_datum(&schema, union).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_union_index() {
+        let schema = Schema::parse_str(r#"["long", "int", "null", "double"]"#).unwrap();
 
         let mut expected = Vec::new();
         zig_i64(1, &mut expected);
         zig_i64(3, &mut expected);
 
+        let union = Value::Union(1, Box::new(Value::Int(3)));
         assert_eq!(to_avro_datum(&schema, union).unwrap(), expected);
+        let union = Value::Union(0, Box::new(Value::Int(3)));
+        assert!(to_avro_datum(&schema, union).is_err());
+    }
+
+    #[test]
+    fn test_union_of_records() {
+        let schema = Schema::parse_str(
+            r#"
+            [
+                "null",
+                {"type": "record", "name": "created", "fields": [{"name": "a", "type": "long"}]},
+                {"type": "record", "name": "cancelled", "fields": [{"name": "b", "type": "long"}]}
+            ]
+            "#,
+        ).unwrap();
+        let union = Value::Union(2, Box::new(Value::Record(vec![(
+            "b".to_string(),
+            Value::Long(3),
+        )])));
+
+        let mut expected = Vec::new();
+        zig_i64(2, &mut expected);
+        zig_i64(3, &mut expected);
+
+        assert_eq!(to_avro_datum(&schema, union).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_options_in_any_union() {
+        let cases = vec![
+            (r#"["string", "null"]"#, Some("foo"), 0),
+            (r#"["string", "null"]"#, None, 1),
+            (r#"["null", "int", "string"]"#, Some("foo"), 2),
+            (r#"["null", "int", "string"]"#, None, 0),
+        ];
+        for (schema, option, position) in cases {
+            let schema = Schema::parse_str(schema).unwrap();
+            let inner = option.map_or(Value::Null, |s| Value::String(s.to_owned()));
+            let union = Value::Union(position as u32, Box::new(inner));
+
+            let mut expected = Vec::new();
+            zig_i64(position, &mut expected);
+            if let Some(s) = option {
+                zig_i64(s.len() as i64, &mut expected);
+                expected.extend(s.as_bytes());
+            }
+
+            assert_eq!(to_value_with_schema(&schema, option).unwrap(), union);
+            assert_eq!(to_avro_datum(&schema, option).unwrap(), expected);
+
+            let mut writer = Writer::new(&schema, Vec::new());
+            writer.append(option).unwrap();
+            writer.flush().unwrap();
+            let result = writer.into_inner();
+            assert!(result.windows(expected.len()).any(|window| window == &expected[..]));
+
+            let values = Reader::new(&result[..])
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(values, vec![union]);
+        }
+
+        let schema = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "test",
+                "fields": [{"name": "a", "type": ["long", "null"]}]
+            }
+        "#,
+        ).unwrap();
+        let mut record = Record::new(&schema).unwrap();
+        record.put("a", None::<i64>);
+        assert_eq!(
+            record.fields,
+            vec![("a".to_owned(), Value::Union(1, Box::new(Value::Null)))]
+        );
     }
 
     #[test]
@@ -442,6 +631,70 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_writer_sized_blocks() {
+        let schema = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "test",
+                "fields": [
+                    {"name": "a", "type": {"type": "array", "items": "long"}},
+                    {"name": "b", "type": {"type": "map", "values": "string"}},
+                    {"name": "c", "type": "long"}
+                ]
+            }
+        "#,
+        ).unwrap();
+        let mut map = HashMap::new();
+        map.insert("k".to_owned(), Value::String("v".to_owned()));
+        let value = Value::Record(vec![
+            ("a".to_owned(), Value::Array(vec![Value::Long(1), Value::Long(2)])),
+            ("b".to_owned(), Value::Map(map)),
+            ("c".to_owned(), Value::Long(3)),
+        ]);
+
+        let mut writer = Writer::new(&schema, Vec::new());
+        writer.set_sized_blocks(true);
+        writer.append_value_ref(&value).unwrap();
+        writer.flush().unwrap();
+        let result = writer.into_inner();
+
+        let mut data = Vec::new();
+        zig_i64(-2, &mut data);
+        zig_i64(2, &mut data);
+        zig_i64(1, &mut data);
+        zig_i64(2, &mut data);
+        data.push(0u8);
+        zig_i64(-1, &mut data);
+        zig_i64(4, &mut data);
+        data.extend(vec![2u8, b'k', 2u8, b'v']);
+        data.push(0u8);
+        zig_i64(3, &mut data);
+        assert!(result.windows(data.len()).any(|window| window == &data[..]));
+
+        let values = Reader::new(&result[..])
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(values, vec![value]);
+
+        let reader_schema = Schema::parse_str(
+            r#"
+            {
+                "type": "record",
+                "name": "test",
+                "fields": [{"name": "c", "type": "long"}]
+            }
+        "#,
+        ).unwrap();
+        let values = Reader::with_schema(&reader_schema, &result[..])
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(values, vec![Value::Record(vec![("c".to_owned(), Value::Long(3))])]);
+    }
+
     #[test]
     fn test_writer_extend() {
         let schema = Schema::parse_str(SCHEMA).unwrap();